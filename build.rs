@@ -35,14 +35,14 @@ fn download_file(url: String, destination: &PathBuf, download_dir: &PathBuf) {
 fn download_node() -> PathBuf {
     #[cfg(target_os = "linux")]
     let target_os = "linux";
-    #[cfg(all(target_os = "macos"))]
+    #[cfg(target_os = "macos")]
     let target_os = "darwin";
 
-    #[cfg(all(target_arch = "arm"))]
+    #[cfg(target_arch = "arm")]
     let target_arch = "armv7l";
-    #[cfg(all(target_arch = "x86_64"))]
+    #[cfg(target_arch = "x86_64")]
     let target_arch = "x64";
-    #[cfg(all(target_arch = "aarch64"))]
+    #[cfg(target_arch = "aarch64")]
     let target_arch = "arm64";
 
     let download_url = format!(
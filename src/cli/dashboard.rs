@@ -1,27 +1,31 @@
 use std::collections::{HashMap, VecDeque};
-use std::io::{self, BufRead, BufReader, Seek, SeekFrom};
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
 use std::time::{Duration, Instant};
 
 use crossterm::event::{self, Event, KeyCode, KeyModifiers};
-use crossterm::terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode};
-use crossterm::{execute};
+use crossterm::execute;
+use crossterm::terminal::{
+    EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode,
+};
 use macros_rs::string;
 
-#[cfg(any(target_os = "linux", target_os = "macos"))]
-use pmc::process::{MemoryInfo, unix::NativeProcess as NativeProcess};
 #[cfg(any(target_os = "linux", target_os = "macos"))]
 use pmc::process::unix::{get_listening_ports, is_port_open};
 
 use pmc::helpers;
-use pmc::process::{Process, Runner, get_process_cpu_usage_percentage};
+use pmc::process::{
+    Process, Runner, get_process_cpu_usage_percentage, process_memory,
+    search::{self, Filters, Level},
+};
 
+use ratatui::Terminal;
 use ratatui::backend::CrosstermBackend;
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::symbols;
 use ratatui::text::{Line, Span};
 use ratatui::widgets::{Block, Borders, List, ListItem, Paragraph, Sparkline, Wrap};
-use ratatui::Terminal;
 
 const HISTORY_LEN: usize = 60;
 const MAX_LOG_LINES: usize = 500;
@@ -47,6 +51,10 @@ struct DashboardState {
     log_stream: LogStream,
     log_lines: Vec<String>,
     log_scroll: usize,
+    log_grep: String,
+    log_level: Option<Level>,
+    log_filter_input: Option<String>,
+    log_filter_error: Option<String>,
     initial_out_lines: Vec<String>,
     initial_err_lines: Vec<String>,
     cpu_history: HashMap<usize, VecDeque<u64>>,
@@ -64,6 +72,10 @@ impl DashboardState {
             log_stream: LogStream::Stdout,
             log_lines: Vec::new(),
             log_scroll: 0,
+            log_grep: String::new(),
+            log_level: None,
+            log_filter_input: None,
+            log_filter_error: None,
             initial_out_lines: Vec::new(),
             initial_err_lines: Vec::new(),
             cpu_history: HashMap::new(),
@@ -100,13 +112,11 @@ impl DashboardState {
             let mut cpu: f64 = 0.0;
             let mut mem: u64 = 0;
 
-            if proc.running {
-                if let Ok(native) = NativeProcess::new(proc.pid as u32) {
-                    cpu = get_process_cpu_usage_percentage(proc.pid);
-                    if let Ok(mi) = native.memory_info() {
-                        mem = MemoryInfo::from(mi).rss;
-                    }
-                }
+            if proc.running
+                && let Some(memory) = process_memory(proc.pid)
+            {
+                cpu = get_process_cpu_usage_percentage(proc.pid);
+                mem = memory.rss;
             }
 
             let cpu_buf = self.cpu_history.get_mut(id).unwrap();
@@ -137,10 +147,28 @@ impl DashboardState {
         };
 
         self.log_lines.clear();
+        self.log_filter_error = None;
+
+        // a filtered view searches the rotated files too
+        if self.filtered() {
+            let filters = Filters {
+                grep: (!self.log_grep.is_empty()).then(|| self.log_grep.clone()),
+                level: self.log_level.map(|level| level.to_string()),
+                ..Filters::default()
+            };
+
+            match filters.compile() {
+                Ok(query) => {
+                    self.log_lines = search::search(Path::new(path), &query, MAX_LOG_LINES).lines
+                }
+                Err(err) => self.log_filter_error = Some(err),
+            }
+            return;
+        }
 
         if let Ok(file) = std::fs::File::open(path) {
             let reader = BufReader::new(file);
-            let all_lines: Vec<String> = reader.lines().filter_map(|l| l.ok()).collect();
+            let all_lines: Vec<String> = reader.lines().map_while(Result::ok).collect();
             let start = if all_lines.len() > MAX_LOG_LINES {
                 all_lines.len() - MAX_LOG_LINES
             } else {
@@ -150,6 +178,41 @@ impl DashboardState {
         }
     }
 
+    fn filtered(&self) -> bool {
+        !self.log_grep.is_empty() || self.log_level.is_some()
+    }
+
+    /// Cycle the minimum level of the shown lines through none, info, warn and error
+    fn cycle_log_level(&mut self) {
+        self.log_level = match self.log_level {
+            None => Some(Level::Info),
+            Some(Level::Info) => Some(Level::Warn),
+            Some(Level::Warn) => Some(Level::Error),
+            _ => None,
+        };
+        self.log_scroll = 0;
+        self.refresh_logs();
+    }
+
+    /// Handle a key while the filter regex is typed
+    fn edit_log_filter(&mut self, code: KeyCode) {
+        let Some(input) = &mut self.log_filter_input else {
+            return;
+        };
+
+        match code {
+            KeyCode::Char(c) => input.push(c),
+            KeyCode::Backspace => drop(input.pop()),
+            KeyCode::Enter => {
+                self.log_grep = self.log_filter_input.take().unwrap_or_default();
+                self.log_scroll = 0;
+                self.refresh_logs();
+            }
+            KeyCode::Esc => self.log_filter_input = None,
+            _ => {}
+        }
+    }
+
     fn refresh_initial_logs(&mut self) {
         self.initial_out_lines.clear();
         self.initial_err_lines.clear();
@@ -158,23 +221,10 @@ impl DashboardState {
             return;
         }
 
+        // the daemon captures the initial lines, they survive the log being rotated
         let (_, proc) = &self.processes[self.selected];
-        let logs = proc.logs();
-        let initial = &proc.initial_logs;
-
-        if let Ok(mut f) = std::fs::File::open(&logs.out) {
-            if f.seek(SeekFrom::Start(initial.start_pos_out)).is_ok() {
-                let reader = BufReader::new(f);
-                self.initial_out_lines = reader.lines().take(100).filter_map(|l| l.ok()).collect();
-            }
-        }
-
-        if let Ok(mut f) = std::fs::File::open(&logs.error) {
-            if f.seek(SeekFrom::Start(initial.start_pos_error)).is_ok() {
-                let reader = BufReader::new(f);
-                self.initial_err_lines = reader.lines().take(100).filter_map(|l| l.ok()).collect();
-            }
-        }
+        self.initial_out_lines = proc.initial_logs.out.clone();
+        self.initial_err_lines = proc.initial_logs.error.clone();
     }
 
     fn selected_id(&self) -> Option<usize> {
@@ -184,7 +234,7 @@ impl DashboardState {
     fn do_restart(&mut self) {
         if let Some(id) = self.selected_id() {
             let mut runner = Runner::new();
-            runner.restart(id, false);
+            let _ = runner.restart(id, false);
         }
     }
 
@@ -198,7 +248,7 @@ impl DashboardState {
     fn do_start(&mut self) {
         if let Some(id) = self.selected_id() {
             let mut runner = Runner::new();
-            runner.restart(id, false);
+            let _ = runner.restart(id, false);
         }
     }
 
@@ -230,79 +280,84 @@ pub fn run() {
 
         let timeout = TICK_RATE.saturating_sub(last_tick.elapsed());
 
-        if event::poll(timeout).unwrap_or(false) {
-            if let Ok(Event::Key(key)) = event::read() {
-                match key.code {
-                    KeyCode::Char('q') | KeyCode::Esc => {
-                        state.should_quit = true;
-                    }
-                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                        state.should_quit = true;
-                    }
-                    KeyCode::Up | KeyCode::Char('k') => {
-                        if state.selected > 0 {
-                            state.selected -= 1;
-                            state.log_scroll = 0;
-                            state.refresh_logs();
-                            state.refresh_initial_logs();
-                        }
-                    }
-                    KeyCode::Down | KeyCode::Char('j') => {
-                        if state.selected + 1 < state.processes.len() {
-                            state.selected += 1;
-                            state.log_scroll = 0;
-                            state.refresh_logs();
-                            state.refresh_initial_logs();
-                        }
-                    }
-                    KeyCode::Tab => {
-                        state.tab = match state.tab {
-                            Tab::Overview => Tab::Logs,
-                            Tab::Logs => Tab::InitialLogs,
-                            Tab::InitialLogs => Tab::Overview,
-                        };
-                        state.log_scroll = 0;
-                        if state.tab == Tab::Logs {
-                            state.refresh_logs();
-                        }
-                        if state.tab == Tab::InitialLogs {
-                            state.refresh_initial_logs();
-                        }
-                    }
-                    KeyCode::Char('1') => {
-                        if state.tab == Tab::Logs {
-                            state.log_stream = LogStream::Stdout;
-                            state.log_scroll = 0;
-                            state.refresh_logs();
-                        }
-                    }
-                    KeyCode::Char('2') => {
-                        if state.tab == Tab::Logs {
-                            state.log_stream = LogStream::Stderr;
-                            state.log_scroll = 0;
-                            state.refresh_logs();
-                        }
-                    }
-                    KeyCode::PageUp => {
-                        state.log_scroll = state.log_scroll.saturating_add(10);
-                    }
-                    KeyCode::PageDown => {
-                        state.log_scroll = state.log_scroll.saturating_sub(10);
-                    }
-                    KeyCode::Char('r') => {
-                        state.do_restart();
-                    }
-                    KeyCode::Char('s') => {
-                        state.do_stop();
-                    }
-                    KeyCode::Char('S') => {
-                        state.do_start();
+        if event::poll(timeout).unwrap_or(false)
+            && let Ok(Event::Key(key)) = event::read()
+        {
+            if state.log_filter_input.is_some() && !key.modifiers.contains(KeyModifiers::CONTROL) {
+                state.edit_log_filter(key.code);
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => {
+                    state.should_quit = true;
+                }
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    state.should_quit = true;
+                }
+                KeyCode::Up | KeyCode::Char('k') if state.selected > 0 => {
+                    state.selected -= 1;
+                    state.log_scroll = 0;
+                    state.refresh_logs();
+                    state.refresh_initial_logs();
+                }
+                KeyCode::Down | KeyCode::Char('j')
+                    if state.selected + 1 < state.processes.len() =>
+                {
+                    state.selected += 1;
+                    state.log_scroll = 0;
+                    state.refresh_logs();
+                    state.refresh_initial_logs();
+                }
+                KeyCode::Tab => {
+                    state.tab = match state.tab {
+                        Tab::Overview => Tab::Logs,
+                        Tab::Logs => Tab::InitialLogs,
+                        Tab::InitialLogs => Tab::Overview,
+                    };
+                    state.log_scroll = 0;
+                    if state.tab == Tab::Logs {
+                        state.refresh_logs();
                     }
-                    KeyCode::Char('f') => {
-                        state.do_flush();
+                    if state.tab == Tab::InitialLogs {
+                        state.refresh_initial_logs();
                     }
-                    _ => {}
                 }
+                KeyCode::Char('1') if state.tab == Tab::Logs => {
+                    state.log_stream = LogStream::Stdout;
+                    state.log_scroll = 0;
+                    state.refresh_logs();
+                }
+                KeyCode::Char('2') if state.tab == Tab::Logs => {
+                    state.log_stream = LogStream::Stderr;
+                    state.log_scroll = 0;
+                    state.refresh_logs();
+                }
+                KeyCode::Char('/') if state.tab == Tab::Logs => {
+                    state.log_filter_input = Some(state.log_grep.clone());
+                }
+                KeyCode::Char('l') if state.tab == Tab::Logs => {
+                    state.cycle_log_level();
+                }
+                KeyCode::PageUp => {
+                    state.log_scroll = state.log_scroll.saturating_add(10);
+                }
+                KeyCode::PageDown => {
+                    state.log_scroll = state.log_scroll.saturating_sub(10);
+                }
+                KeyCode::Char('r') => {
+                    state.do_restart();
+                }
+                KeyCode::Char('s') => {
+                    state.do_stop();
+                }
+                KeyCode::Char('S') => {
+                    state.do_start();
+                }
+                KeyCode::Char('f') => {
+                    state.do_flush();
+                }
+                _ => {}
             }
         }
 
@@ -363,12 +418,10 @@ fn draw_process_list(f: &mut ratatui::Frame, state: &DashboardState, area: Rect)
         .iter()
         .enumerate()
         .map(|(i, (id, proc))| {
-            let status = if proc.running {
-                Span::styled("online ", Style::default().fg(Color::Green))
-            } else if proc.crash.crashed {
-                Span::styled("crashed", Style::default().fg(Color::Red))
-            } else {
-                Span::styled("stopped", Style::default().fg(Color::Red))
+            let status = match proc.status().as_str() {
+                "online" => Span::styled("online ", Style::default().fg(Color::Green)),
+                "exited(0)" => Span::styled("exited(0)", Style::default().fg(Color::Yellow)),
+                status => Span::styled(status.to_string(), Style::default().fg(Color::Red)),
             };
 
             let prefix = if i == state.selected { "> " } else { "  " };
@@ -403,7 +456,7 @@ fn draw_process_list(f: &mut ratatui::Frame, state: &DashboardState, area: Rect)
             let mut line_spans = vec![
                 Span::styled(format!("{prefix}[{id}] "), style),
                 Span::styled(
-                    format!("{:<15} ", truncate_str(&proc.name, 15)),
+                    format!("{:<15} ", truncate_str(&proc.display_name(), 15)),
                     style,
                 ),
                 status,
@@ -455,7 +508,7 @@ fn draw_overview(f: &mut ratatui::Frame, state: &DashboardState, area: Rect) {
         .unwrap_or_default();
 
     let cpu_block = Block::default()
-        .title(format!(" CPU % — {} ", proc.name))
+        .title(format!(" CPU % — {} ", proc.display_name()))
         .borders(Borders::ALL)
         .border_style(Style::default().fg(Color::Green));
 
@@ -478,7 +531,7 @@ fn draw_overview(f: &mut ratatui::Frame, state: &DashboardState, area: Rect) {
     let mem_max = mem_data.iter().copied().max().unwrap_or(1024).max(1024);
 
     let mem_block = Block::default()
-        .title(format!(" Memory — {} ", proc.name))
+        .title(format!(" Memory — {} ", proc.display_name()))
         .borders(Borders::ALL)
         .border_style(Style::default().fg(Color::Magenta));
 
@@ -504,31 +557,19 @@ fn draw_overview(f: &mut ratatui::Frame, state: &DashboardState, area: Rect) {
         string!("none")
     };
 
-    let status_str = if proc.running {
-        "online"
-    } else if proc.crash.crashed {
-        "crashed"
-    } else {
-        "stopped"
-    };
+    let status_str = proc.status();
 
     let mut cpu_val = string!("0.00%");
     let mut mem_val = string!("0b");
 
-    if proc.running {
-        if let Ok(native) = NativeProcess::new(proc.pid as u32) {
-            cpu_val = format!("{:.2}%", get_process_cpu_usage_percentage(proc.pid));
-            if let Ok(mi) = native.memory_info() {
-                mem_val = helpers::format_memory(MemoryInfo::from(mi).rss);
-            }
-        }
+    if proc.running
+        && let Some(memory) = process_memory(proc.pid)
+    {
+        cpu_val = format!("{:.2}%", get_process_cpu_usage_percentage(proc.pid));
+        mem_val = helpers::format_memory(memory.rss);
     }
 
-    let ports: Vec<u16> = state
-        .port_map
-        .get(&proc.pid)
-        .cloned()
-        .unwrap_or_default();
+    let ports: Vec<u16> = state.port_map.get(&proc.pid).cloned().unwrap_or_default();
 
     let mut ports_spans = vec![Span::styled("Ports: ", Style::default().fg(Color::Cyan))];
     if !proc.running || ports.is_empty() {
@@ -547,11 +588,11 @@ fn draw_overview(f: &mut ratatui::Frame, state: &DashboardState, area: Rect) {
         Line::from(vec![
             Span::styled("Status: ", Style::default().fg(Color::Cyan)),
             Span::styled(
-                status_str,
-                Style::default().fg(if proc.running {
-                    Color::Green
-                } else {
-                    Color::Red
+                status_str.clone(),
+                Style::default().fg(match status_str.as_str() {
+                    "online" => Color::Green,
+                    "exited(0)" => Color::Yellow,
+                    _ => Color::Red,
                 }),
             ),
             Span::raw("  "),
@@ -570,6 +611,9 @@ fn draw_overview(f: &mut ratatui::Frame, state: &DashboardState, area: Rect) {
             Span::raw("  "),
             Span::styled("Restarts: ", Style::default().fg(Color::Cyan)),
             Span::raw(format!("{}", proc.restarts)),
+            Span::raw("  "),
+            Span::styled("Last exit: ", Style::default().fg(Color::Cyan)),
+            Span::raw(proc.last_exit()),
         ]),
         Line::from(ports_spans),
         Line::from(vec![
@@ -583,7 +627,7 @@ fn draw_overview(f: &mut ratatui::Frame, state: &DashboardState, area: Rect) {
     ];
 
     let info_block = Block::default()
-        .title(format!(" Info — [{}] {} ", id, proc.name))
+        .title(format!(" Info — [{}] {} ", id, proc.display_name()))
         .borders(Borders::ALL)
         .border_style(Style::default().fg(Color::Yellow));
 
@@ -597,12 +641,25 @@ fn draw_logs(f: &mut ratatui::Frame, state: &DashboardState, area: Rect) {
         LogStream::Stderr => "stderr",
     };
 
-    let title = if let Some((id, proc)) = state.processes.get(state.selected) {
-        format!(" Logs ({stream_name}) — [{}] {} ", id, proc.name)
+    let filter = [
+        (!state.log_grep.is_empty()).then(|| format!("/{}/", state.log_grep)),
+        state.log_level.map(|level| format!("{level}+")),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<String>>()
+    .join(" ");
+
+    let mut title = if let Some((id, proc)) = state.processes.get(state.selected) {
+        format!(" Logs ({stream_name}) — [{}] {} ", id, proc.display_name())
     } else {
         format!(" Logs ({stream_name}) ")
     };
 
+    if !filter.is_empty() {
+        title.push_str(&format!("— filter {filter} "));
+    }
+
     let block = Block::default()
         .title(title)
         .borders(Borders::ALL)
@@ -611,8 +668,20 @@ fn draw_logs(f: &mut ratatui::Frame, state: &DashboardState, area: Rect) {
             LogStream::Stderr => Color::Red,
         }));
 
+    if let Some(err) = &state.log_filter_error {
+        let p = Paragraph::new(err.as_str())
+            .style(Style::default().fg(Color::Red))
+            .block(block);
+        f.render_widget(p, area);
+        return;
+    }
+
     if state.log_lines.is_empty() {
-        let p = Paragraph::new("No logs available").block(block);
+        let message = match state.filtered() {
+            true => "No matching lines",
+            false => "No logs available",
+        };
+        let p = Paragraph::new(message).block(block);
         f.render_widget(p, area);
         return;
     }
@@ -620,11 +689,7 @@ fn draw_logs(f: &mut ratatui::Frame, state: &DashboardState, area: Rect) {
     let inner_height = area.height.saturating_sub(2) as usize;
     let total = state.log_lines.len();
 
-    let max_scroll = if total > inner_height {
-        total - inner_height
-    } else {
-        0
-    };
+    let max_scroll = total.saturating_sub(inner_height);
 
     let scroll = state.log_scroll.min(max_scroll);
     let start = if total > inner_height {
@@ -653,7 +718,7 @@ fn draw_logs(f: &mut ratatui::Frame, state: &DashboardState, area: Rect) {
 
 fn draw_initial_logs(f: &mut ratatui::Frame, state: &DashboardState, area: Rect) {
     let title = if let Some((id, proc)) = state.processes.get(state.selected) {
-        format!(" Initial Logs — [{}] {} ", id, proc.name)
+        format!(" Initial Logs — [{}] {} ", id, proc.display_name())
     } else {
         String::from(" Initial Logs ")
     };
@@ -708,11 +773,7 @@ fn draw_initial_logs(f: &mut ratatui::Frame, state: &DashboardState, area: Rect)
     }
 
     let total = lines.len();
-    let max_scroll = if total > inner_height {
-        total - inner_height
-    } else {
-        0
-    };
+    let max_scroll = total.saturating_sub(inner_height);
 
     let scroll = state.log_scroll.min(max_scroll);
     let start = if total > inner_height {
@@ -729,52 +790,189 @@ fn draw_initial_logs(f: &mut ratatui::Frame, state: &DashboardState, area: Rect)
 
 fn draw_status_bar(f: &mut ratatui::Frame, state: &DashboardState, area: Rect) {
     let bar = match state.tab {
+        Tab::Logs if state.log_filter_input.is_some() => Line::from(vec![
+            Span::styled(
+                " filter /",
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(format!(
+                "{}_ ",
+                state.log_filter_input.as_deref().unwrap_or_default()
+            )),
+            Span::styled(
+                "[Enter]",
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" apply, empty clears "),
+            Span::styled(
+                "[Esc]",
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" cancel"),
+        ]),
         Tab::Overview => Line::from(vec![
-            Span::styled(" [r]", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::styled(
+                " [r]",
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
             Span::raw("estart "),
-            Span::styled("[s]", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::styled(
+                "[s]",
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
             Span::raw("top "),
-            Span::styled("[S]", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::styled(
+                "[S]",
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
             Span::raw("tart "),
-            Span::styled("[f]", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::styled(
+                "[f]",
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
             Span::raw("lush "),
-            Span::styled("[Tab]", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+            Span::styled(
+                "[Tab]",
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            ),
             Span::raw(" logs "),
-            Span::styled("[q]", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+            Span::styled(
+                "[q]",
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ),
             Span::raw("uit"),
         ]),
         Tab::Logs => Line::from(vec![
-            Span::styled(" [1]", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::styled(
+                " [1]",
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
             Span::raw(" stdout "),
-            Span::styled("[2]", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::styled(
+                "[2]",
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
             Span::raw(" stderr "),
-            Span::styled("[PgUp/PgDn]", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+            Span::styled(
+                "[/]",
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" filter "),
+            Span::styled(
+                "[l]",
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw("evel "),
+            Span::styled(
+                "[PgUp/PgDn]",
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            ),
             Span::raw(" scroll "),
-            Span::styled("[r]", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::styled(
+                "[r]",
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
             Span::raw("estart "),
-            Span::styled("[s]", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::styled(
+                "[s]",
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
             Span::raw("top "),
-            Span::styled("[f]", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::styled(
+                "[f]",
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
             Span::raw("lush "),
-            Span::styled("[Tab]", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+            Span::styled(
+                "[Tab]",
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            ),
             Span::raw(" initial-logs "),
-            Span::styled("[q]", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+            Span::styled(
+                "[q]",
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ),
             Span::raw("uit"),
         ]),
         Tab::InitialLogs => Line::from(vec![
-            Span::styled(" [PgUp/PgDn]", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+            Span::styled(
+                " [PgUp/PgDn]",
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            ),
             Span::raw(" scroll "),
-            Span::styled("[r]", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::styled(
+                "[r]",
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
             Span::raw("estart "),
-            Span::styled("[s]", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::styled(
+                "[s]",
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
             Span::raw("top "),
-            Span::styled("[S]", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::styled(
+                "[S]",
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
             Span::raw("tart "),
-            Span::styled("[f]", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::styled(
+                "[f]",
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
             Span::raw("lush "),
-            Span::styled("[Tab]", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+            Span::styled(
+                "[Tab]",
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            ),
             Span::raw(" overview "),
-            Span::styled("[q]", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+            Span::styled(
+                "[q]",
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ),
             Span::raw("uit"),
         ]),
     };
@@ -1,18 +1,20 @@
 use super::Item;
 use colored::Colorize;
+use hcl::Structure;
 use macros_rs::{crashln, string};
 use serde::{Deserialize, Serialize};
 
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet, HashMap},
     fs::{self, OpenOptions},
     io::prelude::*,
+    path::Path,
 };
 
 use pmc::{
-    file::Exists,
+    file::{self, Exists},
     helpers,
-    process::{Env, Runner},
+    process::{Env, Options, Runner, backoff::Backoff, dependency, instances::Instances, secrets},
 };
 
 #[derive(Deserialize, Debug)]
@@ -26,25 +28,121 @@ struct Process {
     script: String,
     server: Option<String>,
     watch: Option<Watch>,
+    instances: Option<Instances>,
     #[serde(default)]
     env: Env,
+    #[serde(flatten)]
+    options: Options,
+    #[serde(default)]
+    backoff: Backoff,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug)]
 struct Watch {
     path: String,
 }
 
+type Profiles = BTreeMap<String, Env>;
+
+/// Take the `env "<profile>" { ... }` blocks out of the process blocks, they would replace the plain `env` values
+fn take_env_profiles(body: &mut hcl::Body) -> Result<HashMap<String, Profiles>, hcl::Error> {
+    let mut profiles = HashMap::new();
+
+    for block in body
+        .blocks_mut()
+        .filter(|block| block.identifier() == "process")
+    {
+        let Some(name) = block
+            .labels()
+            .first()
+            .map(|label| label.as_str().to_string())
+        else {
+            continue;
+        };
+
+        let (named, rest): (Vec<Structure>, Vec<Structure>) = std::mem::take(&mut block.body.0)
+            .into_iter()
+            .partition(|structure| {
+                structure
+                    .as_block()
+                    .is_some_and(|env| env.identifier() == "env" && !env.labels().is_empty())
+            });
+        block.body.0 = rest;
+
+        let mut process_profiles = Profiles::new();
+        for env in named.into_iter().filter_map(Structure::into_block) {
+            let profile = env.labels()[0].as_str().to_string();
+            process_profiles.insert(profile, hcl::from_body(env.body)?);
+        }
+
+        profiles.insert(name, process_profiles);
+    }
+
+    Ok(profiles)
+}
+
 impl Process {
     fn get_watch_path(&self) -> Option<String> {
         self.watch.as_ref().map(|w| w.path.clone())
     }
+
+    /// Server the process runs on, exported files use an empty server for the local one
+    fn server_name(&self) -> String {
+        match self.server.as_deref() {
+            None | Some("") => string!("local"),
+            Some(server) => string!(server),
+        }
+    }
 }
 
-pub fn read_hcl(path: &String) {
-    let mut servers: Vec<String> = vec![];
+/// File formats processes are imported from and exported to, picked by the file extension
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Hcl,
+    Toml,
+    Json,
+    Yaml,
+}
 
-    println!("{} Applying action importProcess", *helpers::SUCCESS);
+impl Format {
+    fn of(path: &str) -> Format {
+        let extension = Path::new(path)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+
+        match extension.as_deref() {
+            Some("toml") => Format::Toml,
+            Some("json") => Format::Json,
+            Some("yaml" | "yml") => Format::Yaml,
+            _ => Format::Hcl,
+        }
+    }
+}
+
+/// Parse the `process` map of a TOML, JSON or YAML file, or the apps of a PM2 ecosystem file
+fn parse_document(format: Format, contents: &str) -> Result<ProcessWrapper, String> {
+    let document: serde_json::Value = match format {
+        Format::Toml => toml::from_str(contents).map_err(|err| err.to_string())?,
+        Format::Yaml => serde_yaml::from_str(contents).map_err(|err| err.to_string())?,
+        _ => serde_json::from_str(contents).map_err(|err| err.to_string())?,
+    };
+
+    let document = match super::pm2::is_ecosystem(&document) {
+        true => super::pm2::convert(document)?,
+        false => document,
+    };
+
+    serde_json::from_value(document).map_err(|err| err.to_string())
+}
+
+/// Read the processes of a file, with the env profile selected on the processes that define it
+fn parse_file(path: &String, env_profile: &Option<String>) -> BTreeMap<String, Process> {
+    if path.ends_with(".js") {
+        crashln!(
+            "{} Cannot import {path}, PM2 ecosystem files need to be written as JSON or YAML",
+            *helpers::FAIL
+        );
+    }
 
     let contents = match fs::read_to_string(path) {
         Ok(contents) => contents,
@@ -55,44 +153,106 @@ pub fn read_hcl(path: &String) {
         ),
     };
 
-    let hcl_parsed: ProcessWrapper = match hcl::from_str(&contents) {
-        Ok(hcl) => hcl,
+    let parsed = match Format::of(path) {
+        Format::Hcl => hcl::parse(&contents)
+            .and_then(|mut body| {
+                let profiles = take_env_profiles(&mut body)?;
+                hcl::from_body::<ProcessWrapper>(body).map(|wrapper| (wrapper, profiles))
+            })
+            .map_err(|err| err.to_string()),
+        format => parse_document(format, &contents).map(|wrapper| (wrapper, HashMap::new())),
+    };
+
+    let (wrapper, mut profiles) = match parsed {
+        Ok(parsed) => parsed,
         Err(err) => crashln!(
             "{} Cannot parse imported file.\n{}",
             *helpers::FAIL,
-            string!(err).white()
+            err.white()
         ),
     };
 
-    for (name, item) in hcl_parsed.list {
-        let mut runner = Runner::new();
-        let server_name = &item.server.clone().unwrap_or("local".into());
-        let (kind, list_name) = super::format(server_name);
-
-        runner = super::Internal {
-            id: 0,
-            server_name,
-            kind: kind.clone(),
-            runner: runner.clone(),
-        }
-        .create(
-            &item.script,
-            &Some(name.clone()),
-            &item.get_watch_path(),
-            true,
-        );
+    let mut items = wrapper
+        .list
+        .into_iter()
+        .map(|(name, mut item)| {
+            item.options
+                .env_profiles
+                .extend(profiles.remove(&name).unwrap_or_default());
+            (name, item)
+        })
+        .collect::<BTreeMap<String, Process>>();
 
-        println!("{} Imported {kind}process {name}", *helpers::SUCCESS);
+    if let Some(profile) = env_profile {
+        if !items
+            .values()
+            .any(|item| item.options.env_profiles.contains_key(profile))
+        {
+            crashln!(
+                "{} No process in {path} has an env \"{profile}\" profile",
+                *helpers::FAIL
+            );
+        }
+
+        // processes without the selected profile keep their plain env
+        items
+            .values_mut()
+            .filter(|item| item.options.env_profiles.contains_key(profile))
+            .for_each(|item| item.options.env_profile = Some(profile.clone()));
+    }
+
+    items
+}
+
+/// Create the process from an imported definition and start it with its env, returns the server list it belongs to
+fn import_process(name: &String, item: &Process) -> String {
+    let mut runner = Runner::new();
+    let server_name = &item.server_name();
+    let (kind, list_name) = super::format(server_name);
+
+    runner = super::Internal {
+        id: 0,
+        server_name,
+        kind: kind.clone(),
+        runner: runner.clone(),
+    }
+    .create(
+        &item.script,
+        &Some(name.clone()),
+        &item.get_watch_path(),
+        &item.instances.unwrap_or_default(),
+        &item.options,
+        &item.backoff,
+        true,
+    );
 
-        match runner.find(&name, server_name) {
-            Some(id) => {
-                let mut p = runner.get(id);
+    println!("{} Imported {kind}process {name}", *helpers::SUCCESS);
+
+    match runner.find_all(name, server_name) {
+        ids if !ids.is_empty() => {
+            for id in ids {
+                let mut p = runner.clone().get(id);
                 p.stop();
-                p.set_env(item.env);
-                p.restart();
+                p.set_env(item.env.clone());
+                if let Err(err) = p.restart() {
+                    println!("{} Failed to restart process {name}: {err}", *helpers::FAIL);
+                }
+                runner = p.get_runner().clone();
             }
-            None => crashln!("{} Failed to write to ({name})", *helpers::FAIL),
         }
+        _ => crashln!("{} Failed to write to ({name})", *helpers::FAIL),
+    }
+
+    list_name
+}
+
+pub fn read_file(path: &String, env_profile: &Option<String>) {
+    let mut servers: Vec<String> = vec![];
+
+    println!("{} Applying action importProcess", *helpers::SUCCESS);
+
+    for (name, item) in parse_file(path, env_profile) {
+        let list_name = import_process(&name, &item);
 
         if !servers.contains(&list_name) {
             servers.push(list_name);
@@ -108,73 +268,880 @@ pub fn read_hcl(path: &String) {
     );
 }
 
-pub fn export_hcl(item: &Item, path: &Option<String>) {
-    println!("{} Applying action exportProcess", *helpers::SUCCESS);
+/// What `pmc apply` does with the processes of one name
+enum Change {
+    Create(Process),
+    Update {
+        ids: Vec<usize>,
+        item: Process,
+        lines: Vec<String>,
+    },
+    Unchanged,
+    Remove(Vec<usize>),
+    /// Not in the file and kept because `--prune` was not given
+    Kept,
+}
+
+fn show_value(key: &str, value: &str, patterns: &[String]) -> String {
+    match secrets::is_secret(key, patterns) {
+        true => string!(secrets::MASKED),
+        false => format!("{value:?}"),
+    }
+}
+
+fn show_json(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null => string!("none"),
+        value => value.to_string(),
+    }
+}
+
+/// Changed variables of `desired`, removed ones only count when `strict` as the stored env also holds inherited values
+fn diff_env(
+    prefix: &str,
+    current: &Env,
+    desired: &Env,
+    strict: bool,
+    patterns: &[String],
+) -> Vec<String> {
+    let mut lines = vec![];
+
+    for (key, value) in desired {
+        if current.get(key) != Some(value) {
+            let old = current
+                .get(key)
+                .map_or(string!("none"), |old| show_value(key, old, patterns));
+            lines.push(format!(
+                "{prefix}{key}: {old} -> {}",
+                show_value(key, value, patterns)
+            ));
+        }
+    }
+
+    if strict {
+        for (key, value) in current
+            .iter()
+            .filter(|(key, _)| !desired.contains_key(*key))
+        {
+            lines.push(format!(
+                "{prefix}{key}: {} -> none",
+                show_value(key, value, patterns)
+            ));
+        }
+    }
+
+    lines
+}
 
+fn diff_options(current: &Options, desired: &Options, patterns: &[String]) -> Vec<String> {
+    let empty = Env::new();
+    let mut lines = vec![];
+
+    let profiles = current
+        .env_profiles
+        .keys()
+        .chain(desired.env_profiles.keys())
+        .collect::<BTreeSet<&String>>();
+
+    for profile in profiles {
+        lines.extend(diff_env(
+            &format!("env \"{profile}\" "),
+            current.env_profiles.get(profile).unwrap_or(&empty),
+            desired.env_profiles.get(profile).unwrap_or(&empty),
+            true,
+            patterns,
+        ));
+    }
+
+    let to_json = |options: &Options| {
+        serde_json::to_value(Options {
+            env_profiles: BTreeMap::new(),
+            ..options.clone()
+        })
+        .unwrap_or_default()
+    };
+
+    let (current, desired) = (to_json(current), to_json(desired));
+    if let (Some(current), Some(desired)) = (current.as_object(), desired.as_object()) {
+        for (key, value) in desired {
+            let old = current.get(key).unwrap_or(&serde_json::Value::Null);
+            if old != value {
+                lines.push(format!("{key}: {} -> {}", show_json(old), show_json(value)));
+            }
+        }
+    }
+
+    lines
+}
+
+/// Compare a process block against the running processes with its name
+fn plan_change(runner: &Runner, ids: Vec<usize>, mut item: Process, patterns: &[String]) -> Change {
+    let current = runner.try_info(ids[0]);
+    item.options = super::internal::script_options(&item.script, &item.options);
+    let mut lines = vec![];
+
+    if current.script != item.script {
+        lines.push(format!("script: {:?} -> {:?}", current.script, item.script));
+    }
+
+    let watch = current.watch.enabled.then(|| current.watch.path.clone());
+    if watch != item.get_watch_path() {
+        let show = |watch: &Option<String>| {
+            watch
+                .as_ref()
+                .map_or(string!("none"), |path| format!("{path:?}"))
+        };
+        lines.push(format!(
+            "watch: {} -> {}",
+            show(&watch),
+            show(&item.get_watch_path())
+        ));
+    }
+
+    let instances = item.instances.unwrap_or_default().count();
+    if ids.len() != instances {
+        lines.push(format!("instances: {} -> {instances}", ids.len()));
+    }
+
+    if current.crash.backoff != item.backoff {
+        lines.push(format!(
+            "backoff: {} -> {}",
+            current.crash.backoff, item.backoff
+        ));
+    }
+
+    lines.extend(diff_env("env ", &current.env, &item.env, false, patterns));
+    lines.extend(diff_options(&current.options, &item.options, patterns));
+
+    match lines.is_empty() {
+        true => Change::Unchanged,
+        false => Change::Update { ids, item, lines },
+    }
+}
+
+fn print_change(name: &str, change: &Change) {
+    match change {
+        Change::Create(item) => {
+            println!("{} {name} {}", "+".green(), "(create)".green());
+            println!("    {}", format!("script: {:?}", item.script).green());
+        }
+        Change::Update { lines, .. } => {
+            println!(
+                "{} {name} {}",
+                "~".yellow(),
+                "(update and restart)".yellow()
+            );
+            lines
+                .iter()
+                .for_each(|line| println!("    {}", line.yellow()));
+        }
+        Change::Remove(_) => println!("{} {name} {}", "-".red(), "(remove)".red()),
+        Change::Unchanged => println!("  {name} {}", "(unchanged)".bright_black()),
+        Change::Kept => println!(
+            "  {name} {}",
+            "(not in file, use --prune to remove)".bright_black()
+        ),
+    }
+}
+
+/// The changes `pmc apply` makes by process name, in the order they are made
+struct Plan {
+    changes: BTreeMap<String, Change>,
+    /// Removals first, then creates and updates with the processes they depend on first
+    order: Vec<String>,
+    /// Processes other processes depend on, they have to be ready before the next change
+    dependencies: BTreeSet<String>,
+}
+
+/// Plan how to reconcile the processes of `runner` with the processes of a file
+fn plan(
+    runner: &Runner,
+    mut items: BTreeMap<String, Process>,
+    prune: bool,
+    patterns: &[String],
+) -> Result<Plan, String> {
+    let mut groups: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (id, process) in runner.items() {
+        groups.entry(process.name.clone()).or_default().push(id);
+    }
+
+    // processes from the file and the ones that stay, pruned processes cannot be depended on
+    let mut nodes = items
+        .iter()
+        .map(|(name, item)| (name.clone(), name.clone(), item.options.depends_on.clone()))
+        .collect::<Vec<_>>();
+
+    if !prune {
+        for (name, ids) in groups.iter().filter(|(name, _)| !items.contains_key(*name)) {
+            let depends_on = runner.try_info(ids[0]).options.depends_on.clone();
+            nodes.push((name.clone(), name.clone(), depends_on));
+        }
+    }
+
+    let order = dependency::order(&nodes)?;
+
+    let dependencies = nodes
+        .iter()
+        .flat_map(|(_, _, depends_on)| depends_on.iter().cloned())
+        .collect::<BTreeSet<String>>();
+
+    let mut changes: BTreeMap<String, Change> = BTreeMap::new();
+    for (name, ids) in groups {
+        let change = match items.remove(&name) {
+            Some(item) => plan_change(runner, ids, item, patterns),
+            None if prune => Change::Remove(ids),
+            None => Change::Kept,
+        };
+        changes.insert(name, change);
+    }
+
+    for (name, item) in items {
+        changes.insert(name, Change::Create(item));
+    }
+
+    let removals = changes
+        .keys()
+        .filter(|name| !order.contains(name))
+        .cloned()
+        .collect::<Vec<String>>();
+
+    Ok(Plan {
+        order: removals.into_iter().chain(order).collect(),
+        changes,
+        dependencies,
+    })
+}
+
+/// Reconcile the local processes with a process file by process name
+pub fn apply_file(path: &String, env_profile: &Option<String>, prune: bool, dry_run: bool) {
+    println!("{} Applying action applyProcess", *helpers::SUCCESS);
+
+    let items = parse_file(path, env_profile);
     let runner = Runner::new();
 
-    let fetch_process = |id: usize| {
-        let process = runner.try_info(id);
-        let mut watch_parsed = None;
-        let mut env_parsed = HashMap::new();
+    if let Some((name, item)) = items
+        .iter()
+        .find(|(_, item)| !matches!(item.server_name().as_str(), "internal" | "local"))
+    {
+        crashln!(
+            "{} Cannot apply {name}, apply only manages local processes (server={})",
+            *helpers::FAIL,
+            item.server_name()
+        );
+    }
 
-        let current_env: HashMap<String, String> = std::env::vars().collect();
-        let path = path
-            .clone()
-            .unwrap_or(format!("{}.hcl", process.name.clone()));
+    let Plan {
+        mut changes,
+        order,
+        dependencies,
+    } = match plan(&runner, items, prune, &secrets::patterns()) {
+        Ok(plan) => plan,
+        Err(err) => crashln!("{} Cannot apply {path}, {err}", *helpers::FAIL),
+    };
 
-        if process.watch.enabled {
-            watch_parsed = Some(Watch {
-                path: process.watch.path.clone(),
-            })
+    // nothing is changed when one of the processes would be rejected
+    for (name, change) in &changes {
+        let (options, path) = match change {
+            Change::Create(item) => (&item.options, file::cwd()),
+            Change::Update { ids, item, .. } => {
+                (&item.options, runner.try_info(ids[0]).path.clone())
+            }
+            _ => continue,
+        };
+
+        if let Err(err) = options
+            .check()
+            .and_then(|_| options.check_env_profile())
+            .and_then(|_| options.check_log_sinks())
+            .and_then(|_| options.check_env_files(&path))
+        {
+            crashln!("{} Cannot apply {name}\nError: {err}", *helpers::FAIL);
+        }
+    }
+
+    changes
+        .iter()
+        .for_each(|(name, change)| print_change(name, change));
+
+    let count =
+        |matches: fn(&Change) -> bool| changes.values().filter(|change| matches(change)).count();
+    println!(
+        "\n{} {} to create, {} to update, {} to remove",
+        *helpers::SUCCESS,
+        count(|change| matches!(change, Change::Create(_))),
+        count(|change| matches!(change, Change::Update { .. })),
+        count(|change| matches!(change, Change::Remove(_))),
+    );
+
+    if dry_run {
+        println!("{}", "Dry run, no changes were made".bright_black());
+        return;
+    }
+
+    for name in order {
+        let Some(change) = changes.remove(&name) else {
+            continue;
+        };
+
+        let wait = dependencies.contains(&name)
+            && matches!(change, Change::Create(_) | Change::Update { .. });
+
+        match change {
+            Change::Create(item) => {
+                import_process(&name, &item);
+            }
+            Change::Update { ids, item, .. } => {
+                let mut runner = Runner::new();
+
+                for id in &ids {
+                    let process = runner.process(*id);
+                    process.script = item.script.clone();
+                    process.options = item.options.clone();
+                    process.crash.backoff = item.backoff;
+                    process.env.extend(item.env.clone());
+
+                    match item.get_watch_path() {
+                        Some(path) => runner.watch(*id, &path, true),
+                        None => runner.watch(*id, "", false),
+                    };
+
+                    if let Err(err) = runner.restart(*id, false) {
+                        println!("{} Failed to restart process {name}: {err}", *helpers::FAIL);
+                    }
+                }
+
+                let instances = item.instances.unwrap_or_default();
+                if ids.len() != instances.count()
+                    && let Err(err) = runner.scale(ids[0], &instances)
+                {
+                    println!("{} Failed to scale process {name}: {err}", *helpers::FAIL);
+                }
+
+                runner.save();
+                println!("{} Updated process {name}", *helpers::SUCCESS);
+            }
+            Change::Remove(ids) => {
+                let mut runner = Runner::new();
+                ids.into_iter().for_each(|id| runner.remove(id));
+                println!("{} Removed process {name}", *helpers::SUCCESS);
+            }
+            Change::Unchanged | Change::Kept => {}
         }
 
-        for (key, value) in process.env.clone() {
-            if let Some(current_value) = current_env.get(&key) {
-                if current_value != &value {
-                    env_parsed.insert(key, value);
+        if wait {
+            let runner = Runner::new();
+            for id in runner.find_all(&name, &string!("local")) {
+                if !runner.wait_ready(id) {
+                    println!(
+                        "{} Dependency {name} ({id}) did not become ready, applying the processes that depend on it anyway",
+                        *helpers::FAIL
+                    );
                 }
-            } else {
-                env_parsed.insert(key, value);
             }
         }
+    }
+
+    super::Internal::list(&string!("default"), &string!("local"));
+}
+
+/// A process as it is written to an export file, with the defaults and inherited variables left out
+struct Exported {
+    name: String,
+    script: String,
+    watch: Option<Watch>,
+    instances: Option<usize>,
+    env: Env,
+    options: Options,
+    backoff: Backoff,
+}
+
+impl Exported {
+    /// Collect a process for export, returns how many secret values it holds
+    fn new(runner: &Runner, id: usize, reveal: bool, patterns: &[String]) -> (Exported, usize) {
+        let process = runner.try_info(id);
+        let current_env: HashMap<String, String> = std::env::vars().collect();
+
+        let mut env = process
+            .env
+            .iter()
+            .filter(|(key, value)| current_env.get(*key) != Some(*value))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect::<Env>();
+
+        let mut options = process.options.clone();
+        let masked = env
+            .keys()
+            .chain(
+                options
+                    .env_profiles
+                    .values()
+                    .flat_map(|profile| profile.keys()),
+            )
+            .filter(|key| secrets::is_secret(key, patterns))
+            .count();
+
+        if !reveal {
+            secrets::mask(&mut env, patterns);
+            options
+                .env_profiles
+                .values_mut()
+                .for_each(|profile| secrets::mask(profile, patterns));
+        }
+
+        let instances = match runner
+            .list
+            .values()
+            .filter(|p| p.name == process.name)
+            .count()
+        {
+            1 => None,
+            count => Some(count),
+        };
+
+        let exported = Exported {
+            name: process.name.clone(),
+            script: process.script.clone(),
+            watch: process.watch.enabled.then(|| Watch {
+                path: process.watch.path.clone(),
+            }),
+            instances,
+            env,
+            options,
+            backoff: process.crash.backoff,
+        };
+
+        (exported, masked)
+    }
 
-        let data = hcl::block! {
-            process (process.name.clone()) {
-                script = (process.script.clone())
+    /// The process block, env profiles become `env "<profile>"` blocks
+    fn to_hcl(&self) -> hcl::Block {
+        // only export the options that differ from their defaults
+        let defaults = hcl::Body::from_serializable(&Options::default()).unwrap_or_default();
+        let options = Options {
+            env_profiles: BTreeMap::new(),
+            ..self.options.clone()
+        };
+        let options = hcl::Body::from_serializable(&options)
+            .unwrap_or_default()
+            .into_inner()
+            .into_iter()
+            .filter(|option| !defaults.iter().any(|default| default == option));
+
+        let mut data = hcl::block! {
+            process (self.name.clone()) {
+                script = (self.script.clone())
                 server = ("")
-                watch = (watch_parsed)
-                env = (env_parsed)
+                watch = (self.watch.clone())
+                instances = (self.instances)
+                env = (self.env.clone())
             }
         };
 
-        let serialized = hcl::to_string(&data).unwrap();
+        let env_index = data.body.0.len() - 1;
+        data.body.0.splice(env_index..env_index, options);
 
-        if Exists::check(&path).file() {
-            let mut file = OpenOptions::new().append(true).open(path.clone()).unwrap();
-            if let Err(err) = writeln!(file, "{}", serialized) {
-                crashln!(
-                    "{} Error writing to file.\n{}",
-                    *helpers::FAIL,
-                    string!(err).white()
+        if self.backoff != Backoff::default() {
+            let backoff = hcl::Body::from_serializable(&self.backoff).unwrap_or_default();
+            let block = hcl::Block::builder("backoff")
+                .add_structures(backoff.into_inner())
+                .build();
+            data.body.0.insert(data.body.0.len() - 1, block.into());
+        }
+
+        for (profile, env) in &self.options.env_profiles {
+            let block = hcl::Block::builder("env")
+                .add_label(profile.as_str())
+                .add_attributes(
+                    env.iter()
+                        .map(|(key, value)| hcl::Attribute::new(key.clone(), value.clone())),
                 )
+                .build();
+            data.body.0.push(block.into());
+        }
+
+        data
+    }
+
+    /// The process table for TOML, JSON and YAML files
+    fn to_value(&self) -> serde_json::Value {
+        let mut process = serde_json::Map::new();
+        process.insert(string!("script"), self.script.clone().into());
+
+        if let Some(watch) = &self.watch {
+            process.insert(string!("watch"), serde_json::json!({ "path": watch.path }));
+        }
+
+        if let Some(instances) = self.instances {
+            process.insert(string!("instances"), instances.into());
+        }
+
+        if !self.env.is_empty() {
+            process.insert(
+                string!("env"),
+                serde_json::to_value(&self.env).unwrap_or_default(),
+            );
+        }
+
+        let defaults = serde_json::to_value(Options::default()).unwrap_or_default();
+        if let serde_json::Value::Object(options) =
+            serde_json::to_value(&self.options).unwrap_or_default()
+        {
+            process.extend(
+                options
+                    .into_iter()
+                    .filter(|(key, value)| defaults.get(key) != Some(value)),
+            );
+        }
+
+        if self.backoff != Backoff::default() {
+            process.insert(
+                string!("backoff"),
+                serde_json::to_value(self.backoff).unwrap_or_default(),
+            );
+        }
+
+        process.into()
+    }
+}
+
+fn serialize(format: Format, processes: &[Exported]) -> Result<String, String> {
+    if format == Format::Hcl {
+        let body = hcl::Body::from_iter(processes.iter().map(Exported::to_hcl));
+        return hcl::to_string(&body).map_err(|err| err.to_string());
+    }
+
+    let list = processes
+        .iter()
+        .map(|process| (process.name.clone(), process.to_value()))
+        .collect::<serde_json::Map<String, serde_json::Value>>();
+    let document = serde_json::json!({ "process": list });
+
+    match format {
+        Format::Toml => toml::to_string(&document).map_err(|err| err.to_string()),
+        Format::Yaml => serde_yaml::to_string(&document).map_err(|err| err.to_string()),
+        _ => serde_json::to_string_pretty(&document)
+            .map(|json| json + "\n")
+            .map_err(|err| err.to_string()),
+    }
+}
+
+/// Write processes to a file in the format of its extension, `all` exports every local process into one file
+pub fn export_file(item: &Item, path: &Option<String>, reveal: bool) {
+    println!("{} Applying action exportProcess", *helpers::SUCCESS);
+
+    let runner = Runner::new();
+    let patterns = secrets::patterns();
+
+    let ids = match item {
+        Item::Id(id) => vec![*id],
+        Item::Name(name) if name == "all" => {
+            let mut first: BTreeMap<String, usize> = BTreeMap::new();
+            for (id, process) in runner.items() {
+                first.entry(process.name.clone()).or_insert(id);
             }
-        } else if let Err(err) = fs::write(path.clone(), serialized) {
+            first.into_values().collect()
+        }
+        Item::Name(name) => match runner.find(name, &string!("internal")) {
+            Some(id) => vec![id],
+            None => crashln!("{} Process ({name}) not found", *helpers::FAIL),
+        },
+    };
+
+    if ids.is_empty() {
+        crashln!("{} No processes to export", *helpers::FAIL);
+    }
+
+    let (processes, masked): (Vec<Exported>, Vec<usize>) = ids
+        .iter()
+        .map(|id| Exported::new(&runner, *id, reveal, &patterns))
+        .unzip();
+    let masked = masked.iter().sum::<usize>();
+
+    let path = match (path, item) {
+        (Some(path), _) => path.clone(),
+        (None, Item::Name(name)) if name == "all" => string!("processes.hcl"),
+        (None, _) => format!("{}.hcl", processes[0].name),
+    };
+
+    let format = Format::of(&path);
+    let serialized = match serialize(format, &processes) {
+        Ok(serialized) => serialized,
+        Err(err) => crashln!(
+            "{} Cannot serialize processes.\n{}",
+            *helpers::FAIL,
+            err.white()
+        ),
+    };
+
+    // HCL blocks can be appended to an existing file, the other formats hold a single document
+    if format == Format::Hcl && Exists::check(&path).file() {
+        let mut file = OpenOptions::new().append(true).open(path.clone()).unwrap();
+        if let Err(err) = writeln!(file, "{}", serialized) {
             crashln!(
-                "{} Error writing file.\n{}",
+                "{} Error writing to file.\n{}",
                 *helpers::FAIL,
                 string!(err).white()
             )
         }
+    } else if let Err(err) = fs::write(path.clone(), serialized) {
+        crashln!(
+            "{} Error writing file.\n{}",
+            *helpers::FAIL,
+            string!(err).white()
+        )
+    }
 
-        println!("{} Exported process {id} to {path}", *helpers::SUCCESS);
-    };
+    match ids.as_slice() {
+        [id] => println!("{} Exported process {id} to {path}", *helpers::SUCCESS),
+        ids => println!(
+            "{} Exported {} processes to {path}",
+            *helpers::SUCCESS,
+            ids.len()
+        ),
+    }
 
-    match item {
-        Item::Id(id) => fetch_process(*id),
-        Item::Name(name) => match runner.find(name, &string!("internal")) {
-            Some(id) => fetch_process(id),
-            None => crashln!("{} Process ({name}) not found", *helpers::FAIL),
-        },
+    if !reveal && masked > 0 {
+        println!(
+            "{}",
+            format!("Masked {masked} secret env values, use --reveal to export them")
+                .bright_black()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pmc::process::{Process as Running, id::Id};
+    use serde_json::json;
+
+    fn running(id: usize, name: &str, script: &str, depends_on: &[&str]) -> Running {
+        serde_json::from_value(json!({
+            "id": id, "pid": 0, "env": {}, "name": name, "path": "/tmp", "script": script,
+            "restarts": 0, "running": true, "crash": { "crashed": false, "value": 0 },
+            "watch": { "enabled": false, "path": "", "hash": "" }, "children": [], "started": 0,
+            "options": { "depends_on": depends_on },
+        }))
+        .unwrap()
+    }
+
+    fn desired(script: &str, depends_on: &[&str]) -> Process {
+        serde_json::from_value(json!({ "script": script, "depends_on": depends_on })).unwrap()
+    }
+
+    fn runner(processes: Vec<Running>) -> Runner {
+        Runner {
+            id: Id::new(processes.len()),
+            remote: None,
+            list: processes
+                .into_iter()
+                .map(|process| (process.id, process))
+                .collect(),
+        }
+    }
+
+    fn env(pairs: &[(&str, &str)]) -> Env {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_plan_create_update_remove() {
+        let runner = runner(vec![
+            running(0, "api", "sleep 30", &[]),
+            running(1, "old", "sleep 30", &[]),
+            running(2, "same", "sleep 10", &[]),
+        ]);
+        let items = || {
+            BTreeMap::from([
+                (string!("api"), desired("sleep 60", &[])),
+                (string!("new"), desired("sleep 5", &[])),
+                (string!("same"), desired("sleep 10", &[])),
+            ])
+        };
+
+        let plan = plan(&runner, items(), false, &[]).unwrap();
+        assert!(
+            matches!(&plan.changes["api"], Change::Update { ids, lines, .. }
+            if ids == &[0] && lines == &[r#"script: "sleep 30" -> "sleep 60""#])
+        );
+        assert!(matches!(&plan.changes["new"], Change::Create(item) if item.script == "sleep 5"));
+        assert!(matches!(plan.changes["same"], Change::Unchanged));
+        assert!(matches!(plan.changes["old"], Change::Kept));
+        assert_eq!(plan.order, ["api", "new", "same", "old"]);
+
+        // removals come before everything else
+        let plan = super::plan(&runner, items(), true, &[]).unwrap();
+        assert!(matches!(&plan.changes["old"], Change::Remove(ids) if ids == &[1]));
+        assert_eq!(plan.order, ["old", "api", "new", "same"]);
+    }
+
+    #[test]
+    fn test_plan_dependency_order() {
+        let runner = runner(vec![running(0, "cache", "sleep 30", &[])]);
+        let items = || {
+            BTreeMap::from([
+                (string!("api"), desired("sleep 30", &["db"])),
+                (string!("db"), desired("sleep 30", &[])),
+                (string!("web"), desired("sleep 30", &["api", "cache"])),
+            ])
+        };
+
+        let plan = plan(&runner, items(), false, &[]).unwrap();
+        let position = |name: &str| plan.order.iter().position(|key| key == name).unwrap();
+        assert!(position("db") < position("api") && position("api") < position("web"));
+        assert!(position("cache") < position("web"));
+        assert_eq!(
+            plan.dependencies,
+            BTreeSet::from([string!("api"), string!("cache"), string!("db")])
+        );
+
+        // a pruned process cannot be depended on
+        assert!(super::plan(&runner, items(), true, &[]).is_err());
+
+        let cycle = BTreeMap::from([
+            (string!("a"), desired("sleep 30", &["b"])),
+            (string!("b"), desired("sleep 30", &["a"])),
+        ]);
+        assert!(super::plan(&runner, cycle, false, &[]).is_err());
+    }
+
+    #[test]
+    fn test_diff_env() {
+        let current = env(&[
+            ("A", "1"),
+            ("B", "2"),
+            ("PATH", "/bin"),
+            ("API_TOKEN", "old"),
+        ]);
+        let desired = env(&[("A", "1"), ("B", "3"), ("C", "4"), ("API_TOKEN", "new")]);
+        let patterns = [string!("*TOKEN*")];
+
+        assert_eq!(
+            diff_env("env ", &current, &desired, false, &patterns),
+            [
+                r#"env API_TOKEN: ******** -> ********"#,
+                r#"env B: "2" -> "3""#,
+                r#"env C: none -> "4""#,
+            ]
+        );
+        assert_eq!(
+            diff_env("env ", &current, &desired, true, &patterns)
+                .last()
+                .unwrap(),
+            r#"env PATH: "/bin" -> none"#
+        );
+        assert!(diff_env("env ", &current, &current, true, &patterns).is_empty());
+    }
+
+    #[test]
+    fn test_diff_options() {
+        let current = Options {
+            env_profiles: BTreeMap::from([(string!("prod"), env(&[("A", "1"), ("B", "2")]))]),
+            ..Options::default()
+        };
+        let desired = Options {
+            user: Some(string!("www-data")),
+            env_profiles: BTreeMap::from([
+                (string!("dev"), env(&[("C", "3")])),
+                (string!("prod"), env(&[("A", "2")])),
+            ]),
+            ..Options::default()
+        };
+
+        assert_eq!(
+            diff_options(&current, &desired, &[]),
+            [
+                r#"env "dev" C: none -> "3""#,
+                r#"env "prod" A: "1" -> "2""#,
+                r#"env "prod" B: "2" -> none"#,
+                r#"user: none -> "www-data""#,
+            ]
+        );
+        assert!(diff_options(&current, &current, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_parse_ecosystem_file() {
+        let contents = r#"
+apps:
+  - name: api
+    script: server.js
+    interpreter: /usr/bin/node
+    node_args: --max-old-space-size=512
+    cwd: services/api
+    env:
+      PORT: 3000
+    env_production:
+      NODE_ENV: production
+"#;
+
+        let wrapper = parse_document(Format::Yaml, contents).unwrap();
+        let api = &wrapper.list["api"];
+
+        assert_eq!(api.options.interpreter_args, ["--max-old-space-size=512"]);
+        assert_eq!(api.env, env(&[("PORT", "3000")]));
+        assert_eq!(
+            api.options.env_profiles["production"],
+            env(&[("NODE_ENV", "production")])
+        );
+
+        // the relative cwd is resolved against the directory the process is created in
+        assert_eq!(
+            api.options.working_dir(Path::new("/srv/app")),
+            Path::new("/srv/app/services/api")
+        );
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let mut api = running(0, "api", "node server.js --port 3000", &["db"]);
+        api.env = env(&[("PMC_EXPORT_TEST", "1")]);
+        api.watch.enabled = true;
+        api.watch.path = string!("src");
+        api.crash.backoff = "exponential:100:2000".parse().unwrap();
+        api.options.kill_timeout = 1600;
+        api.options.restart = "never".parse().unwrap();
+        api.options.cwd = Some("services/api".into());
+        api.options.interpreter_args = vec![string!("--inspect")];
+        api.options.env_profiles =
+            BTreeMap::from([(string!("production"), env(&[("NODE_ENV", "production")]))]);
+
+        let mut second = api.clone();
+        second.id = 1;
+        let runner = runner(vec![api.clone(), second]);
+
+        let (exported, masked) = Exported::new(&runner, 0, true, &[]);
+        assert_eq!(masked, 0);
+
+        for extension in ["hcl", "toml", "json", "yaml"] {
+            let path = std::env::temp_dir()
+                .join(format!("pmc-export-{}.{extension}", std::process::id()))
+                .to_string_lossy()
+                .to_string();
+
+            fs::write(
+                &path,
+                serialize(Format::of(&path), std::slice::from_ref(&exported)).unwrap(),
+            )
+            .unwrap();
+            let items = parse_file(&path, &None);
+            let _ = fs::remove_file(&path);
+
+            let item = &items["api"];
+            assert_eq!(items.len(), 1, "{extension}");
+            assert_eq!(item.script, api.script, "{extension}");
+            assert_eq!(item.get_watch_path(), Some(string!("src")), "{extension}");
+            assert_eq!(item.instances, Some(Instances::Count(2)), "{extension}");
+            assert_eq!(item.env, api.env, "{extension}");
+            assert_eq!(item.backoff, api.crash.backoff, "{extension}");
+            assert_eq!(
+                serde_json::to_value(&item.options).unwrap(),
+                serde_json::to_value(&api.options).unwrap(),
+                "{extension}"
+            );
+        }
     }
 }
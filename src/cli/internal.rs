@@ -1,22 +1,43 @@
+use chrono::{Local, Utc};
 use colored::Colorize;
 use futures::{StreamExt, stream::FuturesUnordered};
 use macros_rs::{crashln, string, ternary, then};
-use std::io::BufRead;
-#[cfg(any(target_os = "linux", target_os = "macos"))]
-use pmc::process::{MemoryInfo, unix::NativeProcess as Process};
 #[cfg(any(target_os = "linux", target_os = "macos"))]
 use pmc::process::unix::{format_ports_colored, get_listening_ports};
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use pmc::process::{MemoryInfo, process_memory};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use tokio::{runtime::Runtime, signal, sync::broadcast};
+use std::{
+    collections::HashSet,
+    io::BufRead,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tokio::{
+    runtime::Runtime,
+    signal,
+    sync::{broadcast, mpsc},
+};
 use tokio_tungstenite::{connect_async, tungstenite::Message as WsMessage};
 
 use pmc::{
     config, file,
     helpers::{self, ColoredString},
     log,
-    process::{ItemSingle, Runner, get_process_cpu_usage_percentage, http},
+    process::{
+        Crash, Env, ItemSingle, Options, Runner,
+        backoff::Backoff,
+        cgroup,
+        environment::{self, EnvMode},
+        get_process_cpu_usage_percentage, http,
+        instances::Instances,
+        logline::{self, Merger},
+        restart::{RestartReason, RestartRecord},
+        search::{self, Filters, SearchResult},
+        secrets,
+    },
 };
 
 use tabled::{
@@ -111,7 +132,7 @@ fn print_snapshot(id: usize, item_name: &str, kind: &str, path: &str, lines: &[S
         println!(
             "{} {}",
             format!("{}|{}|{kind} |", id, item_name).color(color),
-            line
+            logline::colorize(line)
         );
     }
 }
@@ -121,17 +142,203 @@ fn print_line(id: usize, item_name: &str, kind: &str, line: &str) {
     println!(
         "{} {}",
         format!("{}|{}|{kind} |", id, item_name).color(color),
-        line
+        logline::colorize(line)
     );
 }
 
-async fn stream_ws_once(
-    url: String,
+fn format_backoff(crash: &Crash) -> String {
+    match crash.restart_at {
+        Some(time) => format!(
+            "{} (restart in {}s)  ",
+            crash.backoff,
+            (time - Utc::now()).num_seconds().max(0)
+        ),
+        None => format!("{}  ", crash.backoff),
+    }
+}
+
+fn format_history(history: &[RestartRecord]) -> String {
+    if history.is_empty() {
+        return string!("none  ");
+    }
+
+    let recent = history
+        .iter()
+        .rev()
+        .take(3)
+        .map(|record| {
+            format!(
+                "{} {} ago",
+                record.reason,
+                helpers::format_duration(record.at)
+            )
+        })
+        .collect::<Vec<String>>();
+
+    format!("{}  ", recent.join(", "))
+}
+
+fn format_cron(item: &pmc::process::Process) -> String {
+    match &item.options.cron {
+        Some(cron) => match cron.next_after(Local::now()) {
+            Some(next) => format!("{cron} (next {})  ", next.format("%Y-%m-%d %H:%M")),
+            None => format!("{cron} (never fires)  "),
+        },
+        None => string!("none  "),
+    }
+}
+
+fn format_cgroup(options: &Options, path: Option<PathBuf>) -> String {
+    let limits = [
+        options.cpu_max.map(|cpu| format!("cpu {cpu}")),
+        options.memory_max.map(|memory| format!("memory {memory}")),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<String>>();
+
+    match (path, limits.is_empty()) {
+        (Some(path), true) => format!("{}  ", path.display()),
+        (Some(path), false) => format!("{} ({})  ", path.display(), limits.join(", ")),
+        (None, true) => string!("none  "),
+        (None, false) => format!("{}  ", limits.join(", ")),
+    }
+}
+
+/// Options a local script starts with, bare js and ts scripts run with the configured node
+pub fn script_options(script: &str, options: &Options) -> Options {
+    let pattern = Regex::new(r"(?m)^[a-zA-Z0-9]+(/[a-zA-Z0-9]+)*(\.js|\.ts)?$").unwrap();
+
+    match pattern.is_match(script) && options.interpreter.is_none() {
+        true => Options {
+            interpreter: Some(config::read().runner.node),
+            ..options.clone()
+        },
+        false => options.clone(),
+    }
+}
+
+fn format_log_sinks(options: &Options) -> String {
+    match options.log_sinks.is_empty() {
+        true => string!("none  "),
+        false => format!("{}  ", options.log_sinks.join(", ")),
+    }
+}
+
+fn format_depends_on(options: &Options) -> String {
+    match options.depends_on.is_empty() {
+        true => string!("none  "),
+        false => format!("{}  ", options.depends_on.join(", ")),
+    }
+}
+
+fn format_env_mode(options: &Options) -> String {
+    let mode = match options.env_mode {
+        EnvMode::Allowlist => format!(
+            "allowlist ({})",
+            environment::allowlist(&options.env_allow).join(", ")
+        ),
+        mode => mode.to_string(),
+    };
+
+    match &options.env_profile {
+        Some(profile) => format!("{mode}, profile {profile}  "),
+        None => format!("{mode}  "),
+    }
+}
+
+fn format_user(options: &Options) -> String {
+    let user = match (&options.user, &options.group) {
+        (None, None) => string!("daemon user"),
+        (Some(user), None) => user.clone(),
+        (user, Some(group)) => format!("{}:{group}", user.as_deref().unwrap_or("daemon user")),
+    };
+
+    match options.umask {
+        Some(umask) => format!("{user} (umask {umask})  "),
+        None => format!("{user}  "),
+    }
+}
+
+fn format_cron_runs(history: &[RestartRecord]) -> String {
+    let runs = history
+        .iter()
+        .rev()
+        .filter(|record| record.reason == RestartReason::Cron)
+        .take(3)
+        .map(|record| {
+            record
+                .at
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .collect::<Vec<String>>();
+
+    match runs.is_empty() {
+        true => string!("none  "),
+        false => format!("{}  ", runs.join(", ")),
+    }
+}
+
+fn format_command(item: &pmc::process::Process, shell: &str, args: &[String]) -> String {
+    match item.options.exec {
+        true => item.exec_argv().join(" "),
+        false => format!("{shell} {} '{}'", args.join(" "), item.shell_command()),
+    }
+}
+
+fn format_status(status: String) -> colored::ColoredString {
+    match status.trim() {
+        "online" => status.green().bold(),
+        "exited(0)" => status.yellow().bold(),
+        _ => status.red().bold(),
+    }
+}
+
+/// One log file of a process streamed from the daemon
+#[derive(Clone)]
+struct LogStream {
     id: usize,
-    item_name: String,
+    name: String,
     kind: String,
+    url: String,
+}
+
+/// Log streams of a process on the local or remote daemon of `runner`, error first
+fn log_streams(runner: &Runner, id: usize, name: &str, tail: usize) -> Vec<LogStream> {
+    ["error", "out"]
+        .into_iter()
+        .map(|kind| LogStream {
+            id,
+            name: name.to_string(),
+            kind: kind.to_string(),
+            url: match &runner.remote {
+                Some(remote) => remote_ws_url(remote.address(), remote.token(), id, kind, tail),
+                None => local_ws_url(id, kind, tail),
+            },
+        })
+        .collect()
+}
+
+/// Lines a stream hands to the merged output, keyed by the index of the stream
+enum MergeEvent {
+    Snapshot(usize, Vec<String>),
+    Line(usize, String),
+    Ended(usize),
+}
+
+async fn stream_ws_once(
+    stream: LogStream,
+    merge: Option<(usize, mpsc::UnboundedSender<MergeEvent>)>,
     mut shutdown: broadcast::Receiver<()>,
 ) -> anyhow::Result<()> {
+    let LogStream {
+        id,
+        name: item_name,
+        kind,
+        url,
+    } = stream;
     let (mut ws, _) = connect_async(url).await?;
 
     loop {
@@ -148,12 +355,18 @@ async fn stream_ws_once(
                             match frame.kind.as_str() {
                                 "snapshot" => {
                                     if let (Some(path), Some(lines)) = (frame.path, frame.lines) {
-                                        print_snapshot(id, &item_name, &kind, &path, &lines);
+                                        match &merge {
+                                            Some((index, sink)) => drop(sink.send(MergeEvent::Snapshot(*index, lines))),
+                                            None => print_snapshot(id, &item_name, &kind, &path, &lines),
+                                        }
                                     }
                                 }
                                 "line" => {
                                     if let Some(line) = frame.line {
-                                        print_line(id, &item_name, &kind, &line);
+                                        match &merge {
+                                            Some((index, sink)) => drop(sink.send(MergeEvent::Line(*index, line))),
+                                            None => print_line(id, &item_name, &kind, &line),
+                                        }
                                     }
                                 }
                                 "error" => {
@@ -176,25 +389,91 @@ async fn stream_ws_once(
     Ok(())
 }
 
-async fn stream_ws_multi(
-    urls: Vec<(String, String)>,
-    id: usize,
-    item_name: String,
-) -> anyhow::Result<()> {
+/// Print the lines of every stream in time order, the last `tail` of the snapshots first
+///
+/// Live lines are held for a moment, the daemon polls every log file on its own
+async fn print_merged(
+    streams: &[LogStream],
+    tail: usize,
+    mut events: mpsc::UnboundedReceiver<MergeEvent>,
+) {
+    let mut merger = Merger::default();
+    let mut waiting = (0..streams.len()).collect::<HashSet<usize>>();
+    let mut snapshot = true;
+    let mut tick = tokio::time::interval(Duration::from_millis(1000));
+    let started = tokio::time::Instant::now();
+
+    let print = |lines: Vec<(usize, String)>| {
+        for (index, line) in lines {
+            let stream = &streams[index];
+            print_line(stream.id, &stream.name, &stream.kind, &line);
+        }
+    };
+
+    loop {
+        let flush = tokio::select! {
+            event = events.recv() => {
+                match event {
+                    Some(MergeEvent::Snapshot(index, lines)) => {
+                        waiting.remove(&index);
+                        lines.into_iter().for_each(|line| merger.push(index, line));
+                    }
+                    Some(MergeEvent::Line(index, line)) => merger.push(index, line),
+                    Some(MergeEvent::Ended(index)) => drop(waiting.remove(&index)),
+                    None => break,
+                }
+                false
+            }
+            _ = tick.tick() => true,
+        };
+
+        // a stream that never answers does not hold back the others for long
+        if snapshot && (waiting.is_empty() || started.elapsed() > Duration::from_secs(3)) {
+            let lines = merger.drain();
+            let start = lines.len().saturating_sub(tail);
+
+            println!(
+                "{}",
+                format!(
+                    "\nlast {} lines of {} logs, merged:",
+                    lines.len() - start,
+                    streams.len()
+                )
+                .bright_black()
+            );
+            print(lines.into_iter().skip(start).collect());
+            snapshot = false;
+        } else if !snapshot && flush {
+            print(merger.drain());
+        }
+    }
+
+    print(merger.drain());
+}
+
+async fn stream_ws_multi(streams: Vec<LogStream>, tail: usize, merged: bool) -> anyhow::Result<()> {
     let (tx, _) = broadcast::channel(2);
+    let (events, receiver) = mpsc::unbounded_channel();
     let mut tasks = FuturesUnordered::new();
-    let total = urls.len();
+    let total = streams.len();
 
-    for (kind, url) in urls {
-        tasks.push(tokio::spawn(stream_ws_once(
-            url,
-            id,
-            item_name.clone(),
-            kind,
-            tx.subscribe(),
-        )));
+    for (index, stream) in streams.iter().cloned().enumerate() {
+        let merge = merged.then(|| (index, events.clone()));
+        let shutdown = tx.subscribe();
+
+        tasks.push(tokio::spawn(async move {
+            let ended = merge.as_ref().map(|(index, sink)| (*index, sink.clone()));
+            let result = stream_ws_once(stream, merge, shutdown).await;
+
+            if let Some((index, sink)) = ended {
+                let _ = sink.send(MergeEvent::Ended(index));
+            }
+            result
+        }));
     }
 
+    drop(events);
+
     let joiner = async {
         let mut failures = 0usize;
         while let Some(res) = tasks.next().await {
@@ -213,9 +492,13 @@ async fn stream_ws_multi(
         failures
     };
 
+    let printer = async {
+        then!(merged, print_merged(&streams, tail, receiver).await);
+    };
+
     let all_failed = tokio::select! {
         _ = signal::ctrl_c() => { let _ = tx.send(()); return Ok(()); }
-        failures = joiner => failures == total,
+        (failures, _) = async { tokio::join!(joiner, printer) } => failures == total,
     };
 
     if all_failed {
@@ -225,29 +508,131 @@ async fn stream_ws_multi(
     }
 }
 
+/// Search the logs of the processes `ids` and print the newest `lines` matches of every log, or of all of them merged
+fn print_search(runner: &Runner, ids: &[usize], lines: usize, merged: bool, filters: &Filters) {
+    let query = filters
+        .compile()
+        .unwrap_or_else(|err| crashln!("{} {err}", *helpers::FAIL));
+    let mut logs = vec![];
+
+    for id in ids {
+        let item = runner
+            .info(*id)
+            .unwrap_or_else(|| crashln!("{} Process ({id}) not found", *helpers::FAIL));
+
+        for kind in ["error", "out"] {
+            let result = match &runner.remote {
+                Some(remote) => match http::search(remote, *id, kind, filters, lines) {
+                    Ok(response) if response.status().is_success() => {
+                        response.json::<SearchResult>().unwrap_or_else(|err| {
+                            crashln!("{} Failed to read search results: {err}", *helpers::FAIL)
+                        })
+                    }
+                    Ok(response) => {
+                        let status = response.status();
+                        let message = response
+                            .json::<serde_json::Value>()
+                            .ok()
+                            .and_then(|body| body["message"].as_str().map(String::from))
+                            .unwrap_or_else(|| status.to_string());
+                        crashln!("{} Failed to search logs: {message}", *helpers::FAIL)
+                    }
+                    Err(err) => crashln!("{} Failed to search logs: {err}", *helpers::FAIL),
+                },
+                None => {
+                    let path = ternary!(kind == "out", item.logs().out, item.logs().error);
+                    search::search(Path::new(&path), &query, lines)
+                }
+            };
+
+            logs.push((*id, item.name.clone(), kind, result));
+        }
+    }
+
+    if !merged {
+        for (id, name, kind, result) in &logs {
+            let last = ternary!(result.truncated, "last ", "");
+            println!(
+                "{}",
+                format!(
+                    "\n{} {last}{} matching lines ({kind}):",
+                    result.path,
+                    result.lines.len()
+                )
+                .bright_black()
+            );
+            result
+                .lines
+                .iter()
+                .for_each(|line| print_line(*id, name, kind, line));
+        }
+        return;
+    }
+
+    let mut merger = Merger::default();
+    for (index, (_, _, _, result)) in logs.iter().enumerate() {
+        result
+            .lines
+            .iter()
+            .for_each(|line| merger.push(index, line.clone()));
+    }
+
+    let matches = merger.drain();
+    let start = matches.len().saturating_sub(lines);
+
+    println!(
+        "{}",
+        format!(
+            "\nlast {} matching lines of {} logs, merged:",
+            matches.len() - start,
+            logs.len()
+        )
+        .bright_black()
+    );
+    for (index, line) in matches.into_iter().skip(start) {
+        let (id, name, kind, _) = &logs[index];
+        print_line(*id, name, kind, &line);
+    }
+}
+
 impl<'i> Internal<'i> {
+    #[allow(clippy::too_many_arguments)]
     pub fn create(
         mut self,
         script: &String,
         name: &Option<String>,
         watch: &Option<String>,
+        instances: &Instances,
+        options: &Options,
+        backoff: &Backoff,
         silent: bool,
     ) -> Runner {
-        let config = config::read();
         let name = match name {
             Some(name) => string!(name),
             None => string!(script.split_whitespace().next().unwrap_or_default()),
         };
 
         if matches!(self.server_name, "internal" | "local") {
-            let pattern = Regex::new(r"(?m)^[a-zA-Z0-9]+(/[a-zA-Z0-9]+)*(\.js|\.ts)?$").unwrap();
-
-            if pattern.is_match(script) {
-                let script = format!("{} {script}", config.runner.node);
-                self.runner.start(&name, &script, file::cwd(), watch).save();
-            } else {
-                self.runner.start(&name, script, file::cwd(), watch).save();
+            if let Err(err) = options
+                .check()
+                .and_then(|_| options.check_env_profile())
+                .and_then(|_| options.check_log_sinks())
+                .and_then(|_| options.check_env_files(&file::cwd()))
+            {
+                crashln!("{} Failed to create {name}\nError: {err}", *helpers::FAIL);
             }
+
+            self.runner
+                .start(
+                    &name,
+                    script,
+                    file::cwd(),
+                    watch,
+                    instances,
+                    &script_options(script, options),
+                    backoff,
+                )
+                .save();
         } else {
             let Some(servers) = config::servers().servers else {
                 crashln!("{} Failed to read servers", *helpers::FAIL)
@@ -255,7 +640,15 @@ impl<'i> Internal<'i> {
 
             if let Some(server) = servers.get(self.server_name) {
                 match Runner::connect(self.server_name.into(), server.get(), false) {
-                    Some(mut remote) => remote.start(&name, script, file::cwd(), watch),
+                    Some(mut remote) => remote.start(
+                        &name,
+                        script,
+                        file::cwd(),
+                        watch,
+                        instances,
+                        options,
+                        backoff,
+                    ),
                     None => crashln!(
                         "{} Failed to connect (name={}, address={})",
                         *helpers::FAIL,
@@ -282,16 +675,66 @@ impl<'i> Internal<'i> {
         );
         then!(
             !silent,
-            println!("{} {}Created ({name}) ✓", *helpers::SUCCESS, self.kind)
+            println!(
+                "{} {}Created ({name}){} ✓",
+                *helpers::SUCCESS,
+                self.kind,
+                ternary!(
+                    *instances == Instances::default(),
+                    string!(""),
+                    format!(" with {instances} instances")
+                )
+            )
         );
 
         self.runner
     }
 
+    /// Restart `ids` with the processes they depend on first, waiting for each dependency to be ready
+    pub fn restart_ordered(
+        mut runner: Runner,
+        ids: &[usize],
+        server_name: &String,
+        kind: &str,
+        env_profile: &Option<String>,
+    ) -> Runner {
+        let (ids, skipped) = runner.start_order(ids);
+
+        for reason in skipped {
+            println!("{} Ignoring dependency, {reason}", *helpers::WARN);
+        }
+
+        let local = matches!(&**server_name, "internal" | "local");
+        let dependencies = runner.dependencies();
+
+        for id in ids {
+            let name = runner.try_info(id).name.clone();
+            let wait = local && dependencies.contains(&name);
+
+            runner = Internal {
+                id,
+                server_name,
+                kind: kind.to_string(),
+                runner: runner.clone(),
+            }
+            .restart(&None, &None, env_profile, false, true);
+
+            if wait && !runner.wait_ready(id) {
+                println!(
+                    "{} Dependency {name} ({id}) did not become ready, starting the processes that depend on it anyway",
+                    *helpers::FAIL
+                );
+            }
+        }
+
+        runner
+    }
+
     pub fn restart(
         mut self,
         name: &Option<String>,
         watch: &Option<String>,
+        env_profile: &Option<String>,
         reset_env: bool,
         silent: bool,
     ) -> Runner {
@@ -305,7 +748,7 @@ impl<'i> Internal<'i> {
             )
         );
 
-        if matches!(self.server_name, "internal" | "local") {
+        let restarted = if matches!(self.server_name, "internal" | "local") {
             let mut item = self.runner.get(self.id);
 
             match watch {
@@ -315,12 +758,17 @@ impl<'i> Internal<'i> {
 
             then!(reset_env, item.clear_env());
 
+            if let Some(profile) = env_profile {
+                item.set_env_profile(profile.clone());
+            }
+
             if let Some(n) = name.as_ref() {
                 item.rename(n.trim().replace("\n", ""))
             }
-            item.restart();
+            let restarted = item.restart();
 
             self.runner = item.get_runner().clone();
+            restarted
         } else {
             let Some(servers) = config::servers().servers else {
                 crashln!("{} Failed to read servers", *helpers::FAIL)
@@ -333,10 +781,14 @@ impl<'i> Internal<'i> {
 
                         then!(reset_env, item.clear_env());
 
+                        if let Some(profile) = env_profile {
+                            item.set_env_profile(profile.clone());
+                        }
+
                         if let Some(n) = name.as_ref() {
                             item.rename(n.trim().replace("\n", ""))
                         }
-                        item.restart();
+                        item.restart()
                     }
                     None => crashln!(
                         "{} Failed to connect (name={}, address={})",
@@ -351,10 +803,18 @@ impl<'i> Internal<'i> {
                     *helpers::FAIL,
                     self.server_name
                 )
-            };
-        }
+            }
+        };
 
-        if !silent {
+        if let Err(err) = restarted {
+            println!(
+                "{} Failed to restart {}({})\nError: {err}",
+                *helpers::FAIL,
+                self.kind,
+                self.id
+            );
+            log!("process restart failed (id={}, error={err})", self.id);
+        } else if !silent {
             println!(
                 "{} Restarted {}({}) ✓",
                 *helpers::SUCCESS,
@@ -414,6 +874,118 @@ impl<'i> Internal<'i> {
         self.runner
     }
 
+    pub fn reload(mut self) {
+        println!(
+            "{} Applying {}action reloadProcess on ({})",
+            *helpers::SUCCESS,
+            self.kind,
+            self.id
+        );
+
+        if !matches!(self.server_name, "internal" | "local") {
+            let Some(servers) = config::servers().servers else {
+                crashln!("{} Failed to read servers", *helpers::FAIL)
+            };
+
+            if let Some(server) = servers.get(self.server_name) {
+                self.runner = match Runner::connect(self.server_name.into(), server.get(), false) {
+                    Some(remote) => remote,
+                    None => crashln!(
+                        "{} Failed to connect (name={}, address={})",
+                        *helpers::FAIL,
+                        self.server_name,
+                        server.address
+                    ),
+                };
+            } else {
+                crashln!(
+                    "{} Server '{}' does not exist",
+                    *helpers::FAIL,
+                    self.server_name
+                )
+            };
+        }
+
+        match self.runner.reload(self.id) {
+            Ok(runner) => {
+                runner.save();
+                println!(
+                    "{} Reloaded {}({}) ✓",
+                    *helpers::SUCCESS,
+                    self.kind,
+                    self.id
+                );
+                log!("process reloaded (id={})", self.id);
+            }
+            Err(err) => {
+                println!(
+                    "{} Failed to reload {}({})\nError: {err}",
+                    *helpers::FAIL,
+                    self.kind,
+                    self.id
+                );
+                log!("process reload failed (id={}, error={err})", self.id);
+            }
+        }
+    }
+
+    pub fn scale(mut self, instances: &Instances) {
+        println!(
+            "{} Applying {}action scaleProcess on ({})",
+            *helpers::SUCCESS,
+            self.kind,
+            self.id
+        );
+
+        if !matches!(self.server_name, "internal" | "local") {
+            let Some(servers) = config::servers().servers else {
+                crashln!("{} Failed to read servers", *helpers::FAIL)
+            };
+
+            if let Some(server) = servers.get(self.server_name) {
+                self.runner = match Runner::connect(self.server_name.into(), server.get(), false) {
+                    Some(remote) => remote,
+                    None => crashln!(
+                        "{} Failed to connect (name={}, address={})",
+                        *helpers::FAIL,
+                        self.server_name,
+                        server.address
+                    ),
+                };
+            } else {
+                crashln!(
+                    "{} Server '{}' does not exist",
+                    *helpers::FAIL,
+                    self.server_name
+                )
+            };
+        }
+
+        let scaled = self.runner.scale(self.id, instances).map(|_| ());
+        self.runner.save();
+
+        match scaled {
+            Ok(()) => {
+                println!(
+                    "{} Scaled {}({}) to {instances} instances ✓",
+                    *helpers::SUCCESS,
+                    self.kind,
+                    self.id
+                );
+                log!("process scaled (id={}, instances={instances})", self.id);
+            }
+            Err(err) => {
+                println!(
+                    "{} Failed to scale {}({})\nError: {err}",
+                    *helpers::FAIL,
+                    self.kind,
+                    self.id
+                );
+                log!("process scale failed (id={}, error={err})", self.id);
+            }
+        }
+    }
+
     pub fn remove(mut self) {
         println!(
             "{} Applying {}action removeProcess on ({})",
@@ -493,13 +1065,21 @@ impl<'i> Internal<'i> {
         log!("process logs cleaned (id={})", self.id);
     }
 
-    pub fn info(&self, format: &String) {
+    pub fn info(&self, format: &str) {
         #[derive(Clone, Debug, Tabled)]
         struct Info {
             #[tabled(rename = "error log path ")]
             log_error: String,
             #[tabled(rename = "out log path")]
             log_out: String,
+            #[tabled(rename = "log rotation")]
+            log_rotate: String,
+            #[tabled(rename = "log format")]
+            log_format: String,
+            #[tabled(rename = "log sinks")]
+            log_sinks: String,
+            #[tabled(rename = "log pipe")]
+            log_pipe: String,
             #[tabled(rename = "cpu percent")]
             cpu_percent: String,
             #[tabled(rename = "memory usage")]
@@ -509,12 +1089,29 @@ impl<'i> Internal<'i> {
             #[tabled(rename = "watching")]
             watch: String,
             children: String,
+            #[tabled(rename = "run as")]
+            user: String,
+            limits: String,
+            cgroup: String,
+            #[tabled(rename = "env mode")]
+            env_mode: String,
+            #[tabled(rename = "depends on")]
+            depends_on: String,
             #[tabled(rename = "exec cwd")]
             path: String,
             #[tabled(rename = "script command ")]
             command: String,
             #[tabled(rename = "script id")]
             id: String,
+            #[tabled(rename = "restart backoff")]
+            backoff: String,
+            #[tabled(rename = "last exit")]
+            last_exit: String,
+            #[tabled(rename = "restart history")]
+            history: String,
+            cron: String,
+            #[tabled(rename = "cron runs")]
+            cron_runs: String,
             restarts: u64,
             uptime: String,
             pid: String,
@@ -529,7 +1126,21 @@ impl<'i> Internal<'i> {
                      "pid": &self.pid.trim(),
                      "name": &self.name.trim(),
                      "path": &self.path.trim(),
+                     "user": &self.user.trim(),
+                     "limits": &self.limits.trim(),
+                     "cgroup": &self.cgroup.trim(),
+                     "env_mode": &self.env_mode.trim(),
+                     "depends_on": &self.depends_on.trim(),
+                     "log_rotate": &self.log_rotate.trim(),
+                     "log_format": &self.log_format.trim(),
+                     "log_sinks": &self.log_sinks.trim(),
+                     "log_pipe": &self.log_pipe.trim(),
                      "restarts": &self.restarts,
+                     "backoff": &self.backoff.trim(),
+                     "last_exit": &self.last_exit.trim(),
+                     "history": &self.history.trim(),
+                     "cron": &self.cron.trim(),
+                     "cron_runs": &self.cron_runs.trim(),
                      "hash": &self.hash.trim(),
                      "watch": &self.watch.trim(),
                      "children": &self.children,
@@ -555,7 +1166,7 @@ impl<'i> Internal<'i> {
                 .to_string();
 
             if let Ok(json) = serde_json::to_string(&data[0]) {
-                match format.as_str() {
+                match format {
                     "raw" => println!("{:?}", data[0]),
                     "json" => println!("{json}"),
                     _ => {
@@ -595,14 +1206,17 @@ impl<'i> Internal<'i> {
                 let path = file::make_relative(&item.path, &home)
                     .to_string_lossy()
                     .into_owned();
+                let cwd = file::make_relative(&item.cwd(), &home)
+                    .to_string_lossy()
+                    .into_owned();
                 let children = if item.children.is_empty() {
                     "none".to_string()
                 } else {
                     format!("{:?}", item.children)
                 };
 
-                if let Ok(process) = Process::new(item.pid as u32) {
-                    memory_usage = process.memory_info().ok().map(MemoryInfo::from);
+                if let Some(memory) = process_memory(item.pid) {
+                    memory_usage = Some(memory);
                     cpu_percent = Some(get_process_cpu_usage_percentage(item.pid));
                 }
 
@@ -616,35 +1230,35 @@ impl<'i> Internal<'i> {
                     None => string!("0b"),
                 };
 
-                let status = if item.running {
-                    "online   ".green().bold()
-                } else {
-                    match item.crash.crashed {
-                        true => "crashed   ",
-                        false => "stopped   ",
-                    }
-                    .red()
-                    .bold()
-                };
+                let status = format_status(format!("{}   ", item.status()));
 
                 let data = vec![Info {
                     children,
                     cpu_percent,
                     memory_usage,
                     id: string!(self.id),
+                    backoff: format_backoff(&item.crash),
+                    last_exit: format!("{}  ", item.last_exit()),
+                    history: format_history(&item.history),
+                    cron: format_cron(item),
+                    cron_runs: format_cron_runs(&item.history),
+                    user: format_user(&item.options),
+                    limits: format!("{}  ", item.options.limits),
+                    cgroup: format_cgroup(&item.options, cgroup::of_pid(item.pid)),
+                    env_mode: format_env_mode(&item.options),
+                    depends_on: format_depends_on(&item.options),
                     restarts: item.restarts,
-                    name: item.name.clone(),
+                    name: item.display_name(),
                     log_out: item.logs().out,
-                    path: format!("{} ", path),
+                    log_rotate: format!("{}  ", item.options.log_rotate.resolve()),
+                    log_format: format!("{}  ", item.options.log_format),
+                    log_sinks: format_log_sinks(&item.options),
+                    log_pipe: format!("{}  ", item.options.log_pipe.as_deref().unwrap_or("none")),
+                    path: format!("{} ", cwd),
                     log_error: item.logs().error,
                     status: ColoredString(status),
                     pid: ternary!(item.running, format!("{}", item.pid), string!("n/a")),
-                    command: format!(
-                        "{} {} '{}'",
-                        config.shell,
-                        config.args.join(" "),
-                        item.script
-                    ),
+                    command: format_command(item, &config.shell, &config.args),
                     hash: ternary!(
                         item.watch.enabled,
                         format!("{}  ", item.watch.hash),
@@ -695,19 +1309,10 @@ impl<'i> Internal<'i> {
             let info = http::info(&remote, self.id);
             let path = item.path.to_string_lossy().into_owned();
 
-            let status = if item.running {
-                "online   ".green().bold()
-            } else {
-                match item.crash.crashed {
-                    true => "crashed   ",
-                    false => "stopped   ",
-                }
-                .red()
-                .bold()
-            };
+            let status = format_status(format!("{}   ", item.status()));
 
             if let Ok(info) = info {
-                let stats = info.json::<ItemSingle>().unwrap().stats;
+                let ItemSingle { stats, log, .. } = info.json::<ItemSingle>().unwrap();
                 let children = if item.children.is_empty() {
                     "none".to_string()
                 } else {
@@ -729,28 +1334,40 @@ impl<'i> Internal<'i> {
                     cpu_percent,
                     memory_usage,
                     id: string!(self.id),
-                    path: path.clone(),
+                    path: item.cwd().to_string_lossy().into_owned(),
                     status: status.into(),
+                    backoff: format_backoff(&item.crash),
+                    last_exit: format!("{}  ", item.last_exit()),
+                    history: format_history(&item.history),
+                    cron: format_cron(&item),
+                    cron_runs: format_cron_runs(&item.history),
+                    user: format_user(&item.options),
+                    limits: format!("{}  ", item.options.limits),
+                    cgroup: format_cgroup(&item.options, None),
+                    env_mode: format_env_mode(&item.options),
+                    depends_on: format_depends_on(&item.options),
                     restarts: item.restarts,
-                    name: item.name.clone(),
+                    name: item.display_name(),
                     pid: ternary!(
                         item.running,
                         format!("{pid}", pid = item.pid),
                         string!("n/a")
                     ),
-                    log_out: format!("{}/{}-out.log", remote.config.log_path, item.name),
-                    log_error: format!("{}/{}-error.log", remote.config.log_path, item.name),
+                    log_out: log.out,
+                    log_rotate: format!(
+                        "{}  ",
+                        item.options.log_rotate.or(&remote.config.log_rotate)
+                    ),
+                    log_format: format!("{}  ", item.options.log_format),
+                    log_sinks: format_log_sinks(&item.options),
+                    log_pipe: format!("{}  ", item.options.log_pipe.as_deref().unwrap_or("none")),
+                    log_error: log.error,
                     hash: ternary!(
                         item.watch.enabled,
                         format!("{}  ", item.watch.hash),
                         string!("none  ")
                     ),
-                    command: format!(
-                        "{} {} '{}'",
-                        remote.config.shell,
-                        remote.config.args.join(" "),
-                        item.script
-                    ),
+                    command: format_command(&item, &remote.config.shell, &remote.config.args),
                     watch: ternary!(
                         item.watch.enabled,
                         format!("{path}/{}  ", item.watch.path),
@@ -768,11 +1385,10 @@ impl<'i> Internal<'i> {
         }
     }
 
-    pub fn logs(mut self, lines: &usize) {
+    pub fn logs(mut self, lines: &usize, merged: bool, filters: &Filters) {
         let tail = *lines;
-        let mut urls: Vec<(String, String)> = Vec::new();
 
-        let item_name = if !matches!(self.server_name, "internal" | "local") {
+        if !matches!(self.server_name, "internal" | "local") {
             let Some(servers) = config::servers().servers else {
                 crashln!("{} Failed to read servers", *helpers::FAIL)
             };
@@ -794,58 +1410,36 @@ impl<'i> Internal<'i> {
                     self.server_name
                 )
             };
+        }
 
-            let item = self
-                .runner
-                .info(self.id)
-                .unwrap_or_else(|| crashln!("{} Process ({}) not found", *helpers::FAIL, self.id));
-            if let Some(remote) = &self.runner.remote {
-                for kind in ["error", "out"] {
-                    urls.push((
-                        kind.to_string(),
-                        remote_ws_url(remote.address(), remote.token(), self.id, kind, tail),
-                    ));
-                }
-            }
+        let item = self
+            .runner
+            .info(self.id)
+            .unwrap_or_else(|| crashln!("{} Process ({}) not found", *helpers::FAIL, self.id));
 
-            Some(item.name.clone())
-        } else {
-            let item = self
-                .runner
-                .info(self.id)
-                .unwrap_or_else(|| crashln!("{} Process ({}) not found", *helpers::FAIL, self.id));
-
-            for kind in ["error", "out"] {
-                urls.push((kind.to_string(), local_ws_url(self.id, kind, tail)));
-            }
+        if !filters.is_empty() {
+            return print_search(&self.runner, &[self.id], tail, merged, filters);
+        }
 
-            Some(item.name.clone())
-        };
+        let streams = log_streams(&self.runner, self.id, &item.name, tail);
 
-        if !urls.is_empty() {
-            println!(
-                "{}",
-                format!(
-                    "Streaming last {tail} lines for {}process [{}] (Ctrl+C to stop)",
-                    self.kind, self.id
-                )
-                .yellow()
-            );
+        println!(
+            "{}",
+            format!(
+                "Streaming last {tail} lines for {}process [{}] (Ctrl+C to stop)",
+                self.kind, self.id
+            )
+            .yellow()
+        );
 
-            let runtime = Runtime::new();
-            if let Err(err) =
-                runtime
-                    .expect("Failed to create tokio runtime")
-                    .block_on(stream_ws_multi(
-                        urls,
-                        self.id,
-                        item_name.clone().unwrap_or_default(),
-                    ))
-            {
-                println!("{} WebSocket streaming failed: {err}", *helpers::FAIL);
-            } else {
-                return;
-            }
+        let runtime = Runtime::new();
+        if let Err(err) = runtime
+            .expect("Failed to create tokio runtime")
+            .block_on(stream_ws_multi(streams, tail, merged))
+        {
+            println!("{} WebSocket streaming failed: {err}", *helpers::FAIL);
+        } else {
+            return;
         }
 
         // fallback to existing behavior
@@ -886,7 +1480,57 @@ impl<'i> Internal<'i> {
         }
     }
 
-    pub fn env(mut self) {
+    /// Stream the logs of every process on the server interleaved by time, or search them with `filters`
+    pub fn logs_all(lines: &usize, merged: bool, filters: &Filters, server_name: &String) {
+        let runner = match config::servers()
+            .servers
+            .as_ref()
+            .and_then(|servers| servers.get(server_name))
+        {
+            Some(server) => Runner::connect(server_name.clone(), server.get(), false)
+                .unwrap_or_else(|| {
+                    crashln!(
+                        "{} Failed to connect (name={server_name}, address={})",
+                        *helpers::FAIL,
+                        server.address
+                    )
+                }),
+            None if matches!(&**server_name, "internal" | "local") => Runner::new(),
+            None => crashln!("{} Server '{server_name}' does not exist", *helpers::FAIL),
+        };
+
+        if runner.is_empty() {
+            println!("{} Cannot show logs, no processes found", *helpers::FAIL);
+            return;
+        }
+
+        if !filters.is_empty() {
+            let ids = runner.items().keys().copied().collect::<Vec<usize>>();
+            return print_search(&runner, &ids, *lines, merged, filters);
+        }
+
+        let streams = runner
+            .items()
+            .iter()
+            .flat_map(|(id, item)| log_streams(&runner, *id, &item.name, *lines))
+            .collect::<Vec<LogStream>>();
+
+        println!(
+            "{}",
+            format!(
+                "Streaming last {lines} lines for {} processes (Ctrl+C to stop)",
+                runner.items().len()
+            )
+            .yellow()
+        );
+
+        let runtime = Runtime::new().expect("Failed to create tokio runtime");
+        if let Err(err) = runtime.block_on(stream_ws_multi(streams, *lines, true)) {
+            println!("{} WebSocket streaming failed: {err}", *helpers::FAIL);
+        }
+    }
+
+    pub fn env(mut self, reveal: bool) {
         println!(
             "{}",
             format!("Showing env for {}process {}:\n", self.kind, self.id).bright_yellow()
@@ -916,10 +1560,67 @@ impl<'i> Internal<'i> {
             };
         }
 
-        let item = self.runner.process(self.id);
-        item.env
-            .iter()
-            .for_each(|(key, value)| println!("{}: {}", key, value.green()));
+        let item = self.runner.process(self.id).clone();
+        let env: Env = match &self.runner.remote {
+            Some(remote) => match http::env(remote, self.id, reveal) {
+                Ok(response) if response.status().is_success() => response
+                    .json()
+                    .unwrap_or_else(|err| crashln!("{} Failed to read env: {err}", *helpers::FAIL)),
+                Ok(response) => {
+                    let status = response.status();
+                    let message = response
+                        .json::<serde_json::Value>()
+                        .ok()
+                        .and_then(|body| body["message"].as_str().map(String::from))
+                        .unwrap_or_else(|| status.to_string());
+                    crashln!("{} Failed to fetch env: {message}", *helpers::FAIL)
+                }
+                Err(err) => crashln!("{} Failed to fetch env: {err}", *helpers::FAIL),
+            },
+            None => {
+                // the dump is only readable by its owner, so local callers may always reveal
+                let mut env = item.full_env();
+                then!(!reveal, secrets::mask(&mut env, &secrets::patterns()));
+                env
+            }
+        };
+
+        env.iter().for_each(|(key, value)| {
+            let value = ternary!(
+                value == secrets::MASKED,
+                value.bright_black(),
+                value.green()
+            );
+
+            // values from env files override the profile and the stored environment on start
+            let source = match (item.file_env.get(key), &item.options.env_profile) {
+                (Some(loaded), _) => Some(loaded.file.clone()),
+                (None, Some(profile))
+                    if item
+                        .options
+                        .profile_env()
+                        .is_some_and(|env| env.contains_key(key)) =>
+                {
+                    Some(format!("env \"{profile}\""))
+                }
+                _ => None,
+            };
+
+            match source {
+                Some(source) => println!(
+                    "{key}: {value} {}",
+                    format!("(from {source})").bright_black()
+                ),
+                None => println!("{key}: {value}"),
+            }
+        });
+
+        if !reveal && env.values().any(|value| value == secrets::MASKED) {
+            println!(
+                "\n{}",
+                "Secret values are masked, use --reveal to show them".bright_black()
+            );
+        }
     }
 
     pub fn save(server_name: &String) {
@@ -932,24 +1633,21 @@ impl<'i> Internal<'i> {
     }
 
     pub fn restore(server_name: &String) {
-        let mut runner = Runner::new();
+        let runner = Runner::new();
         let (kind, list_name) = super::format(server_name);
 
         if !matches!(&**server_name, "internal" | "local") {
             crashln!("{} Cannot restore on remote servers", *helpers::FAIL)
         }
 
-        Runner::new().list().for_each(|(id, p)| {
-            if p.running {
-                runner = Internal {
-                    id: *id,
-                    server_name,
-                    kind: kind.clone(),
-                    runner: runner.clone(),
-                }
-                .restart(&None, &None, false, true);
-            }
-        });
+        let ids = runner
+            .list
+            .iter()
+            .filter(|(_, p)| p.options.restart.restores(p.running))
+            .map(|(id, _)| *id)
+            .collect::<Vec<usize>>();
+
+        Internal::restart_ordered(runner, &ids, server_name, &kind, &None);
 
         println!(
             "{} Restored process statuses from dumpfile",
@@ -969,25 +1667,12 @@ impl<'i> Internal<'i> {
                 let mut cpu_percent = string!("0.00%");
                 let mut memory_usage = string!("0b");
 
-                if internal {
-                    if let Ok(process) = Process::new(item.pid as u32) {
-                        cpu_percent = format!(
-                            "{:.2}%",
-                            get_process_cpu_usage_percentage(item.pid)
-                        );
-                        if let Ok(mem) = process.memory_info() {
-                            memory_usage = helpers::format_memory(MemoryInfo::from(mem).rss);
-                        }
-                    }
+                if internal && let Some(memory) = process_memory(item.pid) {
+                    cpu_percent = format!("{:.2}%", get_process_cpu_usage_percentage(item.pid));
+                    memory_usage = helpers::format_memory(memory.rss);
                 }
 
-                let status_str = if item.running {
-                    "online".green().bold()
-                } else if item.crash.crashed {
-                    "crashed".red().bold()
-                } else {
-                    "stopped".red().bold()
-                };
+                let status_str = format_status(item.status());
 
                 let pid_str = if item.running {
                     format!("{}", item.pid)
@@ -1017,7 +1702,7 @@ impl<'i> Internal<'i> {
                     "\n{}",
                     format!(
                         "━━━ [{id}] {}  ({status_str})  PID: {pid_str}  Uptime: {uptime_str}  ↺ {}  CPU: {cpu_percent}  Mem: {memory_usage}  Ports: {ports_str}",
-                        item.name, item.restarts
+                        item.display_name(), item.restarts
                     )
                     .bright_white()
                     .bold()
@@ -1086,7 +1771,7 @@ impl<'i> Internal<'i> {
         }
     }
 
-    pub fn list(format: &String, server_name: &String) {
+    pub fn list(format: &str, server_name: &String) {
         let render_list = |runner: &mut Runner, internal: bool| {
             let mut processes: Vec<ProcessItem> = Vec::new();
 
@@ -1142,10 +1827,10 @@ impl<'i> Internal<'i> {
                     if internal {
                         let mut usage_internals: (Option<f64>, Option<MemoryInfo>) = (None, None);
 
-                        if let Ok(process) = Process::new(item.pid as u32) {
+                        if let Some(memory) = process_memory(item.pid) {
                             usage_internals = (
                                 Some(get_process_cpu_usage_percentage(item.pid)),
-                                process.memory_info().ok().map(MemoryInfo::from),
+                                Some(memory),
                             );
                         }
 
@@ -1176,16 +1861,7 @@ impl<'i> Internal<'i> {
                         }
                     }
 
-                    let status = if item.running {
-                        "online   ".green().bold()
-                    } else {
-                        match item.crash.crashed {
-                            true => "crashed   ",
-                            false => "stopped   ",
-                        }
-                        .red()
-                        .bold()
-                    };
+                    let status = format_status(format!("{}   ", item.status()));
 
                     let ports_display = if item.running {
                         #[cfg(any(target_os = "linux", target_os = "macos"))]
@@ -1209,7 +1885,7 @@ impl<'i> Internal<'i> {
                         mem: format!("{memory_usage}   "),
                         id: id.to_string().cyan().bold().into(),
                         restarts: format!("{}  ", item.restarts),
-                        name: format!("{}   ", item.name.clone()),
+                        name: format!("{}   ", item.display_name()),
                         pid: ternary!(item.running, format!("{}  ", item.pid), string!("n/a  ")),
                         ports: ports_display,
                         watch: ternary!(
@@ -1233,7 +1909,7 @@ impl<'i> Internal<'i> {
                     .to_string();
 
                 if let Ok(json) = serde_json::to_string(&processes) {
-                    match format.as_str() {
+                    match format {
                         "raw" => println!("{:?}", processes),
                         "json" => println!("{json}"),
                         "default" => println!("{table}"),
@@ -1279,7 +1955,7 @@ impl<'i> Internal<'i> {
                     println!(
                         " {} {} {}",
                         "-".yellow(),
-                        format!("{}", server.0),
+                        server.0,
                         format!("[{}]", server.1).white()
                     )
                 });
@@ -4,13 +4,17 @@ pub use args::*;
 pub(crate) mod dashboard;
 pub(crate) mod import;
 pub(crate) mod internal;
+pub(crate) mod pm2;
 pub(crate) mod server;
 
-use internal::Internal;
 use colored::Colorize;
 use inquire::Select;
+use internal::Internal;
 use macros_rs::{crashln, string, ternary};
-use pmc::{file, helpers, process::Runner};
+use pmc::{
+    file, helpers,
+    process::{Options, Runner, backoff::Backoff, instances::Instances, search::Filters},
+};
 use std::env;
 
 pub(crate) fn format(server_name: &String) -> (String, String) {
@@ -43,10 +47,14 @@ pub fn get_version(short: bool) -> String {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn start(
     name: &Option<String>,
     args: &Args,
     watch: &Option<String>,
+    instances: &Instances,
+    options: &Options,
+    backoff: &Backoff,
     reset_env: &bool,
     server_name: &String,
 ) {
@@ -65,15 +73,7 @@ pub fn start(
         if ids.is_empty() {
             println!("{} Cannot start all, no processes found", *helpers::FAIL);
         } else {
-            for id in ids {
-                runner = Internal {
-                    id,
-                    server_name,
-                    kind: kind.clone(),
-                    runner: runner.clone(),
-                }
-                .restart(&None, &None, false, true);
-            }
+            Internal::restart_ordered(runner, &ids, server_name, &kind, &options.env_profile);
         }
     } else {
         match args {
@@ -84,19 +84,27 @@ pub fn start(
                     server_name,
                     kind,
                 }
-                .restart(name, watch, *reset_env, false);
+                .restart(name, watch, &options.env_profile, *reset_env, false);
             }
-            Args::Script(script) => match runner.find(script, server_name) {
-                Some(id) => {
-                    Internal {
-                        id,
-                        runner,
-                        server_name,
-                        kind,
+            Args::Script(script) => match runner.find_all(script, server_name) {
+                ids if !ids.is_empty() => {
+                    for id in ids {
+                        runner = Internal {
+                            id,
+                            server_name,
+                            kind: kind.clone(),
+                            runner: runner.clone(),
+                        }
+                        .restart(
+                            name,
+                            watch,
+                            &options.env_profile,
+                            *reset_env,
+                            false,
+                        );
                     }
-                    .restart(name, watch, *reset_env, false);
                 }
-                None => {
+                _ => {
                     let prefix_matches = runner.find_prefix(script, server_name);
                     match prefix_matches.len() {
                         1 => {
@@ -107,7 +115,13 @@ pub fn start(
                                 server_name,
                                 kind,
                             }
-                            .restart(name, watch, *reset_env, false);
+                            .restart(
+                                name,
+                                watch,
+                                &options.env_profile,
+                                *reset_env,
+                                false,
+                            );
                         }
                         n if n > 1 => {
                             println!(
@@ -125,7 +139,7 @@ pub fn start(
                                 server_name,
                                 kind,
                             }
-                            .create(script, name, watch, false);
+                            .create(script, name, watch, instances, options, backoff, false);
                         }
                     }
                 }
@@ -170,17 +184,19 @@ pub fn stop(item: &Item, server_name: &String) {
                 }
                 .stop(false);
             }
-            Item::Name(name) => match runner.find(name, server_name) {
-                Some(id) => {
-                    Internal {
-                        id,
-                        runner,
-                        server_name,
-                        kind,
+            Item::Name(name) => match runner.find_all(name, server_name) {
+                ids if !ids.is_empty() => {
+                    for id in ids {
+                        runner = Internal {
+                            id,
+                            server_name,
+                            kind: kind.clone(),
+                            runner: runner.clone(),
+                        }
+                        .stop(false);
                     }
-                    .stop(false);
                 }
-                None => crashln!("{} Process ({name}) not found", *helpers::FAIL),
+                _ => crashln!("{} Process ({name}) not found", *helpers::FAIL),
             },
         }
     }
@@ -223,15 +239,19 @@ pub fn remove(item: &Item, server_name: &String) {
                 kind,
             }
             .remove(),
-            Item::Name(name) => match runner.find(name, server_name) {
-                Some(id) => Internal {
-                    id,
-                    runner,
-                    server_name,
-                    kind,
+            Item::Name(name) => match runner.find_all(name, server_name) {
+                ids if !ids.is_empty() => {
+                    for id in ids {
+                        Internal {
+                            id,
+                            server_name,
+                            kind: kind.clone(),
+                            runner: Runner::new(),
+                        }
+                        .remove();
+                    }
                 }
-                .remove(),
-                None => crashln!("{} Process ({name}) not found", *helpers::FAIL),
+                _ => crashln!("{} Process ({name}) not found", *helpers::FAIL),
             },
         }
     }
@@ -239,7 +259,61 @@ pub fn remove(item: &Item, server_name: &String) {
     super::daemon::reset();
 }
 
-pub fn info(item: &Item, format: &String, server_name: &String) {
+pub fn reload(item: &Item, server_name: &String) {
+    let runner: Runner = Runner::new();
+    let (kind, list_name) = format(server_name);
+
+    let ids: Vec<usize> = match item {
+        Item::Name(name) if name == "all" => runner.items().keys().copied().collect(),
+        Item::Id(id) => vec![*id],
+        Item::Name(name) => runner.find_all(name, server_name),
+    };
+
+    if ids.is_empty() {
+        crashln!("{} Cannot reload, no processes found", *helpers::FAIL);
+    }
+
+    for id in ids {
+        Internal {
+            id,
+            server_name,
+            kind: kind.clone(),
+            runner: Runner::new(),
+        }
+        .reload();
+    }
+
+    Internal::list(&string!("default"), &list_name);
+}
+
+pub fn scale(item: &Item, instances: &Instances, server_name: &String) {
+    let runner: Runner = Runner::new();
+    let (kind, list_name) = format(server_name);
+
+    match item {
+        Item::Id(id) => Internal {
+            id: *id,
+            runner,
+            server_name,
+            kind,
+        }
+        .scale(instances),
+        Item::Name(name) => match runner.find(name, server_name) {
+            Some(id) => Internal {
+                id,
+                runner,
+                server_name,
+                kind,
+            }
+            .scale(instances),
+            None => crashln!("{} Process ({name}) not found", *helpers::FAIL),
+        },
+    }
+
+    Internal::list(&string!("default"), &list_name);
+}
+
+pub fn info(item: &Item, format: &str, server_name: &String) {
     let runner: Runner = Runner::new();
     let (kind, _) = self::format(server_name);
 
@@ -264,12 +338,16 @@ pub fn info(item: &Item, format: &String, server_name: &String) {
     }
 }
 
-pub fn logs(item: &Item, lines: &usize, server_name: &String) {
+pub fn logs(item: &Item, lines: &usize, merged: bool, filters: &Filters, server_name: &String) {
     let runner: Runner = Runner::new();
     let (kind, _) = format(server_name);
 
     let arg = item.get_string().unwrap_or_default();
 
+    if arg == "all" && (merged || !filters.is_empty()) {
+        return Internal::logs_all(lines, merged, filters, server_name);
+    }
+
     if arg == "all" {
         if runner.is_empty() {
             println!("{} Cannot show logs, no processes found", *helpers::FAIL);
@@ -298,7 +376,7 @@ pub fn logs(item: &Item, lines: &usize, server_name: &String) {
             server_name,
             kind,
         }
-        .logs(lines),
+        .logs(lines, merged, filters),
         Item::Name(name) => match runner.find(name, server_name) {
             Some(id) => Internal {
                 id,
@@ -306,7 +384,7 @@ pub fn logs(item: &Item, lines: &usize, server_name: &String) {
                 server_name,
                 kind,
             }
-            .logs(lines),
+            .logs(lines, merged, filters),
             None => {
                 let matches = runner.find_partial(name, server_name);
                 if matches.is_empty() {
@@ -338,7 +416,7 @@ pub fn logs(item: &Item, lines: &usize, server_name: &String) {
                             server_name,
                             kind,
                         }
-                        .logs(lines);
+                        .logs(lines, merged, filters);
                     }
                     Err(_) => crashln!("{} Selection cancelled", *helpers::FAIL),
                 }
@@ -352,7 +430,7 @@ pub fn details(lines: &usize, server_name: &String) {
 }
 
 // combine into a single function that handles multiple
-pub fn env(item: &Item, server_name: &String) {
+pub fn env(item: &Item, server_name: &String, reveal: bool) {
     let runner: Runner = Runner::new();
     let (kind, _) = format(server_name);
 
@@ -363,7 +441,7 @@ pub fn env(item: &Item, server_name: &String) {
             server_name,
             kind,
         }
-        .env(),
+        .env(reveal),
         Item::Name(name) => match runner.find(name, server_name) {
             Some(id) => Internal {
                 id,
@@ -371,7 +449,7 @@ pub fn env(item: &Item, server_name: &String) {
                 server_name,
                 kind,
             }
-            .env(),
+            .env(reveal),
             None => crashln!("{} Process ({name}) not found", *helpers::FAIL),
         },
     }
@@ -414,15 +492,19 @@ pub fn flush(item: &Item, server_name: &String) {
             kind,
         }
         .flush(),
-        Item::Name(name) => match runner.find(name, server_name) {
-            Some(id) => Internal {
-                id,
-                runner,
-                server_name,
-                kind,
+        Item::Name(name) => match runner.find_all(name, server_name) {
+            ids if !ids.is_empty() => {
+                for id in ids {
+                    Internal {
+                        id,
+                        server_name,
+                        kind: kind.clone(),
+                        runner: Runner::new(),
+                    }
+                    .flush();
+                }
             }
-            .flush(),
-            None => crashln!("{} Process ({name}) not found", *helpers::FAIL),
+            _ => crashln!("{} Process ({name}) not found", *helpers::FAIL),
         },
     }
 }
@@ -0,0 +1,249 @@
+use pmc::config;
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+use std::{collections::BTreeMap, path::Path};
+
+/// An app from the `apps` list of a PM2 ecosystem file
+#[derive(Deserialize)]
+struct App {
+    name: Option<String>,
+    script: String,
+    #[serde(default)]
+    args: Value,
+    interpreter: Option<String>,
+    #[serde(default, alias = "node_args")]
+    interpreter_args: Value,
+    cwd: Option<String>,
+    #[serde(default)]
+    env: Map<String, Value>,
+    #[serde(default)]
+    watch: Value,
+    instances: Option<Value>,
+    max_memory_restart: Option<Value>,
+    cron_restart: Option<String>,
+    kill_timeout: Option<u64>,
+    autorestart: Option<bool>,
+    /// `env_<profile>` objects and the settings pmc has no use for
+    #[serde(flatten)]
+    rest: BTreeMap<String, Value>,
+}
+
+/// Check if a parsed file is a PM2 ecosystem file
+pub fn is_ecosystem(document: &Value) -> bool {
+    document.get("apps").is_some_and(Value::is_array)
+}
+
+fn text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Number(number) => Some(number.to_string()),
+        Value::Bool(flag) => Some(flag.to_string()),
+        _ => None,
+    }
+}
+
+/// PM2 takes arguments as a single string or a list
+fn words(value: &Value) -> Vec<String> {
+    match value {
+        Value::String(line) => line.split_whitespace().map(String::from).collect(),
+        Value::Array(list) => list.iter().filter_map(text).collect(),
+        value => text(value).into_iter().collect(),
+    }
+}
+
+/// PM2 env values may be numbers or booleans, pmc only stores strings
+fn env(values: &Map<String, Value>) -> Value {
+    values
+        .iter()
+        .filter_map(|(key, value)| text(value).map(|value| (key.clone(), Value::String(value))))
+        .collect::<Map<String, Value>>()
+        .into()
+}
+
+/// `0`, `-1` and `"max"` all spawn one instance per core in PM2
+fn instances(value: &Value) -> Value {
+    match text(value).map(|count| count.parse::<i64>()) {
+        Some(Ok(count)) if count > 0 => json!(count),
+        Some(Ok(_)) => json!("max"),
+        _ => value.clone(),
+    }
+}
+
+/// PM2 watches the working directory for `watch: true`, pmc watches a single path
+fn watch(value: &Value, cwd: &Option<String>) -> Option<String> {
+    match value {
+        Value::Bool(true) => Some(cwd.clone().unwrap_or(String::from("."))),
+        Value::String(path) => Some(path.clone()),
+        Value::Array(paths) => paths.first().and_then(text),
+        _ => None,
+    }
+}
+
+fn convert_app(app: App) -> (String, Value) {
+    let name = app.name.clone().unwrap_or_else(|| {
+        Path::new(&app.script)
+            .file_stem()
+            .map_or(app.script.clone(), |stem| {
+                stem.to_string_lossy().to_string()
+            })
+    });
+
+    // PM2 runs javascript with node unless told otherwise, `none` runs the script itself
+    let interpreter = match app.interpreter.as_deref() {
+        Some("none") => None,
+        Some(interpreter) => Some(interpreter.to_string()),
+        None => [".js", ".mjs", ".cjs", ".ts"]
+            .iter()
+            .any(|ext| app.script.ends_with(ext))
+            .then(|| config::read().runner.node),
+    };
+
+    let mut process = Map::new();
+    process.insert(String::from("script"), json!(app.script));
+    process.insert(String::from("env"), env(&app.env));
+
+    if let Some(path) = watch(&app.watch, &app.cwd) {
+        process.insert(String::from("watch"), json!({ "path": path }));
+    }
+
+    let mut options = vec![
+        ("interpreter", interpreter.map(Value::String)),
+        ("instances", app.instances.as_ref().map(instances)),
+        ("cwd", app.cwd.map(Value::String)),
+        (
+            "max_memory",
+            app.max_memory_restart
+                .as_ref()
+                .and_then(text)
+                .map(Value::String),
+        ),
+        ("cron", app.cron_restart.map(Value::String)),
+        ("kill_timeout", app.kill_timeout.map(Value::from)),
+        (
+            "restart",
+            (app.autorestart == Some(false)).then(|| json!("never")),
+        ),
+    ];
+
+    let (args, interpreter_args) = (words(&app.args), words(&app.interpreter_args));
+    options.push(("args", (!args.is_empty()).then(|| json!(args))));
+    options.push((
+        "interpreter_args",
+        (!interpreter_args.is_empty()).then(|| json!(interpreter_args)),
+    ));
+
+    for (key, value) in options {
+        if let Some(value) = value {
+            process.insert(String::from(key), value);
+        }
+    }
+
+    let profiles = app
+        .rest
+        .iter()
+        .filter_map(|(key, value)| {
+            Some((
+                key.strip_prefix("env_")?.to_string(),
+                env(value.as_object()?),
+            ))
+        })
+        .collect::<Map<String, Value>>();
+
+    if !profiles.is_empty() {
+        process.insert(String::from("env_profiles"), profiles.into());
+    }
+
+    (name, process.into())
+}
+
+/// Turn the apps of a PM2 ecosystem file into the `process` map pmc imports
+pub fn convert(mut document: Value) -> Result<Value, String> {
+    let apps = document
+        .get_mut("apps")
+        .map(Value::take)
+        .unwrap_or_default();
+    let apps: Vec<App> =
+        serde_json::from_value(apps).map_err(|err| format!("invalid PM2 app: {err}"))?;
+    let mut processes = Map::new();
+
+    for app in apps {
+        let (name, process) = convert_app(app);
+
+        if processes.insert(name.clone(), process).is_some() {
+            return Err(format!(
+                "the ecosystem file has more than one app named {name}"
+            ));
+        }
+    }
+
+    Ok(json!({ "process": processes }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_ecosystem() {
+        let document = json!({
+            "apps": [
+                {
+                    "name": "api",
+                    "script": "server.js",
+                    "interpreter": "/usr/bin/node",
+                    "node_args": "--max-old-space-size=512 --inspect",
+                    "args": ["--port", 3000],
+                    "cwd": "services/api",
+                    "instances": 0,
+                    "exec_mode": "cluster",
+                    "watch": true,
+                    "max_memory_restart": "512M",
+                    "autorestart": false,
+                    "env": { "PORT": 3000, "DEBUG": true },
+                    "env_production": { "NODE_ENV": "production", "PORT": 80 },
+                },
+                { "script": "bin/worker.sh", "interpreter": "none", "instances": 2, "kill_timeout": 1600 },
+            ]
+        });
+
+        assert!(is_ecosystem(&document));
+        let converted = convert(document).unwrap();
+
+        assert_eq!(
+            converted["process"]["api"],
+            json!({
+                "script": "server.js",
+                "interpreter": "/usr/bin/node",
+                "interpreter_args": ["--max-old-space-size=512", "--inspect"],
+                "args": ["--port", "3000"],
+                "cwd": "services/api",
+                "instances": "max",
+                "watch": { "path": "services/api" },
+                "max_memory": "512M",
+                "restart": "never",
+                "env": { "PORT": "3000", "DEBUG": "true" },
+                "env_profiles": { "production": { "NODE_ENV": "production", "PORT": "80" } },
+            })
+        );
+        assert_eq!(
+            converted["process"]["worker"],
+            json!({ "script": "bin/worker.sh", "env": {}, "instances": 2, "kill_timeout": 1600 })
+        );
+    }
+
+    #[test]
+    fn test_convert_duplicate_names() {
+        let document = json!({
+            "apps": [
+                { "name": "api", "script": "a.sh", "interpreter": "none" },
+                { "name": "api", "script": "b.sh", "interpreter": "none" },
+            ]
+        });
+
+        assert!(
+            convert(document)
+                .unwrap_err()
+                .contains("more than one app named api")
+        );
+    }
+}
@@ -3,7 +3,7 @@ pub mod structs;
 use crate::{
     file::{self, Exists},
     helpers,
-    process::RemoteConfig,
+    process::{RemoteConfig, rotate::LogRotate},
 };
 
 use colored::Colorize;
@@ -13,6 +13,7 @@ use reqwest::header::{HeaderMap, HeaderValue};
 use structs::prelude::*;
 
 use std::{
+    collections::BTreeMap,
     fs::write,
     net::{IpAddr, Ipv4Addr},
     path::Path,
@@ -53,10 +54,13 @@ pub fn read() -> Config {
                         args: vec![string!("-c")],
                         node: string!("node"),
                         log_path: format!("{path}/.pmc/logs"),
+                        log_rotate: LogRotate::default(),
                     },
                     daemon: Daemon {
                         restarts: 10,
                         interval: 1000,
+                        memory_samples: 3,
+                        cgroup: None,
                         kind: string!("default"),
                         web: Web {
                             ui: false,
@@ -70,6 +74,8 @@ pub fn read() -> Config {
                             }),
                         },
                     },
+                    secrets: Secrets::default(),
+                    sinks: BTreeMap::new(),
                 };
 
                 let contents = match toml::to_string(&config) {
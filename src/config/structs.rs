@@ -1,8 +1,9 @@
+use crate::process::{rotate::LogRotate, sink::Sink};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 
 pub mod prelude {
-    pub use super::{Config, Daemon, Runner, Secure, Server, Servers, Web};
+    pub use super::{Config, Daemon, Runner, Secrets, Secure, Server, Servers, Web};
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -10,6 +11,11 @@ pub struct Config {
     pub default: String,
     pub runner: Runner,
     pub daemon: Daemon,
+    #[serde(default)]
+    pub secrets: Secrets,
+    /// Log sinks processes can ship their output to with `log_sinks`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub sinks: BTreeMap<String, Sink>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -18,12 +24,21 @@ pub struct Runner {
     pub args: Vec<String>,
     pub node: String,
     pub log_path: String,
+    /// Log rotation used by processes that do not set their own `log_rotate`
+    #[serde(default)]
+    pub log_rotate: LogRotate,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Daemon {
     pub restarts: u64,
     pub interval: u64,
+    /// Consecutive samples over `max_memory` before a process is restarted
+    #[serde(default = "default_memory_samples")]
+    pub memory_samples: u64,
+    /// Delegated cgroup v2 directory, each process gets its own cgroup below it when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cgroup: Option<String>,
     pub kind: String,
     pub web: Web,
 }
@@ -44,6 +59,22 @@ pub struct Secure {
     pub token: String,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Secrets {
+    /// Variable names whose values are masked in env output, `*` matches any characters
+    pub mask: Vec<String>,
+}
+
+impl Default for Secrets {
+    fn default() -> Self {
+        Self {
+            mask: ["*TOKEN*", "*SECRET*", "*PASSWORD*", "*_KEY", "*CREDENTIAL*"]
+                .map(String::from)
+                .to_vec(),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Servers {
     pub servers: Option<BTreeMap<String, Server>>,
@@ -63,3 +94,7 @@ impl Server {
         }
     }
 }
+
+fn default_memory_samples() -> u64 {
+    3
+}
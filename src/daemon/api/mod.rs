@@ -9,9 +9,10 @@ use helpers::{NotFound, create_status};
 use include_dir::{Dir, include_dir};
 use lazy_static::lazy_static;
 use pmc::{config, process};
-use prometheus::{Counter, Gauge, Histogram, HistogramVec};
+use prometheus::{Counter, CounterVec, Gauge, Histogram, HistogramVec};
 use prometheus::{
-    opts, register_counter, register_gauge, register_histogram, register_histogram_vec,
+    opts, register_counter, register_counter_vec, register_gauge, register_histogram,
+    register_histogram_vec,
 };
 use serde_json::{Value, json};
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -52,6 +53,14 @@ lazy_static! {
         "The cpu usage graph of the daemon."
     )
     .unwrap();
+    pub static ref PROCESS_RESTARTS: CounterVec = register_counter_vec!(
+        opts!(
+            "process_restarts_total",
+            "Number of automatic process restarts by reason."
+        ),
+        &["name", "reason"]
+    )
+    .unwrap();
     pub static ref HTTP_REQ_HISTOGRAM: HistogramVec = register_histogram_vec!(
         "http_request_duration_seconds",
         "The HTTP request latencies in seconds.",
@@ -79,10 +88,12 @@ lazy_static! {
         routes::remote_rename,
         routes::remote_action,
         routes::logs_raw_handler,
+        routes::search_handler,
         routes::metrics_handler,
         routes::prometheus_handler,
         routes::create_handler,
-        routes::rename_handler
+        routes::rename_handler,
+        routes::scale_handler
     ),
     components(schemas(
         ErrorMessage,
@@ -93,19 +104,26 @@ lazy_static! {
         process::Watch,
         process::ItemSingle,
         process::ProcessItem,
+        process::backoff::Backoff,
+        process::backoff::Strategy,
+        process::restart::RestartRecord,
+        process::restart::RestartReason,
+        process::limits::Limits,
+        process::rotate::LogRotate,
+        process::search::SearchResult,
         routes::Stats,
         routes::Daemon,
         routes::Version,
         routes::ActionBody,
         routes::ConfigBody,
         routes::CreateBody,
+        routes::ScaleBody,
         routes::MetricsRoot,
         routes::LogResponse,
         routes::DocMemoryInfo,
         routes::ActionResponse,
     ))
 )]
-
 struct ApiDoc;
 struct Logger;
 struct AddCORS;
@@ -128,27 +146,27 @@ impl Modify for SecurityAddon {
 }
 
 #[catch(500)]
-fn internal_error<'m>() -> Json<ErrorMessage> {
+fn internal_error() -> Json<ErrorMessage> {
     create_status(Status::InternalServerError)
 }
 
 #[catch(400)]
-fn bad_request<'m>() -> Json<ErrorMessage> {
+fn bad_request() -> Json<ErrorMessage> {
     create_status(Status::BadRequest)
 }
 
 #[catch(405)]
-fn not_allowed<'m>() -> Json<ErrorMessage> {
+fn not_allowed() -> Json<ErrorMessage> {
     create_status(Status::MethodNotAllowed)
 }
 
 #[catch(404)]
-fn not_found<'m>() -> Json<ErrorMessage> {
+fn not_found() -> Json<ErrorMessage> {
     create_status(Status::NotFound)
 }
 
 #[catch(401)]
-fn unauthorized<'m>() -> Json<ErrorMessage> {
+fn unauthorized() -> Json<ErrorMessage> {
     create_status(Status::Unauthorized)
 }
 
@@ -236,6 +254,7 @@ pub async fn start(webui: bool) {
         routes::list_handler,
         routes::logs_handler,
         routes::logs_raw_handler,
+        routes::search_handler,
         routes::logs_ws,
         routes::metrics_handler,
         routes::remote_metrics,
@@ -244,6 +263,7 @@ pub async fn start(webui: bool) {
         routes::prometheus_handler,
         routes::create_handler,
         routes::rename_handler,
+        routes::scale_handler,
         routes::remote_logs_ws,
     ];
 
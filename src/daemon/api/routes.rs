@@ -38,7 +38,14 @@ use super::{
 use pmc::{
     config, file, helpers,
     process::{
-        ItemSingle, ProcessItem, Runner, dump, get_process_cpu_usage_percentage, http::client,
+        ItemSingle, Options, ProcessItem, Runner,
+        backoff::Backoff,
+        dump, get_process_cpu_usage_percentage,
+        http::client,
+        instances::Instances,
+        rotate::{self, LogRotate},
+        search::{self, Filters, SearchResult},
+        secrets,
     },
 };
 
@@ -52,7 +59,7 @@ use std::{
     env,
     fs::{self, File},
     io::{self, BufRead, BufReader},
-    path::PathBuf,
+    path::{Path, PathBuf},
     thread::sleep,
     time::Duration,
 };
@@ -85,6 +92,12 @@ pub(crate) struct ActionBody {
     method: String,
 }
 
+#[derive(Deserialize, ToSchema)]
+pub(crate) struct ScaleBody {
+    #[schema(value_type = String, example = "4")]
+    instances: Instances,
+}
+
 #[derive(Serialize, ToSchema)]
 pub(crate) struct ConfigBody {
     #[schema(example = "bash")]
@@ -93,6 +106,7 @@ pub(crate) struct ConfigBody {
     args: Vec<String>,
     #[schema(example = "/home/user/.pmc/logs")]
     log_path: String,
+    log_rotate: LogRotate,
 }
 
 #[derive(Serialize, Deserialize, ToSchema)]
@@ -105,6 +119,12 @@ pub(crate) struct CreateBody {
     path: PathBuf,
     #[schema(example = "src")]
     watch: Option<String>,
+    #[schema(value_type = Option<String>, example = "max")]
+    instances: Option<Instances>,
+    #[serde(flatten)]
+    options: Options,
+    #[serde(default)]
+    backoff: Backoff,
 }
 
 #[derive(Serialize, Deserialize, ToSchema)]
@@ -594,7 +614,7 @@ pub async fn remote_action(
 #[get("/daemon/dump")]
 #[utoipa::path(get, tag = "Daemon", path = "/daemon/dump", security((), ("api_key" = [])),
     responses(
-        (status = 200, description = "Dump processes successfully", body = [u8]),
+        (status = 200, description = "Dump processes successfully, secret env values are masked", body = [u8]),
         (
             status = UNAUTHORIZED, description = "Authentication failed or not provided", body = ErrorMessage,
             example = json!({"code": 401, "message": "Unauthorized"})
@@ -606,10 +626,15 @@ pub async fn dump_handler(_t: Token) -> Vec<u8> {
         .with_label_values(&["dump"])
         .start_timer();
 
+    let mut runner = dump::read();
+    secrets::mask_runner(&mut runner, &secrets::patterns());
+
     HTTP_COUNTER.inc();
     timer.observe_duration();
 
-    dump::raw()
+    ron::ser::to_string(&runner)
+        .unwrap_or_default()
+        .into_bytes()
 }
 
 #[get("/daemon/config")]
@@ -635,6 +660,7 @@ pub async fn config_handler(_t: Token) -> Json<ConfigBody> {
         shell: config.shell,
         args: config.args,
         log_path: config.log_path,
+        log_rotate: config.log_rotate,
     })
 }
 
@@ -756,6 +782,85 @@ pub async fn logs_raw_handler(id: usize, kind: String, _t: Token) -> Result<Stri
     }
 }
 
+#[get("/process/<id>/logs/<kind>/search?<grep>&<since>&<until>&<level>&<limit>")]
+#[utoipa::path(get, tag = "Process", path = "/process/{id}/logs/{kind}/search",
+    security((), ("api_key" = [])),
+    params(
+        ("id" = usize, Path, description = "Process id to search logs of", example = 0),
+        ("kind" = String, Path, description = "Log output type", example = "error"),
+        ("grep" = Option<String>, Query, description = "Regex the message has to match", example = "timeout|refused"),
+        ("since" = Option<String>, Query, description = "Only lines after this time, a span back from now or a date", example = "1h"),
+        ("until" = Option<String>, Query, description = "Only lines before this time, a span back from now or a date", example = "2026-10-17 12:00"),
+        ("level" = Option<String>, Query, description = "Only lines with this level or a more severe one", example = "error"),
+        ("limit" = Option<usize>, Query, description = "Number of matching lines returned, the newest are kept", example = 100)
+    ),
+    responses(
+        (status = 200, description = "Matching lines of the log and its rotated files", body = SearchResult),
+        (
+            status = BAD_REQUEST, description = "Invalid regex, time or level", body = ErrorMessage,
+            example = json!({"code": 400, "message": "invalid level 'loud', expected trace, debug, info, warn, error or fatal"})
+        ),
+        (status = NOT_FOUND, description = "Process was not found", body = ErrorMessage),
+        (
+            status = UNAUTHORIZED, description = "Authentication failed or not provided", body = ErrorMessage,
+            example = json!({"code": 401, "message": "Unauthorized"})
+        )
+    )
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn search_handler(
+    id: usize,
+    kind: String,
+    grep: Option<String>,
+    since: Option<String>,
+    until: Option<String>,
+    level: Option<String>,
+    limit: Option<usize>,
+    _t: Token,
+) -> Result<Json<SearchResult>, GenericError> {
+    let timer = HTTP_REQ_HISTOGRAM.with_label_values(&["log"]).start_timer();
+    let filters = Filters {
+        grep,
+        since,
+        until,
+        level,
+    };
+
+    HTTP_COUNTER.inc();
+    let query = match filters.compile() {
+        Ok(query) => query,
+        Err(err) => {
+            timer.observe_duration();
+            return Err(generic_error(Status::BadRequest, err));
+        }
+    };
+
+    match Runner::new().info(id) {
+        Some(item) => {
+            let log_file = match kind.as_str() {
+                "error" | "stderr" => item.logs().error,
+                _ => item.logs().out,
+            };
+
+            let result = search::search(
+                Path::new(&log_file),
+                &query,
+                limit.unwrap_or(search::DEFAULT_LIMIT),
+            );
+
+            timer.observe_duration();
+            Ok(Json(result))
+        }
+        None => {
+            timer.observe_duration();
+            Err(generic_error(
+                Status::NotFound,
+                string!("Process was not found"),
+            ))
+        }
+    }
+}
+
 #[get("/process/<id>/logs/<kind>/ws?<tail>&<token>")]
 pub async fn logs_ws(
     id: usize,
@@ -810,6 +915,18 @@ pub async fn logs_ws(
         loop {
             tokio_sleep(TokioDuration::from_millis(500)).await;
 
+            // rotation copies the file away and truncates it, lines written before that are read from the copy
+            if file.metadata().await.is_ok_and(|metadata| metadata.len() < position) {
+                let rest = rotate::rotated_since(Path::new(&log_file), position).unwrap_or_default();
+                position = 0;
+
+                for line in String::from_utf8_lossy(&rest).lines() {
+                    if stream.send(WsOut::Text(json!({"type": "line", "line": line}).to_string())).await.is_err() {
+                        return Ok(());
+                    }
+                }
+            }
+
             if file.seek(SeekFrom::Start(position)).await.is_err() {
                 let _ = stream.send(WsOut::Text(json!({"type": "error", "message": "Failed to seek log file"}).to_string())).await;
                 break;
@@ -878,13 +995,21 @@ pub async fn info_handler(id: usize, _t: Token) -> Result<Json<ItemSingle>, NotF
             example = json!({"action": "create", "done": true }), status = 200,
         ),
         (status = INTERNAL_SERVER_ERROR, description = "Failed to create process", body = ErrorMessage),
+        (status = BAD_REQUEST, description = "An env file could not be read or the env profile is not defined", body = ErrorMessage),
+        (
+            status = FORBIDDEN, description = "The daemon is not allowed to run as the user or group or to set the limits", body = ErrorMessage,
+            example = json!({"code": 403, "message": "user 'www-data' does not exist"})
+        ),
         (
             status = UNAUTHORIZED, description = "Authentication failed or not provided", body = ErrorMessage,
             example = json!({"code": 401, "message": "Unauthorized"})
         )
     )
 )]
-pub async fn create_handler(body: Json<CreateBody>, _t: Token) -> Result<Json<ActionResponse>, ()> {
+pub async fn create_handler(
+    body: Json<CreateBody>,
+    _t: Token,
+) -> Result<Json<ActionResponse>, GenericError> {
     let timer = HTTP_REQ_HISTOGRAM
         .with_label_values(&["create"])
         .start_timer();
@@ -892,13 +1017,36 @@ pub async fn create_handler(body: Json<CreateBody>, _t: Token) -> Result<Json<Ac
 
     HTTP_COUNTER.inc();
 
+    if let Err(err) = body.options.check() {
+        timer.observe_duration();
+        return Err(generic_error(Status::Forbidden, err));
+    }
+
+    if let Err(err) = body
+        .options
+        .check_env_profile()
+        .and_then(|_| body.options.check_log_sinks())
+        .and_then(|_| body.options.check_env_files(&body.path))
+    {
+        timer.observe_duration();
+        return Err(generic_error(Status::BadRequest, err));
+    }
+
     let name = match &body.name {
         Some(name) => string!(name),
         None => string!(body.script.split_whitespace().next().unwrap_or_default()),
     };
 
     runner
-        .start(&name, &body.script, body.path.clone(), &body.watch)
+        .start(
+            &name,
+            &body.script,
+            body.path.clone(),
+            &body.watch,
+            &body.instances.unwrap_or_default(),
+            &body.options,
+            &body.backoff,
+        )
         .save();
     timer.observe_duration();
 
@@ -916,6 +1064,7 @@ pub async fn create_handler(body: Json<CreateBody>, _t: Token) -> Result<Json<Ac
             example = json!({"action": "rename", "done": true }), status = 200,
         ),
         (status = NOT_FOUND, description = "Process was not found", body = ErrorMessage),
+        (status = INTERNAL_SERVER_ERROR, description = "Process failed to restart", body = ErrorMessage),
         (
             status = UNAUTHORIZED, description = "Authentication failed or not provided", body = ErrorMessage,
             example = json!({"code": 401, "message": "Unauthorized"})
@@ -926,7 +1075,7 @@ pub async fn rename_handler(
     id: usize,
     body: String,
     _t: Token,
-) -> Result<Json<ActionResponse>, NotFound> {
+) -> Result<Json<ActionResponse>, GenericError> {
     let timer = HTTP_REQ_HISTOGRAM
         .with_label_values(&["rename"])
         .start_timer();
@@ -937,44 +1086,131 @@ pub async fn rename_handler(
             HTTP_COUNTER.inc();
             let mut item = runner.get(id);
             item.rename(body.trim().replace("\n", ""));
-            then!(process.running, item.restart());
+            let restarted = ternary!(process.running, item.restart(), Ok(()));
             timer.observe_duration();
-            Ok(Json(attempt(true, "rename")))
+
+            match restarted {
+                Ok(()) => Ok(Json(attempt(true, "rename"))),
+                Err(err) => Err(generic_error(Status::InternalServerError, err)),
+            }
         }
         None => {
             timer.observe_duration();
-            Err(not_found("Process was not found"))
+            Err(generic_error(
+                Status::NotFound,
+                string!("Process was not found"),
+            ))
         }
     }
 }
 
-#[get("/process/<id>/env")]
+#[post("/process/<id>/scale", format = "json", data = "<body>")]
+#[utoipa::path(post, tag = "Process", path = "/process/{id}/scale", request_body = ScaleBody,
+    security((), ("api_key" = [])),
+    params(("id" = usize, Path, description = "Process id of the group to scale", example = 0)),
+    responses(
+        (
+            description = "Scale process group successful", body = ActionResponse,
+            example = json!({"action": "scale", "done": true }), status = 200,
+        ),
+        (status = NOT_FOUND, description = "Process was not found", body = ErrorMessage),
+        (status = INTERNAL_SERVER_ERROR, description = "New instances failed to start", body = ErrorMessage),
+        (
+            status = UNAUTHORIZED, description = "Authentication failed or not provided", body = ErrorMessage,
+            example = json!({"code": 401, "message": "Unauthorized"})
+        )
+    )
+)]
+pub async fn scale_handler(
+    id: usize,
+    body: Json<ScaleBody>,
+    _t: Token,
+) -> Result<Json<ActionResponse>, GenericError> {
+    let timer = HTTP_REQ_HISTOGRAM
+        .with_label_values(&["scale"])
+        .start_timer();
+    let mut runner = Runner::new();
+
+    if runner.exists(id) {
+        HTTP_COUNTER.inc();
+        // instances started before a failure are kept
+        let scaled = runner.scale(id, &body.instances).map(|_| ());
+        runner.save();
+        timer.observe_duration();
+
+        match scaled {
+            Ok(()) => Ok(Json(attempt(true, "scale"))),
+            Err(err) => Err(generic_error(Status::InternalServerError, err)),
+        }
+    } else {
+        timer.observe_duration();
+        Err(generic_error(
+            Status::NotFound,
+            string!("Process was not found"),
+        ))
+    }
+}
+
+#[get("/process/<id>/env?<reveal>")]
 #[utoipa::path(get, tag = "Process", path = "/process/{id}/env",
-    params(("id" = usize, Path, description = "Process id to fetch env from", example = 0)),
+    params(
+        ("id" = usize, Path, description = "Process id to fetch env from", example = 0),
+        ("reveal" = Option<bool>, Query, description = "Show the values of secret variables, needs the api token to be enabled", example = false)
+    ),
     responses(
         (
-            description = "Current process env", body = HashMap<String, String>,
-            example = json!({"ENV_TEST_VALUE": "example_value"}), status = 200
+            description = "Current process env, secret values are masked", body = HashMap<String, String>,
+            example = json!({"ENV_TEST_VALUE": "example_value", "API_TOKEN": "********"}), status = 200
         ),
         (status = NOT_FOUND, description = "Process was not found", body = ErrorMessage),
+        (
+            status = FORBIDDEN, description = "Secrets can only be revealed when the api token is enabled", body = ErrorMessage,
+            example = json!({"code": 403, "message": "Revealing secrets requires the api token to be enabled"})
+        ),
         (
             status = UNAUTHORIZED, description = "Authentication failed or not provided", body = ErrorMessage,
             example = json!({"code": 401, "message": "Unauthorized"})
         )
     )
 )]
-pub async fn env_handler(id: usize, _t: Token) -> Result<EnvList, NotFound> {
+pub async fn env_handler(
+    id: usize,
+    reveal: Option<bool>,
+    _t: Token,
+) -> Result<EnvList, GenericError> {
     let timer = HTTP_REQ_HISTOGRAM.with_label_values(&["env"]).start_timer();
+    let reveal = reveal.unwrap_or(false);
+
+    // without a token anyone who can reach the api would be able to read the secrets
+    if reveal
+        && !config::read()
+            .daemon
+            .web
+            .secure
+            .is_some_and(|secure| secure.enabled)
+    {
+        timer.observe_duration();
+        return Err(generic_error(
+            Status::Forbidden,
+            string!("Revealing secrets requires the api token to be enabled"),
+        ));
+    }
 
     HTTP_COUNTER.inc();
     match Runner::new().info(id) {
         Some(item) => {
+            let mut env = item.full_env();
+            then!(!reveal, secrets::mask(&mut env, &secrets::patterns()));
+
             timer.observe_duration();
-            Ok(Json(item.clone().env))
+            Ok(Json(env))
         }
         None => {
             timer.observe_duration();
-            Err(not_found("Process was not found"))
+            Err(generic_error(
+                Status::NotFound,
+                string!("Process was not found"),
+            ))
         }
     }
 }
@@ -986,6 +1222,7 @@ pub async fn env_handler(id: usize, _t: Token) -> Result<EnvList, NotFound> {
     responses(
         (status = 200, description = "Run action on process successful", body = ActionResponse),
         (status = NOT_FOUND, description = "Process/action was not found", body = ErrorMessage),
+        (status = INTERNAL_SERVER_ERROR, description = "Process failed to start or reloaded process did not become ready", body = ErrorMessage),
         (
             status = UNAUTHORIZED, description = "Authentication failed or not provided", body = ErrorMessage,
             example = json!({"code": 401, "message": "Unauthorized"})
@@ -996,7 +1233,7 @@ pub async fn action_handler(
     id: usize,
     body: Json<ActionBody>,
     _t: Token,
-) -> Result<Json<ActionResponse>, NotFound> {
+) -> Result<Json<ActionResponse>, GenericError> {
     let timer = HTTP_REQ_HISTOGRAM
         .with_label_values(&["action"])
         .start_timer();
@@ -1007,9 +1244,22 @@ pub async fn action_handler(
         HTTP_COUNTER.inc();
         match method {
             "start" | "restart" => {
-                runner.get(id).restart();
+                let restarted = runner.get(id).restart();
                 timer.observe_duration();
-                Ok(Json(attempt(true, method)))
+
+                match restarted {
+                    Ok(()) => Ok(Json(attempt(true, method))),
+                    Err(err) => Err(generic_error(Status::InternalServerError, err)),
+                }
+            }
+            "reload" => {
+                let reloaded = runner.reload(id).map(|runner| runner.save());
+                timer.observe_duration();
+
+                match reloaded {
+                    Ok(_) => Ok(Json(attempt(true, method))),
+                    Err(err) => Err(generic_error(Status::InternalServerError, err)),
+                }
             }
             "stop" | "kill" => {
                 runner.get(id).stop();
@@ -1033,11 +1283,17 @@ pub async fn action_handler(
             }
             _ => {
                 timer.observe_duration();
-                Err(not_found("Invalid action attempt"))
+                Err(generic_error(
+                    Status::NotFound,
+                    string!("Invalid action attempt"),
+                ))
             }
         }
     } else {
-        Err(not_found("Process was not found"))
+        Err(generic_error(
+            Status::NotFound,
+            string!("Process was not found"),
+        ))
     }
 }
 
@@ -1239,19 +1495,19 @@ pub async fn remote_logs_ws(
             match connect_async(url).await {
                 Ok((mut upstream, _)) => {
                     while let Some(msg) = upstream.next().await {
-                        match msg {
+                        let sent = match msg {
                             Ok(UpstreamMessage::Text(text)) => {
-                                if stream.send(WsOut::Text(text.to_string())).await.is_err() {
-                                    return Ok(());
-                                }
+                                stream.send(WsOut::Text(text.to_string())).await
                             }
                             Ok(UpstreamMessage::Binary(bin)) => {
-                                if stream.send(WsOut::Binary(bin.to_vec())).await.is_err() {
-                                    return Ok(());
-                                }
+                                stream.send(WsOut::Binary(bin.to_vec())).await
                             }
                             Ok(UpstreamMessage::Close(_)) => break,
-                            _ => {}
+                            _ => continue,
+                        };
+
+                        if sent.is_err() {
+                            return Ok(());
                         }
                     }
                 }
@@ -0,0 +1,274 @@
+use macros_rs::then;
+use pmc::{
+    config,
+    process::{Process, Runner},
+};
+
+use std::{
+    collections::HashMap,
+    fs::OpenOptions,
+    io::{self, Write},
+    process::{Child, Command, Stdio},
+    sync::{
+        Arc, LazyLock, Mutex, MutexGuard,
+        mpsc::{self, SyncSender, TrySendError},
+    },
+    thread::{self, sleep},
+    time::{Duration, Instant},
+};
+
+/// Delay before a pipe command that exited is started again, doubled after every exit
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(300);
+
+/// A pipe command that stays up this long gets the shortest restart delay again
+const STABLE: Duration = Duration::from_secs(60);
+
+/// Time a pipe command gets to flush and exit after its input is closed
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Lines waiting for a pipe command, more than this go to the log file until it catches up
+const QUEUE_LINES: usize = 1024;
+
+/// Queue feeding the input of a pipe command, a writer thread drains it so a stalled command never blocks the relay
+type Input = Arc<SyncSender<String>>;
+
+/// The `log_pipe` command of a process and its state
+struct LogPipe {
+    command: String,
+    child: Option<Child>,
+    input: Option<Input>,
+    started: Instant,
+    restart_at: Instant,
+    delay: Duration,
+    /// Set while the queue is full, so the switch to the log file is only logged once
+    stalled: bool,
+}
+
+/// Pipe commands keyed by process id
+static PIPES: LazyLock<Mutex<HashMap<usize, LogPipe>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn pipes() -> MutexGuard<'static, HashMap<usize, LogPipe>> {
+    PIPES.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+impl LogPipe {
+    fn new(command: &str) -> Self {
+        Self {
+            command: command.to_string(),
+            child: None,
+            input: None,
+            started: Instant::now(),
+            restart_at: Instant::now(),
+            delay: RESTART_DELAY,
+            stalled: false,
+        }
+    }
+
+    /// Run the command through the configured shell, its own output goes to the log files of the process
+    fn spawn(&mut self, item: &Process) -> io::Result<()> {
+        let config = config::read().runner;
+        let logs = item.logs();
+        let append = |path: &str| OpenOptions::new().create(true).append(true).open(path);
+
+        let child = Command::new(&config.shell)
+            .args(&config.args)
+            .arg(&self.command)
+            .current_dir(item.cwd())
+            .env("PMC_ID", item.id.to_string())
+            .env("PMC_NAME", &item.name)
+            .stdin(Stdio::piped())
+            .stdout(append(&logs.out)?)
+            .stderr(append(&logs.error)?)
+            .spawn()?;
+
+        self.attach(child);
+        Ok(())
+    }
+
+    /// Take over a started command, its input is written from a thread fed through a bounded queue
+    fn attach(&mut self, mut child: Child) {
+        self.input = child.stdin.take().map(|mut stdin| {
+            let (sender, lines) = mpsc::sync_channel::<String>(QUEUE_LINES);
+
+            // ends when the command stops reading for good or the queue is dropped, closing the input
+            thread::spawn(move || {
+                for line in lines {
+                    then!(stdin.write_all(line.as_bytes()).is_err(), break);
+                }
+            });
+
+            Arc::new(sender)
+        });
+
+        self.child = Some(child);
+        self.started = Instant::now();
+        self.stalled = false;
+    }
+
+    /// Close the input and give the command a moment to exit before it is killed
+    fn stop(&mut self) {
+        self.input = None;
+
+        if let Some(mut child) = self.child.take() {
+            thread::spawn(move || {
+                let deadline = Instant::now() + STOP_TIMEOUT;
+
+                while Instant::now() < deadline {
+                    if !matches!(child.try_wait(), Ok(None)) {
+                        return;
+                    }
+                    sleep(Duration::from_millis(100));
+                }
+
+                let _ = child.kill();
+                let _ = child.wait();
+            });
+        }
+    }
+
+    /// Plan the next start after the command exited or failed to start
+    fn backoff(&mut self) -> Duration {
+        let delay = self.delay;
+        self.restart_at = Instant::now() + delay;
+        self.delay = (delay * 2).min(MAX_RESTART_DELAY);
+        delay
+    }
+}
+
+/// Start the pipe commands of running processes, restart the ones that exited and stop the ones no longer needed
+pub fn supervise(runner: &Runner) {
+    let mut pipes = pipes();
+
+    pipes.retain(|id, pipe| {
+        let needed = runner.list.get(id).is_some_and(|item| {
+            item.running && item.options.log_pipe.as_ref() == Some(&pipe.command)
+        });
+
+        then!(!needed, pipe.stop());
+        needed
+    });
+
+    for item in runner.list.values().filter(|item| item.running) {
+        let Some(command) = &item.options.log_pipe else {
+            continue;
+        };
+
+        let pipe = pipes
+            .entry(item.id)
+            .or_insert_with(|| LogPipe::new(command));
+
+        if let Some(child) = &mut pipe.child {
+            match child.try_wait() {
+                Ok(None) => {
+                    then!(pipe.started.elapsed() >= STABLE, pipe.delay = RESTART_DELAY);
+                    continue;
+                }
+                Ok(Some(status)) => {
+                    pipe.input = None;
+                    pipe.child = None;
+
+                    let delay = pipe.backoff();
+                    log!("[log pipe] exited, writing to the log files", "name" => item.name, "command" => pipe.command, "status" => status, "restart in" => format!("{}s", delay.as_secs()));
+                    continue;
+                }
+                Err(err) => {
+                    log!("[log pipe] status check failed", "name" => item.name, "error" => err);
+                    continue;
+                }
+            }
+        }
+
+        then!(Instant::now() < pipe.restart_at, continue);
+
+        match pipe.spawn(item) {
+            Ok(()) => log!("[log pipe] started", "name" => item.name, "command" => pipe.command),
+            Err(err) => {
+                let delay = pipe.backoff();
+                log!("[log pipe] failed to start, writing to the log files", "name" => item.name, "command" => pipe.command, "error" => err, "restart in" => format!("{}s", delay.as_secs()));
+            }
+        }
+    }
+}
+
+/// Feed a line to the pipe command of process `id`, false when the line has to go to the log file instead
+pub fn write(id: usize, name: &str, line: &str) -> bool {
+    let Some(input) = pipes().get(&id).and_then(|pipe| pipe.input.clone()) else {
+        return false;
+    };
+
+    let sent = input.try_send(format!("{line}\n"));
+    let mut pipes = pipes();
+    let Some(pipe) = pipes.get_mut(&id).filter(|pipe| {
+        pipe.input
+            .as_ref()
+            .is_some_and(|current| Arc::ptr_eq(current, &input))
+    }) else {
+        return sent.is_ok();
+    };
+
+    match sent {
+        Ok(()) => {
+            then!(
+                pipe.stalled,
+                log!("[log pipe] caught up, writing to the command again", "name" => name, "command" => pipe.command)
+            );
+            pipe.stalled = false;
+            true
+        }
+        Err(TrySendError::Full(_)) => {
+            then!(
+                !pipe.stalled,
+                log!("[log pipe] stopped reading, writing to the log files", "name" => name, "command" => pipe.command, "queued" => QUEUE_LINES)
+            );
+            pipe.stalled = true;
+            false
+        }
+        // the exit is logged once the command is reaped, a command that only closed its input is noted here
+        Err(TrySendError::Disconnected(_)) => {
+            pipe.input = None;
+            log!("[log pipe] closed its input, writing to the log files", "name" => name, "command" => pipe.command);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stalled_command_does_not_block() {
+        let log = std::env::temp_dir().join(format!("pmc-log-pipe-{}.log", std::process::id()));
+        global_placeholders::init!("pmc.daemon.log", log.display());
+
+        // a command that never reads its input
+        let child = Command::new("sh")
+            .args(["-c", "sleep 30"])
+            .stdin(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let id = usize::MAX;
+        let mut pipe = LogPipe::new("sleep 30");
+        pipe.attach(child);
+        pipes().insert(id, pipe);
+
+        let line = "x".repeat(1024);
+        let started = Instant::now();
+        let fed = (0..QUEUE_LINES * 2)
+            .filter(|_| write(id, "stalled", &line))
+            .count();
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!((QUEUE_LINES..QUEUE_LINES * 2).contains(&fed));
+        assert!(pipes()[&id].stalled);
+
+        if let Some(mut child) = pipes().remove(&id).and_then(|mut pipe| pipe.child.take()) {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        let _ = std::fs::remove_file(log);
+    }
+}
@@ -2,9 +2,12 @@
 mod log;
 mod api;
 mod fork;
+mod log_pipe;
+mod relay;
+mod shipper;
 
-use api::{DAEMON_CPU_PERCENTAGE, DAEMON_MEM_USAGE, DAEMON_START_TIME};
-use chrono::{DateTime, Utc};
+use api::{DAEMON_CPU_PERCENTAGE, DAEMON_MEM_USAGE, DAEMON_START_TIME, PROCESS_RESTARTS};
+use chrono::{DateTime, Local, Utc};
 use colored::Colorize;
 use fork::{Fork, daemon};
 use global_placeholders::global;
@@ -14,12 +17,15 @@ use pmc::process::{MemoryInfo, unix::NativeProcess as Process};
 use serde::Serialize;
 use serde_json::json;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::{process, thread::sleep, time::Duration};
+use std::{collections::HashMap, process, thread::sleep, time::Duration};
 
 use pmc::{
     config, file,
     helpers::{self, ColoredString},
-    process::{Runner, Status, get_process_cpu_usage_percentage, hash, id::Id},
+    process::{
+        Process as Item, Runner, Status, exit::ExitStatus, get_process_cpu_usage_percentage, hash,
+        id::Id, process_tree_memory, restart::RestartReason, rotate,
+    },
 };
 
 use tabled::{
@@ -41,9 +47,33 @@ extern "C" fn handle_termination_signal(_: libc::c_int) {
     unsafe { libc::_exit(0) }
 }
 
-fn restart_process() {
+fn record_restart(runner: &mut Runner, item: &Item, reason: RestartReason) {
+    runner.record_restart(item.id, reason);
+    PROCESS_RESTARTS
+        .with_label_values(&[&item.name, &reason.to_string()])
+        .inc();
+}
+
+/// Check if the cron schedule of the process already fired during the current minute
+fn cron_fired(item: &Item, now: DateTime<Local>) -> bool {
+    item.history
+        .iter()
+        .rev()
+        .find(|record| record.reason == RestartReason::Cron)
+        .is_some_and(|record| record.at.timestamp() / 60 == now.timestamp() / 60)
+}
+
+/// Check if a process whose pid is gone is brought back, stopped processes stay down
+fn should_restart(item: &Item, status: Option<&ExitStatus>) -> bool {
+    item.running && item.options.restart.should_restart(status)
+}
+
+fn restart_process(over_memory: &mut HashMap<usize, u64>) {
     for (id, item) in Runner::new().items_mut() {
         let mut runner = Runner::new();
+
+        // removed since the loop started, like the instances dropped by a scale down
+        then!(!runner.exists(*id), continue);
         let children = pmc::process::process_find_children(item.pid);
 
         if !children.is_empty() && children != item.children {
@@ -51,40 +81,175 @@ fn restart_process() {
             runner.set_children(*id, children).save();
         }
 
+        // a stopped process stays stopped, finished jobs and running processes follow the schedule
+        if let Some(cron) = &item.options.cron
+            && (item.running || item.exited || item.crash.crashed)
+            && cron.matches(&Local::now())
+            && !cron_fired(item, Local::now())
+        {
+            let alive = item.running && pid::running(item.pid as i32);
+
+            record_restart(&mut runner, item, RestartReason::Cron);
+            let restarted = runner.restart(item.id, false).map(|_| ());
+            runner.save();
+
+            if let Err(err) = restarted {
+                log!("[daemon] cron restart failed", "name" => item.name, "id" => id, "error" => err);
+                continue;
+            }
+
+            log!("[daemon] cron triggered", "name" => item.name, "id" => id, "cron" => cron, "action" => ternary!(alive, "restart", "start"));
+            continue;
+        }
+
         if item.running && item.watch.enabled {
             let path = item.path.join(item.watch.path.clone());
             let hash = hash::create(path);
 
             if hash != item.watch.hash {
-                runner.restart(item.id, false);
+                if let Err(err) = runner.restart(item.id, false) {
+                    runner.save();
+                    log!("[daemon] watch reload failed", "name" => item.name, "id" => id, "error" => err);
+                    continue;
+                }
+
                 log!("[daemon] watch reload", "name" => item.name, "hash" => "hash");
                 continue;
             }
         }
 
+        if let Some(max_memory) = item.options.max_memory
+            && item.running
+            && pid::running(item.pid as i32)
+        {
+            let memory = process_tree_memory(item.pid);
+            let samples = over_memory.entry(*id).or_default();
+            *samples = ternary!(memory > max_memory.0, *samples + 1, 0);
+
+            if *samples >= config::read().daemon.memory_samples {
+                over_memory.remove(id);
+                record_restart(&mut runner, item, RestartReason::Memory);
+
+                if let Err(err) = runner.restart(item.id, false) {
+                    runner.save();
+                    log!("[daemon] memory limit restart failed", "name" => item.name, "id" => id, "error" => err);
+                    continue;
+                }
+
+                runner.process(*id).restarts += 1;
+                runner.save();
+                log!("[daemon] memory limit restart", "name" => item.name, "id" => id, "memory" => helpers::format_memory(memory), "limit" => max_memory);
+                continue;
+            }
+        } else {
+            over_memory.remove(id);
+        }
+
         if !item.running && pid::running(item.pid as i32) {
             Runner::new().set_status(*id, Status::Running);
             log!("[daemon] process fix status", "name" => item.name, "id" => id);
             continue;
         }
 
+        if item.running
+            && item.crash.value > 0
+            && item.crash.backoff.is_stable(item.started)
+            && pid::running(item.pid as i32)
+        {
+            runner.reset_crashes(*id).save();
+            log!("[daemon] crashes reset", "name" => item.name, "id" => id);
+        }
+
         then!(!item.running || pid::running(item.pid as i32), continue);
 
+        // first tick that finds the process gone, its status is recorded when it was reaped
+        if item.crash.restart_at.is_none() {
+            let status = item.exit_status();
+
+            let code = status.and_then(|status| status.code);
+            let signal = status.and_then(|status| status.signal);
+
+            runner.set_exit_status(*id, status).save();
+            log!("[daemon] process exited", "name" => item.name, "id" => id, "code" => format!("{code:?}"), "signal" => format!("{signal:?}"));
+
+            if !should_restart(item, status.as_ref()) {
+                runner.set_exited(*id).save();
+                log!("[daemon] process kept down", "name" => item.name, "id" => id, "policy" => item.options.restart);
+                continue;
+            }
+        }
+
         if item.running && item.crash.value == config::read().daemon.restarts {
             log!("[daemon] process has crashed", "name" => item.name, "id" => id);
             runner.stop(item.id);
             runner.set_crashed(*id).save();
             continue;
-        } else {
-            runner.get(item.id).crashed();
-            log!("[daemon] restarted", "name" => item.name, "id" => id, "crashes" => item.crash.value);
+        }
+
+        let delay = item.crash.backoff.delay(item.crash.value + 1);
+
+        match item.crash.restart_at {
+            None if !delay.is_zero() => {
+                runner.set_restart_at(*id, Some(Utc::now() + delay)).save();
+                log!("[daemon] restart delayed", "name" => item.name, "id" => id, "delay" => format!("{}ms", delay.as_millis()));
+            }
+            Some(restart_at) if restart_at > Utc::now() => continue,
+            _ => {
+                record_restart(&mut runner, item, RestartReason::Crash);
+
+                if let Err(err) = runner.get(item.id).crashed() {
+                    log!("[daemon] restart failed", "name" => item.name, "id" => id, "error" => err);
+                    continue;
+                }
+
+                log!("[daemon] restarted", "name" => item.name, "id" => id, "crashes" => item.crash.value);
+            }
         }
     }
 }
 
-fn capture_initial_logs() {
+/// Append the complete lines written after `start_pos` until 100 initial lines are captured
+fn capture_lines(path: &str, start_pos: &mut u64, lines: &mut Vec<String>) -> bool {
     use std::io::{BufRead, BufReader, Seek, SeekFrom};
 
+    let Ok(mut f) = std::fs::File::open(path) else {
+        return false;
+    };
+
+    // the file was flushed or rotated since the last capture
+    if f.metadata()
+        .is_ok_and(|metadata| metadata.len() < *start_pos)
+    {
+        *start_pos = 0;
+    }
+
+    if f.seek(SeekFrom::Start(*start_pos)).is_err() {
+        return false;
+    }
+
+    let mut reader = BufReader::new(f);
+    let mut line = Vec::new();
+    let captured = lines.len();
+
+    while lines.len() < 100 {
+        line.clear();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(read) if read > 0 && line.ends_with(b"\n") => {
+                *start_pos += read as u64;
+                lines.push(
+                    String::from_utf8_lossy(&line)
+                        .trim_end_matches(['\n', '\r'])
+                        .to_string(),
+                );
+            }
+            _ => break,
+        }
+    }
+
+    lines.len() > captured
+}
+
+fn capture_initial_logs() {
     let mut runner = Runner::new();
     let mut changed = false;
 
@@ -97,37 +262,54 @@ fn capture_initial_logs() {
         }
 
         let logs = item.logs();
+        let initial = &mut item.initial_logs;
 
-        if item.initial_logs.out.len() < 100 {
-            if let Ok(mut f) = std::fs::File::open(&logs.out) {
-                if f.seek(SeekFrom::Start(item.initial_logs.start_pos_out)).is_ok() {
-                    let reader = BufReader::new(f);
-                    let lines: Vec<String> = reader
-                        .lines()
-                        .take(100)
-                        .filter_map(|l| l.ok())
-                        .collect();
-                    if lines.len() > item.initial_logs.out.len() {
-                        item.initial_logs.out = lines;
-                        changed = true;
-                    }
-                }
-            }
+        if initial.out.len() < 100 {
+            changed |= capture_lines(&logs.out, &mut initial.start_pos_out, &mut initial.out);
+        }
+
+        if initial.error.len() < 100 {
+            changed |= capture_lines(
+                &logs.error,
+                &mut initial.start_pos_error,
+                &mut initial.error,
+            );
         }
+    }
+
+    if changed {
+        runner.save();
+    }
+}
+
+/// Rotate the log files that went over the size or age limits of their process
+fn rotate_logs() {
+    let global = config::read().runner.log_rotate;
+    let mut runner = Runner::new();
+    let mut changed = false;
+
+    for item in runner.list.values_mut() {
+        let settings = item.options.log_rotate.or(&global);
+        then!(!settings.enabled(), continue);
 
-        if item.initial_logs.error.len() < 100 {
-            if let Ok(mut f) = std::fs::File::open(&logs.error) {
-                if f.seek(SeekFrom::Start(item.initial_logs.start_pos_error)).is_ok() {
-                    let reader = BufReader::new(f);
-                    let lines: Vec<String> = reader
-                        .lines()
-                        .take(100)
-                        .filter_map(|l| l.ok())
-                        .collect();
-                    if lines.len() > item.initial_logs.error.len() {
-                        item.initial_logs.error = lines;
-                        changed = true;
-                    }
+        let logs = item.logs();
+        let initial = &mut item.initial_logs;
+
+        for (path, start_pos) in [
+            (logs.out, &mut initial.start_pos_out),
+            (logs.error, &mut initial.start_pos_error),
+        ] {
+            let path = std::path::Path::new(&path);
+            then!(!settings.due(path), continue);
+
+            match rotate::rotate(path, &settings) {
+                Ok(size) => {
+                    *start_pos = 0;
+                    changed = true;
+                    log!("[daemon] log rotated", "name" => item.name, "path" => path.display(), "size" => size);
+                }
+                Err(err) => {
+                    log!("[daemon] log rotation failed", "name" => item.name, "path" => path.display(), "error" => err)
                 }
             }
         }
@@ -138,7 +320,7 @@ fn capture_initial_logs() {
     }
 }
 
-pub fn health(format: &String) {
+pub fn health(format: &str) {
     let mut pid: Option<i32> = None;
     let mut cpu_percent: Option<f64> = None;
     let mut uptime: Option<DateTime<Utc>> = None;
@@ -235,7 +417,7 @@ pub fn health(format: &String) {
         .to_string();
 
     if let Ok(json) = serde_json::to_string(&data[0]) {
-        match format.as_str() {
+        match format {
             "raw" => println!("{:?}", data[0]),
             "json" => println!("{json}"),
             "default" => {
@@ -318,7 +500,12 @@ pub fn start(verbose: bool) {
         let api_enabled = ENABLE_API.load(Ordering::Acquire);
         let ui_enabled = ENABLE_WEBUI.load(Ordering::Acquire);
 
-        unsafe { libc::signal(libc::SIGTERM, handle_termination_signal as usize) };
+        unsafe {
+            libc::signal(
+                libc::SIGTERM,
+                handle_termination_signal as *const () as usize,
+            )
+        };
         DAEMON_START_TIME.set(Utc::now().timestamp_millis() as f64);
 
         pid::write(process::id());
@@ -329,6 +516,8 @@ pub fn start(verbose: bool) {
             tokio::spawn(async move { api::start(ui_enabled).await });
         }
 
+        let mut over_memory = HashMap::new();
+
         loop {
             if api_enabled && let Ok(process) = Process::new(process::id()) {
                 DAEMON_CPU_PERCENTAGE
@@ -336,8 +525,12 @@ pub fn start(verbose: bool) {
                 DAEMON_MEM_USAGE.observe(process.memory_info().ok().unwrap().rss() as f64);
             }
 
-            then!(!Runner::new().is_empty(), restart_process());
+            then!(!Runner::new().is_empty(), restart_process(&mut over_memory));
+            let runner = Runner::new();
+            log_pipe::supervise(&runner);
+            relay::attach(&runner);
             capture_initial_logs();
+            rotate_logs();
             sleep(Duration::from_millis(config.interval));
         }
     }
@@ -390,3 +583,170 @@ pub fn reset() {
 }
 
 pub mod pid;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pmc::process::{ProcessMetadata, limits::Limits, process_run};
+    use std::time::Instant;
+
+    fn item(restart: &str, running: bool) -> Item {
+        serde_json::from_value(json!({
+            "id": 1, "pid": 0, "env": {}, "name": "job", "path": "/tmp", "script": "", "restarts": 0,
+            "running": running, "crash": { "crashed": false, "value": 0 },
+            "watch": { "enabled": false, "path": "", "hash": "" }, "children": [], "started": 0,
+            "options": { "restart": restart },
+        }))
+        .unwrap()
+    }
+
+    fn metadata(command: &str, exit_file: &std::path::Path) -> ProcessMetadata {
+        ProcessMetadata {
+            name: string!("policy-test"),
+            shell: string!("/bin/sh"),
+            command: String::new(),
+            log_path: std::env::temp_dir().to_string_lossy().to_string(),
+            args: vec![string!("-c"), command.to_string()],
+            env: vec![],
+            exit_file: Some(exit_file.to_string_lossy().to_string()),
+            ready_file: None,
+            cwd: None,
+            exec: true,
+            credentials: None,
+            umask: None,
+            limits: Limits::default(),
+            cgroup: None,
+            file_env: Default::default(),
+            clear_env: false,
+            pipes: None,
+        }
+    }
+
+    fn exit_file(name: &str) -> std::path::PathBuf {
+        let exit_file = std::env::temp_dir().join(format!(
+            "pmc-exit-{}-{}",
+            process::id(),
+            name.replace(' ', "-")
+        ));
+        let _ = std::fs::remove_file(&exit_file);
+        exit_file
+    }
+
+    /// Wait for the status the supervisor records once the run exited
+    fn recorded(exit_file: &std::path::Path) -> Option<ExitStatus> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            if let Ok(raw) = std::fs::read_to_string(exit_file) {
+                let _ = std::fs::remove_file(exit_file);
+                return ExitStatus::from_wait(raw.trim().parse().ok()?, Utc::now());
+            }
+            sleep(Duration::from_millis(10));
+        }
+        None
+    }
+
+    /// Run `command` through the daemon spawn path and read the status recorded when it was reaped
+    fn run(command: &str) -> Option<ExitStatus> {
+        let exit_file = exit_file(command);
+        process_run(metadata(command, &exit_file)).unwrap();
+        recorded(&exit_file)
+    }
+
+    #[test]
+    fn test_restart_policy_decisions() {
+        let clean = run("exit 0");
+        let failed = run("exit 3");
+        let high = run("exit 130");
+        let killed = run("kill -9 $$");
+
+        assert_eq!(high.and_then(|status| status.code), Some(130));
+        assert!(killed.is_some_and(|status| status.signal.is_some()));
+
+        for status in [clean, failed, high, killed] {
+            assert!(should_restart(&item("always", true), status.as_ref()));
+            assert!(should_restart(
+                &item("unless-stopped", true),
+                status.as_ref()
+            ));
+            assert!(!should_restart(&item("never", true), status.as_ref()));
+            // a process stopped by the user is never brought back by the daemon loop
+            assert!(!should_restart(&item("always", false), status.as_ref()));
+        }
+
+        assert!(!should_restart(&item("on-failure", true), clean.as_ref()));
+        assert!(should_restart(&item("on-failure", true), failed.as_ref()));
+        assert!(should_restart(&item("on-failure", true), high.as_ref()));
+        assert!(should_restart(&item("on-failure", true), killed.as_ref()));
+        assert!(should_restart(&item("on-failure", true), None));
+    }
+
+    #[test]
+    fn test_cli_started_run_is_recorded() {
+        let exit_file = exit_file("cli started");
+
+        // spawn from a short lived process that exits right away, like the CLI does
+        match unsafe { libc::fork() } {
+            0 => {
+                let spawned = process_run(metadata("sleep 0.2; exit 0", &exit_file)).is_ok();
+                unsafe { libc::_exit(ternary!(spawned, 0, 1)) }
+            }
+            pid => {
+                let mut status = 0;
+                unsafe { libc::waitpid(pid, &mut status, 0) };
+                assert!(libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0);
+            }
+        }
+
+        let status = recorded(&exit_file);
+        assert_eq!(status.and_then(|status| status.code), Some(0));
+        assert!(!should_restart(&item("on-failure", true), status.as_ref()));
+    }
+
+    #[test]
+    fn test_restart_process_reads_recorded_status() {
+        let base = std::env::temp_dir().join(format!("pmc-daemon-{}", process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+        std::fs::create_dir_all(base.join("exits")).unwrap();
+
+        global_placeholders::init!("pmc.base", format!("{}/", base.display()));
+        global_placeholders::init!("pmc.dump", base.join("process.dump").display());
+        // kept outside the base, the other tests may still log after it is removed
+        let log = std::env::temp_dir().join(format!("pmc-daemon-{}.log", process::id()));
+        global_placeholders::init!("pmc.daemon.log", log.display());
+
+        let mut runner = Runner {
+            id: Id::new(2),
+            remote: None,
+            list: Default::default(),
+        };
+
+        // the runs write their status to the file the daemon reads for the current run
+        for (id, restart, command) in [(0, "on-failure", "exit 0"), (1, "never", "exit 3")] {
+            let started = Utc::now();
+            let exit_file = base.join(format!("exits/{id}-{}", started.timestamp_millis()));
+
+            let mut item = item(restart, true);
+            item.id = id;
+            item.started = started;
+            item.pid = process_run(metadata(command, &exit_file)).unwrap();
+            runner.list.insert(id, item);
+
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while !exit_file.exists() && Instant::now() < deadline {
+                sleep(Duration::from_millis(10));
+            }
+        }
+
+        runner.save();
+        restart_process(&mut HashMap::new());
+
+        let runner = Runner::new();
+        let (job, task) = (runner.try_info(0), runner.try_info(1));
+
+        assert_eq!((job.status(), job.restarts), (string!("exited(0)"), 0));
+        assert_eq!((task.status(), task.restarts), (string!("exited(3)"), 0));
+        assert_eq!(task.last_exit_code, Some(3));
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
+}
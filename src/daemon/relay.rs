@@ -0,0 +1,164 @@
+use chrono::Utc;
+use macros_rs::then;
+use pmc::process::{Runner, logline::LogFormat, sink::Record};
+
+use super::{log_pipe, shipper};
+
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    os::{
+        fd::AsRawFd,
+        unix::fs::{FileTypeExt, OpenOptionsExt},
+    },
+    sync::{LazyLock, Mutex, MutexGuard},
+    thread,
+};
+
+/// Where the lines read from a pipe go, kept up to date by the daemon loop
+#[derive(Clone)]
+struct Target {
+    id: usize,
+    pid: i64,
+    name: String,
+    stream: &'static str,
+    log: String,
+    format: LogFormat,
+    sinks: Vec<String>,
+    pipe: bool,
+}
+
+/// Pipes with a reader thread, keyed by path
+static READERS: LazyLock<Mutex<HashMap<String, Target>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn readers() -> MutexGuard<'static, HashMap<String, Target>> {
+    READERS.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Start reading the pipes of running relayed processes that have no reader yet
+pub fn attach(runner: &Runner) {
+    let mut readers = readers();
+
+    for item in runner
+        .list
+        .values()
+        .filter(|item| item.running && item.options.relayed())
+    {
+        let (logs, pipes) = (item.logs(), item.pipes());
+
+        for (stream, pipe, log) in [
+            ("stdout", pipes.out, logs.out),
+            ("stderr", pipes.error, logs.error),
+        ] {
+            let target = Target {
+                id: item.id,
+                pid: item.pid,
+                name: item.name.clone(),
+                format: item.options.log_format,
+                sinks: item.options.log_sinks.clone(),
+                pipe: item.options.log_pipe.is_some(),
+                stream,
+                log,
+            };
+
+            // a run started while the daemon was down writes to the log files and has no pipe
+            if !fs::metadata(&pipe).is_ok_and(|metadata| metadata.file_type().is_fifo()) {
+                continue;
+            }
+
+            if readers.insert(pipe.clone(), target).is_none() {
+                thread::spawn(move || read(pipe));
+            }
+        }
+    }
+}
+
+/// Copy lines from the pipe to the sinks and to the pipe command or the log file until every writer closed it
+fn read(pipe: String) {
+    // opening without O_NONBLOCK would wait for a writer when the process is already gone
+    let file = match OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(&pipe)
+    {
+        Ok(file) => file,
+        Err(err) => {
+            log!("[daemon] pipe open failed", "path" => pipe, "error" => err);
+            readers().remove(&pipe);
+            return;
+        }
+    };
+
+    unsafe {
+        let flags = libc::fcntl(file.as_raw_fd(), libc::F_GETFL);
+        libc::fcntl(file.as_raw_fd(), libc::F_SETFL, flags & !libc::O_NONBLOCK);
+    }
+
+    let mut reader = BufReader::new(file);
+    let mut output: Option<(String, File)> = None;
+    let mut line = Vec::new();
+
+    loop {
+        line.clear();
+        then!(
+            !matches!(reader.read_until(b'\n', &mut line), Ok(read) if read > 0),
+            break
+        );
+
+        let Some(target) = readers().get(&pipe).cloned() else {
+            break;
+        };
+
+        let at = Utc::now();
+        let msg = String::from_utf8_lossy(&line);
+        let msg = msg.trim_end_matches(['\n', '\r']);
+
+        if !target.sinks.is_empty() {
+            let record = Record {
+                at,
+                stream: target.stream,
+                id: target.id,
+                pid: target.pid,
+                name: target.name.clone(),
+                msg: msg.to_string(),
+            };
+
+            shipper::send(&target.sinks, &record);
+        }
+
+        let text = target
+            .format
+            .encode(at, target.stream, target.id, &target.name, msg);
+
+        // while the pipe command is down the lines go to the log file
+        then!(
+            target.pipe && log_pipe::write(target.id, &target.name, &text),
+            continue
+        );
+
+        // the log moves with a rename
+        if output.as_ref().is_none_or(|(path, _)| *path != target.log) {
+            match OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&target.log)
+            {
+                Ok(file) => output = Some((target.log.clone(), file)),
+                Err(err) => {
+                    log!("[daemon] log open failed", "name" => target.name, "path" => target.log, "error" => err);
+                    continue;
+                }
+            }
+        }
+
+        if let Some((path, file)) = &mut output
+            && let Err(err) = writeln!(file, "{text}")
+        {
+            log!("[daemon] log write failed", "name" => target.name, "path" => path, "error" => err);
+        }
+    }
+
+    readers().remove(&pipe);
+}
@@ -0,0 +1,542 @@
+use macros_rs::then;
+use pmc::{
+    config,
+    process::sink::{Facility, JOURNALD_SOCKET, Record, Sink, SyslogAddress},
+};
+
+use reqwest::{
+    blocking::Client,
+    header::{CONTENT_TYPE, HeaderMap, HeaderName, HeaderValue},
+};
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{self, Write},
+    net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket},
+    os::unix::net::UnixDatagram,
+    path::PathBuf,
+    sync::{
+        LazyLock, Mutex, MutexGuard,
+        mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError},
+    },
+    thread::{self, sleep},
+    time::{Duration, Instant},
+};
+
+/// Lines queued per sink, newer lines are dropped while a sink is this far behind
+const QUEUE: usize = 10_000;
+
+/// Time between attempts to reach a sink that is down
+const RECONNECT: Duration = Duration::from_secs(5);
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Queue of a running sink worker
+struct Sender {
+    queue: SyncSender<Record>,
+    dropped: u64,
+}
+
+/// Sinks used so far, `None` for the ones that are missing from the config or invalid
+static SINKS: LazyLock<Mutex<HashMap<String, Option<Sender>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn sinks() -> MutexGuard<'static, HashMap<String, Option<Sender>>> {
+    SINKS.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Queue a line for the sinks `names`, a worker is started for every sink on first use
+///
+/// Sinks are read from the config once, changes need a daemon restart
+pub fn send(names: &[String], record: &Record) {
+    let mut sinks = sinks();
+
+    for name in names {
+        let slot = sinks.entry(name.clone()).or_insert_with(|| start(name));
+        let Some(sender) = slot else {
+            continue;
+        };
+
+        match sender.queue.try_send(record.clone()) {
+            Ok(()) if sender.dropped > 0 => {
+                log!("[sink] caught up", "name" => name, "dropped" => sender.dropped);
+                sender.dropped = 0;
+            }
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                then!(
+                    sender.dropped == 0,
+                    log!("[sink] queue full, dropping lines", "name" => name)
+                );
+                sender.dropped += 1;
+            }
+            // the worker is gone, it is started again with the next line
+            Err(TrySendError::Disconnected(_)) => drop(sinks.remove(name)),
+        }
+    }
+}
+
+fn start(name: &str) -> Option<Sender> {
+    let Some(sink) = config::read().sinks.remove(name) else {
+        log!("[sink] not defined in the config", "name" => name);
+        return None;
+    };
+
+    if let Err(err) = sink.check() {
+        log!("[sink] invalid settings", "name" => name, "error" => err);
+        return None;
+    }
+
+    let (queue, lines) = mpsc::sync_channel(QUEUE);
+    let mut health = Health::new(name);
+
+    thread::spawn(move || match sink {
+        Sink::Syslog { address, facility } => syslog(&mut health, address, facility, lines),
+        Sink::Journald { socket } => journald(&mut health, socket, lines),
+        Sink::Http {
+            url,
+            headers,
+            batch_size,
+            flush_interval,
+            retries,
+        } => {
+            let batches = Batches {
+                size: batch_size,
+                interval: Duration::from_millis(flush_interval),
+            };
+            http(&mut health, &url, &headers, batches, retries, lines)
+        }
+    });
+
+    log!("[sink] started", "name" => name);
+    Some(Sender { queue, dropped: 0 })
+}
+
+/// Logs when a sink starts failing and when it works again, not every failed line
+struct Health {
+    name: String,
+    failing: bool,
+}
+
+impl Health {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            failing: false,
+        }
+    }
+
+    fn ok(&mut self) {
+        then!(self.failing, log!("[sink] recovered", "name" => self.name));
+        self.failing = false;
+    }
+
+    fn failed(&mut self, err: impl std::fmt::Display) {
+        then!(
+            !self.failing,
+            log!("[sink] failing, lines are dropped", "name" => self.name, "error" => err)
+        );
+        self.failing = true;
+    }
+}
+
+enum Connection {
+    Udp(UdpSocket, SocketAddr),
+    Tcp(TcpStream),
+    Unix(UnixDatagram),
+}
+
+impl Connection {
+    fn open(address: &SyslogAddress) -> io::Result<Connection> {
+        let resolve = |host: &str| {
+            host.to_socket_addrs()?.next().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, format!("{host} did not resolve"))
+            })
+        };
+
+        match address {
+            SyslogAddress::Udp(host) => {
+                let target = resolve(host)?;
+                let socket = UdpSocket::bind(match target.is_ipv4() {
+                    true => "0.0.0.0:0",
+                    false => "[::]:0",
+                })?;
+
+                Ok(Connection::Udp(socket, target))
+            }
+            SyslogAddress::Tcp(host) => {
+                let stream = TcpStream::connect_timeout(&resolve(host)?, TIMEOUT)?;
+                stream.set_write_timeout(Some(TIMEOUT))?;
+                Ok(Connection::Tcp(stream))
+            }
+            SyslogAddress::Unix(path) => {
+                let socket = UnixDatagram::unbound()?;
+                socket.connect(path)?;
+                Ok(Connection::Unix(socket))
+            }
+        }
+    }
+
+    fn send(&mut self, message: &str) -> io::Result<()> {
+        match self {
+            // not connected, a receiver that is down would fail the next send with a refused error
+            Connection::Udp(socket, target) => {
+                socket.send_to(message.as_bytes(), *target).map(|_| ())
+            }
+            // octet counting framing from RFC 6587
+            Connection::Tcp(stream) => write!(stream, "{} {message}", message.len()),
+            Connection::Unix(socket) => socket.send(message.as_bytes()).map(|_| ()),
+        }
+    }
+}
+
+fn syslog(
+    health: &mut Health,
+    address: Option<String>,
+    facility: Facility,
+    lines: Receiver<Record>,
+) {
+    let Ok(address) = SyslogAddress::resolve(&address) else {
+        return;
+    };
+
+    let mut connection: Option<Connection> = None;
+    let mut retry_at = Instant::now();
+
+    for record in lines {
+        let message = record.syslog(facility);
+
+        // a connection that broke is opened again once before the line is given up
+        for _ in 0..2 {
+            if connection.is_none() {
+                then!(Instant::now() < retry_at, break);
+
+                match Connection::open(&address) {
+                    Ok(opened) => connection = Some(opened),
+                    Err(err) => {
+                        retry_at = Instant::now() + RECONNECT;
+                        health.failed(err);
+                        break;
+                    }
+                }
+            }
+
+            if let Some(open) = &mut connection {
+                match open.send(&message) {
+                    Ok(()) => {
+                        health.ok();
+                        break;
+                    }
+                    Err(err) => {
+                        connection = None;
+                        health.failed(err);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn journald(health: &mut Health, socket: Option<PathBuf>, lines: Receiver<Record>) {
+    let path = socket.unwrap_or_else(|| PathBuf::from(JOURNALD_SOCKET));
+    let socket = match UnixDatagram::unbound() {
+        Ok(socket) => socket,
+        Err(err) => return health.failed(err),
+    };
+
+    for record in lines {
+        match socket.send_to(&record.journald(), &path) {
+            Ok(_) => health.ok(),
+            Err(err) => health.failed(err),
+        }
+    }
+}
+
+/// When a batch of an http sink is sent
+struct Batches {
+    size: usize,
+    interval: Duration,
+}
+
+fn http(
+    health: &mut Health,
+    url: &str,
+    headers: &BTreeMap<String, String>,
+    batches: Batches,
+    retries: u32,
+    lines: Receiver<Record>,
+) {
+    let mut header_map = HeaderMap::new();
+    header_map.insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static("application/x-ndjson"),
+    );
+
+    for (key, value) in headers {
+        match (
+            HeaderName::from_bytes(key.as_bytes()),
+            HeaderValue::from_str(value),
+        ) {
+            (Ok(key), Ok(value)) => drop(header_map.insert(key, value)),
+            _ => log!("[sink] invalid header skipped", "name" => health.name, "header" => key),
+        }
+    }
+
+    let client = match Client::builder()
+        .timeout(TIMEOUT)
+        .default_headers(header_map)
+        .build()
+    {
+        Ok(client) => client,
+        Err(err) => return health.failed(err),
+    };
+
+    let mut batch: Vec<Record> = Vec::with_capacity(batches.size);
+    let mut deadline: Option<Instant> = None;
+
+    loop {
+        let wait = deadline.map_or(Duration::from_secs(3600), |at| {
+            at.saturating_duration_since(Instant::now())
+        });
+
+        let ended = match lines.recv_timeout(wait) {
+            Ok(record) => {
+                deadline.get_or_insert_with(|| Instant::now() + batches.interval);
+                batch.push(record);
+                then!(batch.len() < batches.size, continue);
+                false
+            }
+            Err(RecvTimeoutError::Timeout) => false,
+            Err(RecvTimeoutError::Disconnected) => true,
+        };
+
+        if !batch.is_empty() {
+            let body = batch
+                .iter()
+                .map(|record| format!("{}\n", record.json()))
+                .collect::<String>();
+            post(health, &client, url, body, batch.len(), retries);
+            batch.clear();
+        }
+
+        deadline = None;
+        then!(ended, break);
+    }
+}
+
+/// Send a batch, waiting longer after every failed attempt
+fn post(health: &mut Health, client: &Client, url: &str, body: String, count: usize, retries: u32) {
+    let mut error = String::new();
+
+    for attempt in 0..=retries {
+        then!(
+            attempt > 0,
+            sleep(Duration::from_millis(500 << attempt.min(6)))
+        );
+
+        match client.post(url).body(body.clone()).send() {
+            Ok(response) if response.status().is_success() => return health.ok(),
+            // the request itself was rejected, sending it again would not help
+            Ok(response)
+                if response.status().is_client_error() && response.status().as_u16() != 429 =>
+            {
+                error = response.status().to_string();
+                break;
+            }
+            Ok(response) => error = response.status().to_string(),
+            Err(err) => error = err.to_string(),
+        }
+    }
+
+    health.failed(format!("{error}, {count} lines dropped"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use std::{
+        io::{BufRead, BufReader, Read},
+        net::TcpListener,
+        thread::JoinHandle,
+    };
+
+    /// Local http receiver answering with `statuses` in turn and 200 after them, returns its url and the bodies it got
+    fn receiver(statuses: Vec<u16>) -> (String, Receiver<(Instant, String)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/ingest", listener.local_addr().unwrap());
+        let (requests, received) = mpsc::channel();
+
+        thread::spawn(move || {
+            let mut statuses = statuses.into_iter();
+
+            for stream in listener.incoming().flatten() {
+                let mut reader = BufReader::new(&stream);
+                let mut length = 0;
+
+                loop {
+                    let mut line = String::new();
+                    then!(
+                        reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n",
+                        break
+                    );
+
+                    if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                        length = value.trim().parse().unwrap();
+                    }
+                }
+
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+
+                let status = statuses.next().unwrap_or(200);
+                let _ = write!(
+                    &stream,
+                    "HTTP/1.1 {status} Test\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+                );
+                then!(
+                    requests
+                        .send((Instant::now(), String::from_utf8(body).unwrap()))
+                        .is_err(),
+                    break
+                );
+            }
+        });
+
+        (url, received)
+    }
+
+    /// Run an http sink worker, lines queued on the returned sender are shipped to `url`
+    fn worker(
+        url: String,
+        size: usize,
+        interval: u64,
+        retries: u32,
+    ) -> (SyncSender<Record>, JoinHandle<()>) {
+        let log = std::env::temp_dir().join(format!("pmc-shipper-{}.log", std::process::id()));
+        global_placeholders::init!("pmc.daemon.log", log.display());
+
+        let (queue, lines) = mpsc::sync_channel(QUEUE);
+        let batches = Batches {
+            size,
+            interval: Duration::from_millis(interval),
+        };
+
+        let worker = thread::spawn(move || {
+            http(
+                &mut Health::new("test"),
+                &url,
+                &BTreeMap::new(),
+                batches,
+                retries,
+                lines,
+            )
+        });
+        (queue, worker)
+    }
+
+    fn record(msg: &str) -> Record {
+        Record {
+            at: Utc::now(),
+            stream: "stdout",
+            id: 0,
+            pid: 1,
+            name: String::from("api"),
+            msg: msg.to_string(),
+        }
+    }
+
+    /// Messages of the lines in a batch
+    fn messages(body: &str) -> Vec<String> {
+        body.lines()
+            .map(|line| {
+                serde_json::from_str::<serde_json::Value>(line).unwrap()["msg"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    fn next(received: &Receiver<(Instant, String)>) -> (Instant, Vec<String>) {
+        let (at, body) = received
+            .recv_timeout(Duration::from_secs(10))
+            .expect("no request received");
+        (at, messages(&body))
+    }
+
+    #[test]
+    fn test_http_flush_on_size() {
+        let (url, received) = receiver(vec![]);
+        let (queue, worker) = worker(url, 3, 60_000, 0);
+
+        ["a", "b", "c", "d"]
+            .iter()
+            .for_each(|msg| queue.send(record(msg)).unwrap());
+
+        assert_eq!(next(&received).1, ["a", "b", "c"]);
+        assert!(received.recv_timeout(Duration::from_millis(300)).is_err());
+
+        // the lines left are sent when the sink is closed
+        drop(queue);
+        assert_eq!(next(&received).1, ["d"]);
+        worker.join().unwrap();
+    }
+
+    #[test]
+    fn test_http_flush_on_interval() {
+        let (url, received) = receiver(vec![]);
+        let (queue, worker) = worker(url, 100, 300, 0);
+
+        let started = Instant::now();
+        queue.send(record("a")).unwrap();
+        queue.send(record("b")).unwrap();
+
+        let (at, batch) = next(&received);
+        assert_eq!(batch, ["a", "b"]);
+        assert!(at - started >= Duration::from_millis(300));
+
+        // the interval starts again with the first line of the next batch
+        let started = Instant::now();
+        queue.send(record("c")).unwrap();
+
+        let (at, batch) = next(&received);
+        assert_eq!(batch, ["c"]);
+        assert!(at - started >= Duration::from_millis(300));
+
+        drop(queue);
+        worker.join().unwrap();
+    }
+
+    #[test]
+    fn test_http_retry_backoff() {
+        let (url, received) = receiver(vec![503, 429]);
+        let (queue, worker) = worker(url, 1, 60_000, 2);
+
+        queue.send(record("a")).unwrap();
+        let attempts = [next(&received), next(&received), next(&received)];
+
+        assert!(attempts.iter().all(|(_, batch)| batch == &["a"]));
+        assert!(attempts[1].0 - attempts[0].0 >= Duration::from_millis(1000));
+        assert!(attempts[2].0 - attempts[1].0 >= Duration::from_millis(2000));
+
+        drop(queue);
+        worker.join().unwrap();
+        assert!(received.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_http_rejected_batch_is_not_retried() {
+        let (url, received) = receiver(vec![400]);
+        let (queue, worker) = worker(url, 1, 60_000, 3);
+
+        queue.send(record("a")).unwrap();
+        queue.send(record("b")).unwrap();
+
+        assert_eq!(next(&received).1, ["a"]);
+        assert_eq!(next(&received).1, ["b"]);
+
+        drop(queue);
+        worker.join().unwrap();
+    }
+}
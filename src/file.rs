@@ -1,4 +1,7 @@
-use crate::{helpers, log, process::Process};
+use crate::{
+    helpers, log,
+    process::{Process, logline},
+};
 use colored::Colorize;
 use macros_rs::{crashln, string, ternary};
 
@@ -56,7 +59,7 @@ pub fn logs_internal(
         println!(
             "{} {}",
             format!("{}|{} |", id, item_name).color(color),
-            line
+            logline::colorize(line)
         );
     }
 }
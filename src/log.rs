@@ -16,6 +16,15 @@ impl Logger {
         Ok(Logger { file })
     }
 
+    /// Logger writing to the daemon log instead of the cli log
+    pub fn daemon() -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(global!("pmc.daemon.log"))?;
+        Ok(Logger { file })
+    }
+
     pub fn write(&mut self, message: &str) {
         log::info!("{message}");
         writeln!(
@@ -6,6 +6,24 @@ mod webui;
 use clap::{Parser, Subcommand};
 use clap_verbosity_flag::{LogLevel, Verbosity};
 use macros_rs::{str, string, then};
+use pmc::process::{
+    Options,
+    backoff::Backoff,
+    cgroup::CpuMax,
+    cron::Cron,
+    environment::EnvMode,
+    instances::Instances,
+    kill::{self, KillSignal},
+    limits::Limits,
+    logline::LogFormat,
+    memory::MaxMemory,
+    ready::Ready,
+    restart::RestartPolicy,
+    rotate::LogRotate,
+    search::Filters,
+    user::Umask,
+};
+use std::{collections::BTreeMap, path::PathBuf};
 use update_informer::{Check, registry};
 
 use crate::{
@@ -85,12 +103,30 @@ enum Server {
 
 // add pmc restore command
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
 enum Commands {
     /// Import process from environment file
     #[command(visible_alias = "add")]
     Import {
         /// Path of file to import
         path: String,
+        /// Env profile to use, from the `env "<profile>"` blocks or `env_profiles` of the processes
+        #[arg(long = "env")]
+        env_profile: Option<String>,
+    },
+    /// Create, update and remove processes to match a process file
+    Apply {
+        /// Path of the file to apply
+        path: String,
+        /// Env profile to use, from the `env "<profile>"` blocks or `env_profiles` of the processes
+        #[arg(long = "env")]
+        env_profile: Option<String>,
+        /// Remove processes that are not in the file
+        #[arg(long)]
+        prune: bool,
+        /// Only print the changes
+        #[arg(long)]
+        dry_run: bool,
     },
     /// Export environment file from process
     #[command(visible_alias = "get")]
@@ -99,6 +135,9 @@ enum Commands {
         item: Item,
         /// Path to export file
         path: Option<String>,
+        /// Export the values of secret variables instead of masking them
+        #[arg(long)]
+        reveal: bool,
     },
     /// Start/Restart a process
     #[command(visible_alias = "restart")]
@@ -111,12 +150,93 @@ enum Commands {
         /// Watch to reload path
         #[arg(long)]
         watch: Option<String>,
+        /// Number of instances to spawn (number or "max")
+        #[arg(short, long, default_value = "1")]
+        instances: Instances,
+        /// Readiness check used on reload (delay[:ms], port[:port] or signal)
+        #[arg(long, default_value = "delay")]
+        ready: Ready,
+        /// Signal sent to stop the process
+        #[arg(long, default_value = "SIGTERM")]
+        kill_signal: KillSignal,
+        /// Milliseconds to wait before the process tree gets SIGKILL
+        #[arg(long, default_value_t = kill::DEFAULT_KILL_TIMEOUT)]
+        kill_timeout: u64,
+        /// When to restart the process (always, on-failure, never or unless-stopped)
+        #[arg(long, default_value = "unless-stopped")]
+        restart: RestartPolicy,
+        /// Restart the process when its memory stays over this limit (like 512M or 1G)
+        #[arg(long)]
+        max_memory: Option<MaxMemory>,
+        /// Restart the process, or start it again once it exited, on a cron schedule
+        #[arg(long)]
+        cron: Option<Cron>,
+        /// Program the script is passed to (like python3)
+        #[arg(long)]
+        interpreter: Option<String>,
+        /// Arguments for the interpreter, separated by spaces
+        #[arg(long, allow_hyphen_values = true, value_delimiter = ' ')]
+        interpreter_args: Vec<String>,
+        /// Working directory, relative to the current directory
+        #[arg(long)]
+        cwd: Option<PathBuf>,
+        /// Run the interpreter or script directly without a shell
+        #[arg(long)]
+        exec: bool,
+        /// User to run the process as, a name or a uid
+        #[arg(long)]
+        user: Option<String>,
+        /// Group to run the process as, defaults to the primary group of the user
+        #[arg(long)]
+        group: Option<String>,
+        /// File mode creation mask in octal (like 022)
+        #[arg(long)]
+        umask: Option<Umask>,
+        /// CPU limit of the process cgroup (like 50% or 1.5 cores), needs daemon.cgroup
+        #[arg(long)]
+        cpu_max: Option<CpuMax>,
+        /// Memory limit of the process cgroup (like 512M), needs daemon.cgroup
+        #[arg(long)]
+        memory_max: Option<MaxMemory>,
+        /// Env file read on every start, can be given more than once
+        #[arg(long)]
+        env_file: Vec<PathBuf>,
+        /// Environment passed to the process (inherit, clean or allowlist)
+        #[arg(long, default_value = "inherit")]
+        env_mode: EnvMode,
+        /// Variable copied in allowlist mode (like PATH or LC_*), can be given more than once
+        #[arg(long)]
+        env_allow: Vec<String>,
+        /// Env profile to start the process with, remembered for restarts
+        #[arg(long = "env")]
+        env_profile: Option<String>,
+        /// Process started before this one on restore and start all, can be given more than once
+        #[arg(long)]
+        depends_on: Vec<String>,
+        /// How output is written to the log files (raw, timestamp or json), the others need the daemon to read the output
+        #[arg(long, default_value = "raw")]
+        log_format: LogFormat,
+        /// Sink from `[sinks]` in the config the output is shipped to, can be given more than once
+        #[arg(long = "log-sink")]
+        log_sinks: Vec<String>,
+        /// Command fed the output instead of the log files, like "logger -t api"
+        #[arg(long)]
+        log_pipe: Option<String>,
+        /// Delay between crash restarts (none, fixed[:ms] or exponential[:ms[:max_ms]])
+        #[arg(long, default_value = "none")]
+        backoff: Backoff,
+        /// Seconds of stable uptime after which the crash counter is reset
+        #[arg(long, default_value = "0")]
+        reset_after: u64,
         /// Server
         #[arg(short, long)]
         server: Option<String>,
         /// Reset environment values
         #[arg(short, long)]
         reset_env: bool,
+        /// Arguments passed to the script
+        #[arg(last = true)]
+        script_args: Vec<String>,
     },
     /// Stop/Kill a process
     #[command(visible_alias = "kill")]
@@ -127,6 +247,24 @@ enum Commands {
         #[arg(short, long)]
         server: Option<String>,
     },
+    /// Reload a process without downtime, one instance at a time
+    Reload {
+        #[clap(value_parser = cli::validate::<Item>)]
+        item: Item,
+        /// Server
+        #[arg(short, long)]
+        server: Option<String>,
+    },
+    /// Grow or shrink a process group
+    Scale {
+        #[clap(value_parser = cli::validate::<Item>)]
+        item: Item,
+        /// Number of instances (number or "max")
+        instances: Instances,
+        /// Server
+        #[arg(short, long)]
+        server: Option<String>,
+    },
     /// Stop then remove a process
     #[command(visible_alias = "rm", visible_alias = "delete")]
     Remove {
@@ -141,6 +279,9 @@ enum Commands {
     Env {
         #[clap(value_parser = cli::validate::<Item>)]
         item: Item,
+        /// Show the values of secret variables
+        #[arg(long)]
+        reveal: bool,
         /// Server
         #[arg(short, long)]
         server: Option<String>,
@@ -193,6 +334,21 @@ enum Commands {
         item: Item,
         #[arg(long, default_value_t = 15, help = "")]
         lines: usize,
+        /// Interleave the out and error logs by time, for all processes with `all`
+        #[arg(long)]
+        merged: bool,
+        /// Only show lines matching this regex
+        #[arg(long)]
+        grep: Option<String>,
+        /// Only show lines after this time, like 1h or "2026-10-17 12:00"
+        #[arg(long)]
+        since: Option<String>,
+        /// Only show lines before this time, like 30m or "2026-10-17 13:00"
+        #[arg(long)]
+        until: Option<String>,
+        /// Only show lines of this level or a more severe one (trace, debug, info, warn, error, fatal)
+        #[arg(long)]
+        level: Option<String>,
         /// Server
         #[arg(short, long)]
         server: Option<String>,
@@ -241,20 +397,108 @@ fn main() {
     env.filter_level(level).init();
 
     match &cli.command {
-        Commands::Import { path } => cli::import::read_hcl(path),
-        Commands::Export { item, path } => cli::import::export_hcl(item, path),
+        Commands::Import { path, env_profile } => cli::import::read_file(path, env_profile),
+        Commands::Apply {
+            path,
+            env_profile,
+            prune,
+            dry_run,
+        } => cli::import::apply_file(path, env_profile, *prune, *dry_run),
+        Commands::Export { item, path, reveal } => cli::import::export_file(item, path, *reveal),
         Commands::Start {
             name,
             args,
             watch,
+            instances,
+            ready,
+            kill_signal,
+            kill_timeout,
+            restart,
+            max_memory,
+            cron,
+            interpreter,
+            interpreter_args,
+            cwd,
+            exec,
+            user,
+            group,
+            umask,
+            cpu_max,
+            memory_max,
+            env_file,
+            env_mode,
+            env_allow,
+            env_profile,
+            depends_on,
+            log_format,
+            log_sinks,
+            log_pipe,
+            backoff,
+            reset_after,
             server,
             reset_env,
-        } => cli::start(name, args, watch, reset_env, &defaults(server)),
+            script_args,
+        } => {
+            let options = Options {
+                ready: *ready,
+                kill_signal: *kill_signal,
+                kill_timeout: *kill_timeout,
+                restart: *restart,
+                max_memory: *max_memory,
+                cron: cron.clone(),
+                interpreter: interpreter.clone(),
+                interpreter_args: interpreter_args.clone(),
+                args: script_args.clone(),
+                cwd: cwd.clone(),
+                exec: *exec,
+                user: user.clone(),
+                group: group.clone(),
+                umask: *umask,
+                limits: Limits::default(),
+                cpu_max: *cpu_max,
+                memory_max: *memory_max,
+                env_file: env_file.clone(),
+                env_mode: *env_mode,
+                env_allow: env_allow.clone(),
+                env_profiles: BTreeMap::new(),
+                env_profile: env_profile.clone(),
+                depends_on: depends_on.clone(),
+                log_rotate: LogRotate::default(),
+                log_format: *log_format,
+                log_sinks: log_sinks.clone(),
+                log_pipe: log_pipe.clone(),
+            };
+            let backoff = Backoff {
+                reset_after: *reset_after,
+                ..*backoff
+            };
+
+            cli::start(
+                name,
+                args,
+                watch,
+                instances,
+                &options,
+                &backoff,
+                reset_env,
+                &defaults(server),
+            )
+        }
+        Commands::Reload { item, server } => cli::reload(item, &defaults(server)),
         Commands::Stop { item, server } => cli::stop(item, &defaults(server)),
         Commands::Remove { item, server } => cli::remove(item, &defaults(server)),
+        Commands::Scale {
+            item,
+            instances,
+            server,
+        } => cli::scale(item, instances, &defaults(server)),
         Commands::Restore { server } => Internal::restore(&defaults(server)),
         Commands::Save { server } => Internal::save(&defaults(server)),
-        Commands::Env { item, server } => cli::env(item, &defaults(server)),
+        Commands::Env {
+            item,
+            server,
+            reveal,
+        } => cli::env(item, &defaults(server), *reveal),
         Commands::Details {
             item: Some(item),
             format,
@@ -281,8 +525,22 @@ fn main() {
         Commands::Logs {
             item,
             lines,
+            merged,
+            grep,
+            since,
+            until,
+            level,
             server,
-        } => cli::logs(item, lines, &defaults(server)),
+        } => {
+            let filters = Filters {
+                grep: grep.clone(),
+                since: since.clone(),
+                until: until.clone(),
+                level: level.clone(),
+            };
+
+            cli::logs(item, lines, *merged, &filters, &defaults(server))
+        }
         Commands::Flush { item, server } => cli::flush(item, &defaults(server)),
         Commands::Dashboard => cli::dashboard::run(),
 
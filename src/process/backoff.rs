@@ -0,0 +1,160 @@
+use chrono::{DateTime, Utc};
+use core::fmt;
+use serde::{Deserialize, Serialize};
+use std::{str::FromStr, time::Duration};
+use utoipa::ToSchema;
+
+const DEFAULT_DELAY_MS: u64 = 1000;
+const DEFAULT_MAX_DELAY_MS: u64 = 60000;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Strategy {
+    /// Restart on the next daemon tick
+    #[default]
+    None,
+    /// Wait the same delay before every restart
+    Fixed,
+    /// Double the delay on every crash until it reaches the max delay
+    Exponential,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(default)]
+pub struct Backoff {
+    pub strategy: Strategy,
+    /// Delay before the first restart in milliseconds
+    pub delay: u64,
+    /// Upper bound of the exponential delay in milliseconds
+    pub max_delay: u64,
+    /// Seconds of stable uptime after which the crash counter is reset, 0 never resets
+    pub reset_after: u64,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            strategy: Strategy::None,
+            delay: DEFAULT_DELAY_MS,
+            max_delay: DEFAULT_MAX_DELAY_MS,
+            reset_after: 0,
+        }
+    }
+}
+
+impl Backoff {
+    /// Delay to wait before restarting a process that crashed `crashes` times in a row
+    pub fn delay(&self, crashes: u64) -> Duration {
+        let millis = match self.strategy {
+            Strategy::None => 0,
+            Strategy::Fixed => self.delay,
+            Strategy::Exponential => {
+                let exponent = crashes.saturating_sub(1).min(32) as u32;
+                self.delay
+                    .saturating_mul(2u64.pow(exponent))
+                    .min(self.max_delay)
+            }
+        };
+
+        Duration::from_millis(millis)
+    }
+
+    /// Check if a process started at `started` has been up long enough to forget its crashes
+    pub fn is_stable(&self, started: DateTime<Utc>) -> bool {
+        self.reset_after > 0 && (Utc::now() - started).num_seconds() >= self.reset_after as i64
+    }
+}
+
+impl FromStr for Backoff {
+    type Err = String;
+
+    /// Parse `none`, `fixed[:delay]` or `exponential[:delay[:max_delay]]`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s.trim().split(':').map(str::trim).collect::<Vec<&str>>();
+        let millis = |value: &str| {
+            value
+                .parse::<u64>()
+                .map_err(|_| format!("invalid backoff delay '{value}', expected milliseconds"))
+        };
+
+        let mut backoff = Backoff::default();
+
+        match (parts[0].to_lowercase().as_str(), &parts[1..]) {
+            ("none", []) => {}
+            ("fixed", values) if values.len() <= 1 => {
+                backoff.strategy = Strategy::Fixed;
+                if let Some(delay) = values.first() {
+                    backoff.delay = millis(delay)?;
+                }
+            }
+            ("exponential", values) if values.len() <= 2 => {
+                backoff.strategy = Strategy::Exponential;
+                if let Some(delay) = values.first() {
+                    backoff.delay = millis(delay)?;
+                }
+                if let Some(max_delay) = values.get(1) {
+                    backoff.max_delay = millis(max_delay)?;
+                }
+            }
+            _ => {
+                return Err(format!(
+                    "invalid backoff '{s}', expected none, fixed[:ms] or exponential[:ms[:max_ms]]"
+                ));
+            }
+        }
+
+        Ok(backoff)
+    }
+}
+
+impl fmt::Display for Backoff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.strategy {
+            Strategy::None => write!(f, "none")?,
+            Strategy::Fixed => write!(f, "fixed {}ms", self.delay)?,
+            Strategy::Exponential => {
+                write!(f, "exponential {}ms..{}ms", self.delay, self.max_delay)?
+            }
+        }
+
+        if self.reset_after > 0 {
+            write!(f, ", reset after {}s", self.reset_after)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_backoff() {
+        let fixed: Backoff = "fixed:500".parse().unwrap();
+        let exponential: Backoff = "exponential:100:2000".parse().unwrap();
+
+        assert_eq!(fixed.strategy, Strategy::Fixed);
+        assert_eq!(fixed.delay, 500);
+        assert_eq!(exponential.strategy, Strategy::Exponential);
+        assert_eq!((exponential.delay, exponential.max_delay), (100, 2000));
+        assert_eq!("none".parse::<Backoff>(), Ok(Backoff::default()));
+        assert!("linear".parse::<Backoff>().is_err());
+        assert!("fixed:1:2".parse::<Backoff>().is_err());
+        assert!("none:1".parse::<Backoff>().is_err());
+    }
+
+    #[test]
+    fn test_backoff_delay() {
+        let fixed: Backoff = "fixed:500".parse().unwrap();
+        let exponential: Backoff = "exponential:100:1000".parse().unwrap();
+
+        assert_eq!(Backoff::default().delay(3), Duration::ZERO);
+        assert_eq!(fixed.delay(1), Duration::from_millis(500));
+        assert_eq!(fixed.delay(10), Duration::from_millis(500));
+        assert_eq!(exponential.delay(1), Duration::from_millis(100));
+        assert_eq!(exponential.delay(3), Duration::from_millis(400));
+        assert_eq!(exponential.delay(5), Duration::from_millis(1000));
+        assert_eq!(exponential.delay(200), Duration::from_millis(1000));
+    }
+}
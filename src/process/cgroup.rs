@@ -0,0 +1,400 @@
+use super::memory::MaxMemory;
+use crate::config;
+use chrono::{DateTime, Utc};
+use core::fmt;
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{self, Visitor},
+};
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{LazyLock, Mutex, MutexGuard},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Period in microseconds the `cpu.max` quota applies to
+const CPU_PERIOD: u64 = 100_000;
+
+/// Shortest time between the two `cpu.stat` samples used for the cpu percentage
+const CPU_SAMPLE_MS: u64 = 100;
+
+/// Last `cpu.stat` sample of a cgroup and the percentage up to it
+struct Sample {
+    usage: u64,
+    at: Instant,
+    percent: f64,
+}
+
+/// Samples keyed by cgroup path, so only the first lookup has to wait for a second sample
+static SAMPLES: LazyLock<Mutex<HashMap<PathBuf, Sample>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn samples() -> MutexGuard<'static, HashMap<PathBuf, Sample>> {
+    SAMPLES.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// CPU time a cgroup may use, in percent of one CPU, written like `50%` or `1.5` (cores)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CpuMax(pub u64);
+
+impl FromStr for CpuMax {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = s.trim();
+        let percent = match value.strip_suffix('%') {
+            Some(percent) => percent.trim().parse::<f64>().ok(),
+            None => value.parse::<f64>().ok().map(|cores| cores * 100.0),
+        };
+
+        match percent {
+            Some(percent) if percent >= 1.0 && percent.is_finite() => {
+                Ok(CpuMax(percent.round() as u64))
+            }
+            _ => Err(format!(
+                "invalid cpu limit '{s}', expected a percentage like 50% or cores like 1.5"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for CpuMax {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}%", self.0)
+    }
+}
+
+impl Serialize for CpuMax {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for CpuMax {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct CpuVisitor;
+
+        impl<'de> Visitor<'de> for CpuVisitor {
+            type Value = CpuMax;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a percentage like \"50%\" or a number of cores")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<CpuMax, E> {
+                CpuMax::from_str(value).map_err(E::custom)
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<CpuMax, E> {
+                CpuMax::from_str(&value.to_string()).map_err(E::custom)
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<CpuMax, E> {
+                CpuMax::from_str(&value.to_string()).map_err(E::custom)
+            }
+
+            fn visit_f64<E: de::Error>(self, value: f64) -> Result<CpuMax, E> {
+                CpuMax::from_str(&value.to_string()).map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(CpuVisitor)
+    }
+}
+
+/// The cgroup a single process runs in, below the delegated subtree from `daemon.cgroup`
+#[derive(Clone, Debug)]
+pub struct Cgroup {
+    pub path: PathBuf,
+    pub cpu_max: Option<CpuMax>,
+    pub memory_max: Option<MaxMemory>,
+}
+
+/// Root of the delegated cgroup v2 subtree, cgroups are only used when `daemon.cgroup` is set
+pub fn root() -> Option<PathBuf> {
+    config::read().daemon.cgroup.map(PathBuf::from)
+}
+
+/// Path of the cgroup used for the run of the process with `id` started at `started`
+///
+/// Every run gets its own cgroup, so the instance started by a reload is not part of the old one
+pub fn path(root: &Path, id: usize, started: DateTime<Utc>) -> PathBuf {
+    root.join(format!("process-{id}-{}", started.timestamp_millis()))
+}
+
+/// Cgroups of every run of the process with `id`
+fn runs(root: &Path, id: usize) -> Vec<PathBuf> {
+    let legacy = format!("process-{id}");
+    let prefix = format!("{legacy}-");
+
+    fs::read_dir(root)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            name == legacy
+                || name
+                    .strip_prefix(&prefix)
+                    .is_some_and(|started| started.parse::<i64>().is_ok())
+        })
+        .map(|entry| entry.path())
+        .collect()
+}
+
+/// Find where the unified cgroup v2 hierarchy is mounted
+fn mount() -> Option<PathBuf> {
+    fs::read_to_string("/proc/mounts")
+        .ok()?
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<&str>>())
+        .find(|fields| fields.get(2) == Some(&"cgroup2"))
+        .map(|fields| PathBuf::from(fields[1]))
+}
+
+fn write(path: &Path, value: &str) -> Result<(), String> {
+    fs::write(path, value)
+        .map_err(|err| format!("failed to write '{value}' to {}: {err}", path.display()))
+}
+
+impl Cgroup {
+    /// Create the cgroup and apply its limits, the returned `cgroup.procs` lets the child move itself in before exec
+    pub fn create(&self) -> Result<File, String> {
+        let Some(root) = self.path.parent() else {
+            return Err(format!("invalid cgroup path {}", self.path.display()));
+        };
+
+        let subtree = root.join("cgroup.subtree_control");
+
+        // memory accounting is nice to have, it is only required for memory_max
+        if let Err(err) = write(&subtree, "+memory")
+            && self.memory_max.is_some()
+        {
+            return Err(err);
+        }
+
+        if self.cpu_max.is_some() {
+            write(&subtree, "+cpu")?;
+        }
+
+        fs::create_dir_all(&self.path)
+            .map_err(|err| format!("failed to create cgroup {}: {err}", self.path.display()))?;
+
+        match self.cpu_max {
+            Some(CpuMax(percent)) => write(
+                &self.path.join("cpu.max"),
+                &format!("{} {CPU_PERIOD}", percent * CPU_PERIOD / 100),
+            )?,
+            None => reset_limit(&self.path.join("cpu.max")),
+        }
+
+        match self.memory_max {
+            Some(MaxMemory(bytes)) => write(&self.path.join("memory.max"), &bytes.to_string())?,
+            None => reset_limit(&self.path.join("memory.max")),
+        }
+
+        OpenOptions::new()
+            .write(true)
+            .open(self.path.join("cgroup.procs"))
+            .map_err(|err| format!("failed to open cgroup {}: {err}", self.path.display()))
+    }
+}
+
+/// Drop a limit left over from an earlier run, if the controller is enabled at all
+fn reset_limit(path: &Path) {
+    if path.exists() {
+        let _ = fs::write(path, "max");
+    }
+}
+
+/// Check that cgroup limits can be used, they need `daemon.cgroup` to point at a cgroup v2 directory
+pub fn check(cpu_max: &Option<CpuMax>, memory_max: &Option<MaxMemory>) -> Result<(), String> {
+    match root() {
+        Some(root) if !root.join("cgroup.procs").exists() => Err(format!(
+            "daemon.cgroup {} is not a cgroup v2 directory",
+            root.display()
+        )),
+        None if cpu_max.is_some() || memory_max.is_some() => Err(String::from(
+            "cpu_max and memory_max need daemon.cgroup to be set to a delegated cgroup v2 subtree",
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Find the cgroup below `daemon.cgroup` that a pid runs in
+pub fn of_pid(pid: i64) -> Option<PathBuf> {
+    let contents = fs::read_to_string(format!("/proc/{pid}/cgroup")).ok()?;
+    let relative = contents.lines().find_map(|line| line.strip_prefix("0::"))?;
+
+    // skip reading the config for pids that are not in a process cgroup at all
+    if !relative.rsplit('/').next()?.starts_with("process-") {
+        return None;
+    }
+
+    let path = mount()?.join(relative.trim_start_matches('/'));
+    path.parent()
+        .filter(|parent| Some(*parent) == root().as_deref())?;
+
+    Some(path)
+}
+
+/// All pids in the cgroup
+pub fn pids(path: &Path) -> Vec<i64> {
+    fs::read_to_string(path.join("cgroup.procs"))
+        .unwrap_or_default()
+        .split_whitespace()
+        .filter_map(|pid| pid.parse().ok())
+        .collect()
+}
+
+/// Memory charged to the cgroup in bytes, unset when the memory controller is not enabled
+pub fn memory(path: &Path) -> Option<u64> {
+    fs::read_to_string(path.join("memory.current"))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+fn cpu_usage(path: &Path) -> Option<u64> {
+    fs::read_to_string(path.join("cpu.stat"))
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("usage_usec "))?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Sample every process cgroup twice with a single wait in between
+fn sample_all(samples: &mut HashMap<PathBuf, Sample>) {
+    let Some(root) = root() else {
+        return;
+    };
+
+    let first = fs::read_dir(root)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with("process-"))
+        })
+        .filter_map(|path| Some((cpu_usage(&path)?, path)))
+        .collect::<Vec<(u64, PathBuf)>>();
+    let started = Instant::now();
+
+    thread::sleep(Duration::from_millis(CPU_SAMPLE_MS));
+
+    for (start, path) in first {
+        if let Some(usage) = cpu_usage(&path) {
+            let percent = percent(usage.saturating_sub(start), started.elapsed());
+            let at = Instant::now();
+            samples.insert(path, Sample { usage, at, percent });
+        }
+    }
+}
+
+fn percent(used: u64, elapsed: Duration) -> f64 {
+    used as f64 / elapsed.as_micros().max(1) as f64 * 100.0
+}
+
+/// CPU usage of the whole cgroup in percent of one CPU, measured since the previous sample
+pub fn cpu_percent(path: &Path) -> Option<f64> {
+    let mut samples = samples();
+
+    if !samples.contains_key(path) {
+        sample_all(&mut samples);
+    }
+
+    let usage = cpu_usage(path)?;
+    let Some(sample) = samples.get_mut(path) else {
+        let at = Instant::now();
+        samples.insert(
+            path.to_path_buf(),
+            Sample {
+                usage,
+                at,
+                percent: 0.0,
+            },
+        );
+        return Some(0.0);
+    };
+
+    let elapsed = sample.at.elapsed();
+    if elapsed >= Duration::from_millis(CPU_SAMPLE_MS) {
+        sample.percent = percent(usage.saturating_sub(sample.usage), elapsed);
+        sample.usage = usage;
+        sample.at = Instant::now();
+    }
+
+    Some(sample.percent)
+}
+
+/// Remove the cgroups of every run of the process with `id`, killing anything still running in them
+pub fn remove(id: usize) {
+    if let Some(root) = root() {
+        runs(&root, id).iter().for_each(|path| remove_path(path));
+    }
+}
+
+/// Remove the cgroup of the run of the process with `id` started at `started`, once that run was stopped
+pub fn remove_run(id: usize, started: DateTime<Utc>) {
+    if let Some(path) = root()
+        .map(|root| path(&root, id, started))
+        .filter(|path| path.exists())
+    {
+        remove_path(&path);
+    }
+}
+
+fn remove_path(path: &Path) {
+    samples().remove(path);
+    let _ = fs::write(path.join("cgroup.kill"), "1");
+    let started = Instant::now();
+
+    while fs::remove_dir(path).is_err() && started.elapsed() < Duration::from_secs(1) {
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cpu_max() {
+        assert_eq!("50%".parse::<CpuMax>(), Ok(CpuMax(50)));
+        assert_eq!("1.5".parse::<CpuMax>(), Ok(CpuMax(150)));
+        assert_eq!("2".parse::<CpuMax>(), Ok(CpuMax(200)));
+        assert_eq!(CpuMax(25).to_string(), "25%");
+        assert!("0".parse::<CpuMax>().is_err());
+        assert!("fast".parse::<CpuMax>().is_err());
+    }
+
+    #[test]
+    fn test_runs_of_process() {
+        let root = std::env::temp_dir().join(format!("pmc-cgroup-{}", std::process::id()));
+        let started = DateTime::from_timestamp_millis(1_700_000_000_000).unwrap();
+
+        for name in ["process-1", "process-10-5", "process-1-x", "process-12"] {
+            fs::create_dir_all(root.join(name)).unwrap();
+        }
+        fs::create_dir_all(path(&root, 1, started)).unwrap();
+
+        let mut found = runs(&root, 1);
+        found.sort();
+
+        assert_eq!(
+            found,
+            vec![root.join("process-1"), root.join("process-1-1700000000000")]
+        );
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
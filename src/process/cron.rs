@@ -0,0 +1,274 @@
+use chrono::{DateTime, Datelike, Duration, Local, TimeZone, Timelike};
+use core::fmt;
+use macros_rs::then;
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{self, Visitor},
+};
+use std::str::FromStr;
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// How far ahead `Cron::next_after` looks for a matching day
+const SEARCH_DAYS: i64 = 366 * 4;
+
+/// A five field cron schedule (minute hour day-of-month month day-of-week) evaluated in local time
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cron {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+fn parse_value(value: &str, min: u32, max: u32, names: &[&str]) -> Result<u32, String> {
+    let offset = if names.len() == 12 { 1 } else { 0 };
+
+    match names
+        .iter()
+        .position(|name| name.eq_ignore_ascii_case(value))
+    {
+        Some(index) => Ok(index as u32 + offset),
+        None => match value.parse::<u32>() {
+            Ok(number) if (min..=max).contains(&number) => Ok(number),
+            _ => Err(format!("'{value}' is out of range {min}-{max}")),
+        },
+    }
+}
+
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, String> {
+    let mut bits = 0u64;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => match step.parse::<u32>() {
+                Ok(step) if step > 0 => (range, step),
+                _ => return Err(format!("invalid step '{step}'")),
+            },
+            None => (part, 1),
+        };
+
+        let (start, end) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((start, end)) => (
+                    parse_value(start, min, max, names)?,
+                    parse_value(end, min, max, names)?,
+                ),
+                None => {
+                    let start = parse_value(range, min, max, names)?;
+                    (start, if part.contains('/') { max } else { start })
+                }
+            },
+        };
+
+        if start > end {
+            return Err(format!("invalid range '{range}'"));
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+
+    Ok(bits)
+}
+
+impl Cron {
+    /// Check if the schedule fires on the day of `date`
+    fn matches_day(&self, date: &impl Datelike) -> bool {
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+
+        let day_matches = match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        };
+
+        self.months & (1 << date.month()) != 0 && day_matches
+    }
+
+    /// Check if the schedule fires during the minute of `time`
+    pub fn matches<Tz: TimeZone>(&self, time: &DateTime<Tz>) -> bool {
+        self.minutes & (1 << time.minute()) != 0
+            && self.hours & (1 << time.hour()) != 0
+            && self.matches_day(time)
+    }
+
+    /// Find the first minute after `time` the schedule fires at
+    pub fn next_after(&self, time: DateTime<Local>) -> Option<DateTime<Local>> {
+        let start = time.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let first = (start.hour(), start.minute());
+
+        // only the minutes of days the schedule fires on are looked at
+        (0..SEARCH_DAYS).find_map(|offset| {
+            let date = start.date_naive() + Duration::days(offset);
+            then!(!self.matches_day(&date), return None);
+
+            (0..24u32)
+                .filter(|hour| self.hours & (1 << hour) != 0)
+                .flat_map(|hour| {
+                    (0..60u32)
+                        .filter(|minute| self.minutes & (1 << minute) != 0)
+                        .map(move |minute| (hour, minute))
+                })
+                .filter(|at| offset > 0 || *at >= first)
+                .find_map(|(hour, minute)| {
+                    // a minute skipped by a daylight saving change does not exist
+                    Local
+                        .from_local_datetime(&date.and_hms_opt(hour, minute, 0)?)
+                        .earliest()
+                })
+        })
+    }
+}
+
+impl FromStr for Cron {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let expression = s.split_whitespace().collect::<Vec<&str>>().join(" ");
+        let expanded = match expression.to_lowercase().as_str() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            _ => &expression,
+        };
+
+        let fields = expanded.split(' ').collect::<Vec<&str>>();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(format!(
+                "invalid cron '{s}', expected 5 fields (minute hour day month weekday)"
+            ));
+        };
+
+        let invalid = |err: String| format!("invalid cron '{s}': {err}");
+        let weekdays_bits = parse_field(weekdays, 0, 7, &WEEKDAYS).map_err(invalid)?;
+
+        Ok(Cron {
+            minutes: parse_field(minutes, 0, 59, &[]).map_err(invalid)?,
+            hours: parse_field(hours, 0, 23, &[]).map_err(invalid)?,
+            days: parse_field(days, 1, 31, &[]).map_err(invalid)?,
+            months: parse_field(months, 1, 12, &MONTHS).map_err(invalid)?,
+            // both 0 and 7 mean sunday
+            weekdays: (weekdays_bits | weekdays_bits >> 7) & 0x7f,
+            any_day: days.starts_with('*'),
+            any_weekday: weekdays.starts_with('*'),
+            expression,
+        })
+    }
+}
+
+impl fmt::Display for Cron {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.expression)
+    }
+}
+
+impl Serialize for Cron {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.expression)
+    }
+}
+
+impl<'de> Deserialize<'de> for Cron {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct CronVisitor;
+
+        impl<'de> Visitor<'de> for CronVisitor {
+            type Value = Cron;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a cron expression like \"0 3 * * *\"")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Cron, E> {
+                Cron::from_str(value).map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(CronVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(2024, 1, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_cron() {
+        assert!("0 3 * * *".parse::<Cron>().is_ok());
+        assert!("*/15 9-17 * jan-jun mon-fri".parse::<Cron>().is_ok());
+        assert!("@daily".parse::<Cron>().is_ok());
+        assert!("0 3 * *".parse::<Cron>().is_err());
+        assert!("60 * * * *".parse::<Cron>().is_err());
+        assert!("*/0 * * * *".parse::<Cron>().is_err());
+        assert!("5-1 * * * *".parse::<Cron>().is_err());
+    }
+
+    #[test]
+    fn test_cron_matches() {
+        let nightly: Cron = "0 3 * * *".parse().unwrap();
+        let weekdays: Cron = "*/15 9-17 * * mon-fri".parse().unwrap();
+        let either: Cron = "0 0 13 * 5".parse().unwrap();
+
+        // 2024-01-01 is a monday
+        assert!(nightly.matches(&local(1, 3, 0)));
+        assert!(!nightly.matches(&local(1, 3, 1)));
+        assert!(weekdays.matches(&local(1, 9, 45)));
+        assert!(!weekdays.matches(&local(6, 9, 45)));
+        assert!(either.matches(&local(13, 0, 0)));
+        assert!(either.matches(&local(5, 0, 0)));
+        assert!(!either.matches(&local(6, 0, 0)));
+        assert!(
+            "0 0 * * 7"
+                .parse::<Cron>()
+                .unwrap()
+                .matches(&local(7, 0, 0))
+        );
+    }
+
+    #[test]
+    fn test_cron_next_after() {
+        let nightly: Cron = "0 3 * * *".parse().unwrap();
+
+        assert_eq!(nightly.next_after(local(1, 2, 30)), Some(local(1, 3, 0)));
+        assert_eq!(nightly.next_after(local(1, 3, 0)), Some(local(2, 3, 0)));
+        assert_eq!(
+            "30 1 1 * *"
+                .parse::<Cron>()
+                .unwrap()
+                .next_after(local(15, 0, 0)),
+            Local.with_ymd_and_hms(2024, 2, 1, 1, 30, 0).single()
+        );
+        assert_eq!(
+            "*/20 9 * * mon"
+                .parse::<Cron>()
+                .unwrap()
+                .next_after(local(1, 9, 20)),
+            Some(local(1, 9, 40))
+        );
+        assert_eq!(
+            "0 0 30 2 *"
+                .parse::<Cron>()
+                .unwrap()
+                .next_after(local(1, 0, 0)),
+            None
+        );
+    }
+}
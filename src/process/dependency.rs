@@ -0,0 +1,177 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+struct Graph<'a> {
+    depends_on: BTreeMap<&'a str, BTreeSet<&'a str>>,
+    done: BTreeSet<&'a str>,
+    sorted: Vec<&'a str>,
+    /// Set when bad edges are skipped instead of failing, holds why each one was skipped
+    skipped: Option<Vec<String>>,
+}
+
+impl<'a> Graph<'a> {
+    /// Depth first visit, `path` holds the names currently being visited to report cycles
+    fn visit(&mut self, name: &'a str, path: &mut Vec<&'a str>) -> Result<(), String> {
+        if self.done.contains(name) {
+            return Ok(());
+        }
+
+        if let Some(start) = path.iter().position(|visiting| *visiting == name) {
+            let mut cycle = path[start..].to_vec();
+            cycle.push(name);
+            return self.skip(format!("dependency cycle {}", cycle.join(" -> ")));
+        }
+
+        path.push(name);
+        for dependency in self.depends_on.get(name).cloned().unwrap_or_default() {
+            if !self.depends_on.contains_key(dependency) {
+                self.skip(format!(
+                    "{name} depends on {dependency}, which is not a process"
+                ))?;
+                continue;
+            }
+            self.visit(dependency, path)?;
+        }
+        path.pop();
+
+        self.done.insert(name);
+        self.sorted.push(name);
+        Ok(())
+    }
+
+    fn skip(&mut self, reason: String) -> Result<(), String> {
+        let Some(skipped) = &mut self.skipped else {
+            return Err(reason);
+        };
+
+        skipped.push(reason);
+        Ok(())
+    }
+
+    fn new(nodes: &'a [(impl Clone, String, Vec<String>)], skip: bool) -> Self {
+        let mut graph = Graph {
+            depends_on: BTreeMap::new(),
+            done: BTreeSet::new(),
+            sorted: vec![],
+            skipped: skip.then(Vec::new),
+        };
+
+        // instances share a name, their dependencies are merged
+        for (_, name, depends_on) in nodes {
+            graph
+                .depends_on
+                .entry(name.as_str())
+                .or_default()
+                .extend(depends_on.iter().map(String::as_str));
+        }
+
+        graph
+    }
+
+    fn keys<K: Clone>(&self, nodes: &[(K, String, Vec<String>)]) -> Vec<K> {
+        self.sorted
+            .iter()
+            .flat_map(|name| nodes.iter().filter(move |(_, node, _)| node == name))
+            .map(|(key, _, _)| key.clone())
+            .collect()
+    }
+}
+
+/// Order `(key, name, depends_on)` nodes so every process comes after the processes it depends on, otherwise keeping the given order
+pub fn order<K: Clone>(nodes: &[(K, String, Vec<String>)]) -> Result<Vec<K>, String> {
+    let mut graph = Graph::new(nodes, false);
+
+    for (_, name, _) in nodes {
+        graph.visit(name, &mut vec![])?;
+    }
+
+    Ok(graph.keys(nodes))
+}
+
+/// Order only the nodes named in `names` and what they depend on, unknown dependencies and
+/// edges closing a cycle are skipped and returned next to the order
+pub fn order_lenient<K: Clone>(
+    nodes: &[(K, String, Vec<String>)],
+    names: &[&str],
+) -> (Vec<K>, Vec<String>) {
+    let mut graph = Graph::new(nodes, true);
+
+    for name in names {
+        let _ = graph.visit(name, &mut vec![]);
+    }
+
+    (graph.keys(nodes), graph.skipped.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: usize, name: &str, depends_on: &[&str]) -> (usize, String, Vec<String>) {
+        (
+            id,
+            name.to_string(),
+            depends_on.iter().map(|name| name.to_string()).collect(),
+        )
+    }
+
+    #[test]
+    fn test_dependency_order() {
+        let nodes = [
+            node(0, "api", &["db-proxy", "queue"]),
+            node(1, "queue", &["db-proxy"]),
+            node(2, "db-proxy", &[]),
+            node(3, "api", &["db-proxy", "queue"]),
+            node(4, "cron", &[]),
+        ];
+
+        assert_eq!(order(&nodes), Ok(vec![2, 1, 0, 3, 4]));
+    }
+
+    #[test]
+    fn test_dependency_errors() {
+        let cycle = [
+            node(0, "a", &["b"]),
+            node(1, "b", &["c"]),
+            node(2, "c", &["a"]),
+        ];
+        let missing = [node(0, "api", &["db"])];
+
+        assert_eq!(
+            order(&cycle),
+            Err(String::from("dependency cycle a -> b -> c -> a"))
+        );
+        assert_eq!(
+            order(&missing),
+            Err(String::from("api depends on db, which is not a process"))
+        );
+        assert!(order(&[node(0, "self", &["self"])]).is_err());
+    }
+
+    #[test]
+    fn test_dependency_order_lenient() {
+        let nodes = [
+            node(0, "api", &["db", "queue"]),
+            node(1, "queue", &["api"]),
+            node(2, "db", &[]),
+            node(3, "a", &["b"]),
+            node(4, "b", &["a", "gone"]),
+        ];
+
+        // the broken part of the graph is not looked at
+        assert_eq!(order_lenient(&nodes, &["db"]), (vec![2], vec![]));
+
+        let (ids, skipped) = order_lenient(&nodes, &["api"]);
+        assert_eq!(ids, vec![2, 1, 0]);
+        assert_eq!(skipped, ["dependency cycle api -> queue -> api"]);
+
+        let (ids, skipped) = order_lenient(&nodes, &["b"]);
+        assert_eq!(ids, vec![3, 4]);
+        assert_eq!(
+            skipped,
+            [
+                "dependency cycle b -> a -> b",
+                "b depends on gone, which is not a process"
+            ]
+        );
+    }
+}
@@ -0,0 +1,236 @@
+use super::Env;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+use utoipa::ToSchema;
+
+/// A value loaded from an env file, with the file it came from
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+pub struct FileValue {
+    pub value: String,
+    pub file: String,
+}
+
+pub type FileEnv = BTreeMap<String, FileValue>;
+
+/// Find the quote closing a value, skipping escaped quotes in double quoted values
+fn closing(body: &str, quote: char) -> Option<usize> {
+    let mut chars = body.char_indices();
+
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\\' if quote == '"' => {
+                chars.next();
+            }
+            c if c == quote => return Some(index),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Expand `$NAME`, `${NAME}` and `${NAME:-default}`, unset variables expand to nothing
+fn expand(
+    value: &str,
+    escapes: bool,
+    resolve: &dyn Fn(&str) -> Option<String>,
+) -> Result<String, String> {
+    let mut expanded = String::new();
+    let mut chars = value.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if escapes => match chars.next() {
+                Some('n') => expanded.push('\n'),
+                Some('t') => expanded.push('\t'),
+                Some('r') => expanded.push('\r'),
+                Some(other) => expanded.push(other),
+                None => expanded.push('\\'),
+            },
+            '$' if chars.peek() == Some(&'{') => {
+                chars.next();
+                let inner = chars.by_ref().take_while(|c| *c != '}').collect::<String>();
+
+                match inner.split_once(":-") {
+                    Some((name, default)) => {
+                        match resolve(name).filter(|value| !value.is_empty()) {
+                            Some(value) => expanded.push_str(&value),
+                            None => expanded.push_str(&expand(default, false, resolve)?),
+                        }
+                    }
+                    None if valid_key(&inner) => {
+                        expanded.push_str(&resolve(&inner).unwrap_or_default())
+                    }
+                    None => return Err(format!("invalid variable '${{{inner}}}'")),
+                }
+            }
+            '$' if chars
+                .peek()
+                .is_some_and(|c| c.is_ascii_alphabetic() || *c == '_') =>
+            {
+                let mut name = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                    name.push(c);
+                }
+                expanded.push_str(&resolve(&name).unwrap_or_default());
+            }
+            c => expanded.push(c),
+        }
+    }
+
+    Ok(expanded)
+}
+
+fn valid_key(key: &str) -> bool {
+    key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Parse the contents of an env file, variables not set earlier in the file are looked up with `lookup`
+pub fn parse(
+    contents: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<Vec<(String, String)>, String> {
+    let mut values: Vec<(String, String)> = vec![];
+    let mut lines = contents.lines().enumerate();
+
+    while let Some((index, line)) = lines.next() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, raw)) = line.split_once('=') else {
+            return Err(format!("line {}: expected KEY=VALUE", index + 1));
+        };
+
+        let key = key.trim();
+        if !valid_key(key) {
+            return Err(format!("line {}: invalid key '{key}'", index + 1));
+        }
+
+        let resolve = |name: &str| {
+            values
+                .iter()
+                .rev()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.clone())
+                .or_else(|| lookup(name))
+        };
+
+        let raw = raw.trim();
+        let value = match raw.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                // quoted values may span several lines
+                let mut body = raw[1..].to_string();
+                while closing(&body, quote).is_none() {
+                    match lines.next() {
+                        Some((_, next)) => {
+                            body.push('\n');
+                            body.push_str(next);
+                        }
+                        None => {
+                            return Err(format!("line {}: unterminated quote in {key}", index + 1));
+                        }
+                    }
+                }
+
+                let inner = &body[..closing(&body, quote).unwrap_or(body.len())];
+                match quote {
+                    '\'' => inner.to_string(),
+                    _ => expand(inner, true, &resolve)
+                        .map_err(|err| format!("line {}: {err}", index + 1))?,
+                }
+            }
+            _ => {
+                let value = raw.split(" #").next().unwrap_or_default().trim();
+                expand(value, false, &resolve)
+                    .map_err(|err| format!("line {}: {err}", index + 1))?
+            }
+        };
+
+        values.push((key.to_string(), value));
+    }
+
+    Ok(values)
+}
+
+/// Read the env files in order relative to `cwd`, later files override earlier ones and can use their values
+pub fn load(files: &[PathBuf], cwd: &Path, env: &Env) -> Result<FileEnv, String> {
+    let mut loaded = FileEnv::new();
+
+    for file in files {
+        let path = cwd.join(file);
+        let contents = fs::read_to_string(&path)
+            .map_err(|err| format!("failed to read env file {}: {err}", path.display()))?;
+
+        let lookup = |name: &str| {
+            loaded
+                .get(name)
+                .map(|loaded| loaded.value.clone())
+                .or_else(|| env.get(name).cloned())
+                .or_else(|| std::env::var(name).ok())
+        };
+
+        let values = parse(&contents, lookup)
+            .map_err(|err| format!("invalid env file {}: {err}", path.display()))?;
+        for (key, value) in values {
+            let file = file.display().to_string();
+            loaded.insert(key, FileValue { value, file });
+        }
+    }
+
+    Ok(loaded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(contents: &str) -> Vec<(String, String)> {
+        parse(contents, |name| {
+            (name == "HOME").then(|| String::from("/home/app"))
+        })
+        .unwrap()
+    }
+
+    fn pair(key: &str, value: &str) -> (String, String) {
+        (key.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn test_parse_env_file() {
+        let contents = "# database\nexport HOST=localhost\nPORT = 5432 # default\nURL=postgres://${HOST}:$PORT/app\n\nNAME='literal $HOST'\nKEY=\"line\\nnext\"\n";
+
+        assert_eq!(
+            parsed(contents),
+            vec![
+                pair("HOST", "localhost"),
+                pair("PORT", "5432"),
+                pair("URL", "postgres://localhost:5432/app"),
+                pair("NAME", "literal $HOST"),
+                pair("KEY", "line\nnext"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_env_file_interpolation() {
+        assert_eq!(
+            parsed("DATA=$HOME/data"),
+            vec![pair("DATA", "/home/app/data")]
+        );
+        assert_eq!(
+            parsed("LEVEL=${LOG_LEVEL:-info}"),
+            vec![pair("LEVEL", "info")]
+        );
+        assert_eq!(parsed("CERT=\"a\nb\""), vec![pair("CERT", "a\nb")]);
+        assert!(parse("NOT VALID", |_| None).is_err());
+        assert!(parse("KEY=\"open", |_| None).is_err());
+    }
+}
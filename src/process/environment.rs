@@ -0,0 +1,113 @@
+use super::{Env, glob};
+use core::fmt;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+/// Variables passed through in allowlist mode when no `env_allow` is given
+pub const DEFAULT_ALLOW: [&str; 7] = ["PATH", "HOME", "USER", "LOGNAME", "SHELL", "LANG", "TZ"];
+
+/// Which part of the environment pmc runs in is handed to a process
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum EnvMode {
+    /// Keep the environment of the caller that created the process and of the daemon
+    #[default]
+    Inherit,
+    /// Start with an empty environment, only explicitly set variables are passed
+    Clean,
+    /// Like clean, but the allowed variables are copied from the caller when the process is created
+    Allowlist,
+}
+
+impl EnvMode {
+    /// Check if the process picks up the environment of whoever starts or restarts it
+    pub fn inherits(&self) -> bool {
+        *self == EnvMode::Inherit
+    }
+}
+
+impl FromStr for EnvMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "inherit" => Ok(EnvMode::Inherit),
+            "clean" => Ok(EnvMode::Clean),
+            "allowlist" => Ok(EnvMode::Allowlist),
+            _ => Err(format!(
+                "invalid env mode '{s}', expected inherit, clean or allowlist"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for EnvMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EnvMode::Inherit => write!(f, "inherit"),
+            EnvMode::Clean => write!(f, "clean"),
+            EnvMode::Allowlist => write!(f, "allowlist"),
+        }
+    }
+}
+
+/// Patterns of the variables passed through in allowlist mode
+pub fn allowlist(allow: &[String]) -> Vec<String> {
+    match allow.is_empty() {
+        true => DEFAULT_ALLOW.map(String::from).to_vec(),
+        false => allow.to_vec(),
+    }
+}
+
+/// Environment stored with a new process, taken from the variables of the caller
+pub fn capture(
+    mode: EnvMode,
+    allow: &[String],
+    vars: impl Iterator<Item = (String, String)>,
+) -> Env {
+    match mode {
+        EnvMode::Inherit => vars.collect(),
+        EnvMode::Clean => Env::new(),
+        EnvMode::Allowlist => {
+            let allow = allowlist(allow);
+            vars.filter(|(key, _)| allow.iter().any(|pattern| glob::matches(pattern, key)))
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars() -> impl Iterator<Item = (String, String)> {
+        [
+            ("PATH", "/usr/bin"),
+            ("HOME", "/root"),
+            ("LC_ALL", "C"),
+            ("OLDPWD", "/tmp"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+    }
+
+    #[test]
+    fn test_capture_env() {
+        assert_eq!(capture(EnvMode::Inherit, &[], vars()).len(), 4);
+        assert!(capture(EnvMode::Clean, &[], vars()).is_empty());
+
+        let allowed = capture(EnvMode::Allowlist, &[], vars());
+        assert_eq!(allowed.keys().collect::<Vec<_>>(), ["HOME", "PATH"]);
+
+        let allowed = capture(EnvMode::Allowlist, &[String::from("LC_*")], vars());
+        assert_eq!(allowed.keys().collect::<Vec<_>>(), ["LC_ALL"]);
+    }
+
+    #[test]
+    fn test_parse_env_mode() {
+        assert_eq!("Allowlist".parse::<EnvMode>(), Ok(EnvMode::Allowlist));
+        assert_eq!(EnvMode::Clean.to_string(), "clean");
+        assert!("none".parse::<EnvMode>().is_err());
+    }
+}
@@ -0,0 +1,120 @@
+use chrono::{DateTime, Utc};
+use macros_rs::ternary;
+use nix::{
+    sys::{signal::Signal, wait::WaitStatus},
+    unistd::Pid,
+};
+use std::process::Child;
+
+/// How a run of a process ended, decoded from the wait status recorded when it was reaped
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExitStatus {
+    /// Exit code of the process, not set when it was killed by a signal
+    pub code: Option<i32>,
+    pub signal: Option<Signal>,
+    /// Status as returned by waitpid
+    pub raw: i32,
+    pub at: DateTime<Utc>,
+}
+
+impl ExitStatus {
+    /// Decode a raw wait status, `None` when it is not the status of a terminated process
+    pub fn from_wait(raw: i32, at: DateTime<Utc>) -> Option<Self> {
+        let (code, signal) = match WaitStatus::from_raw(Pid::from_raw(0), raw).ok()? {
+            WaitStatus::Exited(_, code) => (Some(code), None),
+            WaitStatus::Signaled(_, signal, _) => (None, Some(signal)),
+            _ => return None,
+        };
+
+        Some(ExitStatus {
+            code,
+            signal,
+            raw,
+            at,
+        })
+    }
+
+    /// Check if the run exited on its own with code 0
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+}
+
+/// Raw wait status of a terminated process, laid out like waitpid returns it
+pub fn raw(status: WaitStatus) -> Option<i32> {
+    match status {
+        WaitStatus::Exited(_, code) => Some((code & 0xff) << 8),
+        WaitStatus::Signaled(_, signal, core_dumped) => {
+            Some(signal as i32 | ternary!(core_dumped, 0x80, 0))
+        }
+        _ => None,
+    }
+}
+
+/// Wait for `child` to terminate and return its raw wait status
+///
+/// On linux the child is left unreaped, so its pid stays taken until the caller recorded the status
+#[cfg(target_os = "linux")]
+pub fn wait(child: &mut Child) -> Option<i32> {
+    use nix::{
+        errno::Errno,
+        sys::wait::{Id, WaitPidFlag, waitid},
+    };
+
+    let pid = Pid::from_raw(child.id() as i32);
+
+    loop {
+        match waitid(Id::Pid(pid), WaitPidFlag::WEXITED | WaitPidFlag::WNOWAIT) {
+            Ok(status) => return raw(status),
+            Err(Errno::EINTR) => continue,
+            Err(_) => return None,
+        }
+    }
+}
+
+/// Wait for `child` to terminate and return its raw wait status
+#[cfg(not(target_os = "linux"))]
+pub fn wait(child: &mut Child) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    child.wait().ok().map(|status| status.into_raw())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    #[test]
+    fn test_exit_status_from_wait() {
+        let at = Utc::now();
+        let exited = |code: i32| ExitStatus::from_wait(code << 8, at).unwrap();
+
+        assert_eq!(exited(0).code, Some(0));
+        assert_eq!(exited(1).code, Some(1));
+        // a code above 128 is not mistaken for a signal
+        assert_eq!(exited(130).code, Some(130));
+        assert_eq!(exited(130).signal, None);
+
+        let killed = ExitStatus::from_wait(libc::SIGKILL, at).unwrap();
+        assert_eq!(killed.signal, Some(Signal::SIGKILL));
+        assert_eq!(killed.code, None);
+
+        let dumped = ExitStatus::from_wait(libc::SIGSEGV | 0x80, at).unwrap();
+        assert_eq!(dumped.signal, Some(Signal::SIGSEGV));
+        assert_eq!(dumped.raw, libc::SIGSEGV | 0x80);
+    }
+
+    #[test]
+    fn test_wait_records_real_status() {
+        let mut child = Command::new("sh").args(["-c", "exit 130"]).spawn().unwrap();
+        let status = ExitStatus::from_wait(wait(&mut child).unwrap(), Utc::now()).unwrap();
+        assert_eq!((status.code, status.signal), (Some(130), None));
+        assert_eq!(child.wait().unwrap().code(), Some(130));
+
+        let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+        child.kill().unwrap();
+        let status = ExitStatus::from_wait(wait(&mut child).unwrap(), Utc::now()).unwrap();
+        assert_eq!((status.code, status.signal), (None, Some(Signal::SIGKILL)));
+        let _ = child.wait();
+    }
+}
@@ -0,0 +1,47 @@
+/// Match `key` against a pattern where `*` matches any run of characters, ignoring case
+pub fn matches(pattern: &str, key: &str) -> bool {
+    let pattern = pattern.to_uppercase().chars().collect::<Vec<char>>();
+    let key = key.to_uppercase().chars().collect::<Vec<char>>();
+
+    let (mut p, mut k) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while k < key.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, k));
+                p += 1;
+            }
+            Some(c) if *c == key[k] => {
+                p += 1;
+                k += 1;
+            }
+            // let the last star swallow one more character and retry
+            _ => match star {
+                Some((star_p, star_k)) => {
+                    star = Some((star_p, star_k + 1));
+                    p = star_p + 1;
+                    k = star_k + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_matches() {
+        assert!(matches("*_TOKEN", "github_token"));
+        assert!(matches("LC_*", "LC_ALL"));
+        assert!(matches("*a*b*", "xaxxbx"));
+        assert!(matches("*", ""));
+        assert!(!matches("LC_*", "LANG"));
+        assert!(!matches("PATH", "PATHS"));
+    }
+}
@@ -1,4 +1,4 @@
-use crate::process::Remote;
+use crate::process::{Options, Remote, backoff::Backoff, instances::Instances, search::Filters};
 use macros_rs::{fmtstr, string};
 use reqwest::Client;
 use reqwest::header::{HeaderMap, HeaderValue};
@@ -21,6 +21,15 @@ struct CreateBody<'c> {
     pub script: &'c String,
     pub path: PathBuf,
     pub watch: &'c Option<String>,
+    pub instances: &'c Instances,
+    #[serde(flatten)]
+    pub options: &'c Options,
+    pub backoff: &'c Backoff,
+}
+
+#[derive(Serialize)]
+struct ScaleBody<'s> {
+    pub instances: &'s Instances,
 }
 
 pub mod sync {
@@ -91,12 +100,44 @@ pub fn logs(
     })
 }
 
+pub fn env(
+    Remote { address, token, .. }: &Remote,
+    id: usize,
+    reveal: bool,
+) -> Result<sync::Response, anyhow::Error> {
+    let (client, headers) = sync::client(token);
+    Ok(client
+        .get(fmtstr!("{address}/process/{id}/env?reveal={reveal}"))
+        .headers(headers)
+        .send()?)
+}
+
+pub fn search(
+    Remote { address, token, .. }: &Remote,
+    id: usize,
+    kind: &str,
+    filters: &Filters,
+    limit: usize,
+) -> Result<sync::Response, anyhow::Error> {
+    let (client, headers) = sync::client(token);
+    Ok(client
+        .get(fmtstr!("{address}/process/{id}/logs/{kind}/search"))
+        .query(filters)
+        .query(&[("limit", limit)])
+        .headers(headers)
+        .send()?)
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn create(
     Remote { address, token, .. }: &Remote,
     name: &String,
     script: &String,
     path: PathBuf,
     watch: &Option<String>,
+    instances: &Instances,
+    options: &Options,
+    backoff: &Backoff,
 ) -> Result<sync::Response, anyhow::Error> {
     let (client, headers) = sync::client(token);
     let content = CreateBody {
@@ -104,13 +145,17 @@ pub fn create(
         script,
         path,
         watch,
+        instances,
+        options,
+        backoff,
     };
 
     Ok(client
         .post(fmtstr!("{address}/process/create"))
         .json(&content)
         .headers(headers)
-        .send()?)
+        .send()?
+        .error_for_status()?)
 }
 
 pub fn restart(
@@ -126,7 +171,8 @@ pub fn restart(
         .post(fmtstr!("{address}/process/{id}/action"))
         .json(&content)
         .headers(headers)
-        .send()?)
+        .send()?
+        .error_for_status()?)
 }
 
 pub fn rename(
@@ -142,6 +188,39 @@ pub fn rename(
         .send()?)
 }
 
+pub fn reload(
+    Remote { address, token, .. }: &Remote,
+    id: usize,
+) -> Result<sync::Response, anyhow::Error> {
+    let (client, headers) = sync::client(token);
+    let content = ActionBody {
+        method: string!("reload"),
+    };
+
+    Ok(client
+        .post(fmtstr!("{address}/process/{id}/action"))
+        .json(&content)
+        .headers(headers)
+        .send()?
+        .error_for_status()?)
+}
+
+pub fn scale(
+    Remote { address, token, .. }: &Remote,
+    id: usize,
+    instances: &Instances,
+) -> Result<sync::Response, anyhow::Error> {
+    let (client, headers) = sync::client(token);
+    let content = ScaleBody { instances };
+
+    Ok(client
+        .post(fmtstr!("{address}/process/{id}/scale"))
+        .json(&content)
+        .headers(headers)
+        .send()?
+        .error_for_status()?)
+}
+
 // merge into one function
 pub fn stop(
     Remote { address, token, .. }: &Remote,
@@ -0,0 +1,145 @@
+use core::fmt;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instances {
+    Count(usize),
+    Max,
+}
+
+impl Instances {
+    /// Resolve the amount of instances to spawn, `max` uses every available core
+    pub fn count(&self) -> usize {
+        match self {
+            Instances::Count(count) => (*count).max(1),
+            Instances::Max => num_cpus::get(),
+        }
+    }
+}
+
+/// Plan scaling a group of `(index, id)` instances to `count`, returns the indices to add and the ids to remove
+///
+/// New instances take the lowest free indices and the instances with the highest indices are removed first,
+/// the indices of the instances that are kept never change
+pub fn scale_plan(group: &[(usize, usize)], count: usize) -> (Vec<usize>, Vec<usize>) {
+    let mut group = group.to_vec();
+    group.sort();
+
+    if count <= group.len() {
+        let surplus = group.split_off(count);
+        return (vec![], surplus.into_iter().map(|(_, id)| id).collect());
+    }
+
+    let free = (0..)
+        .filter(|index| !group.iter().any(|(instance, _)| instance == index))
+        .take(count - group.len())
+        .collect();
+
+    (free, vec![])
+}
+
+impl Default for Instances {
+    fn default() -> Self {
+        Instances::Count(1)
+    }
+}
+
+impl FromStr for Instances {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        if s.eq_ignore_ascii_case("max") {
+            return Ok(Instances::Max);
+        }
+
+        match s.parse::<usize>() {
+            Ok(0) => Err(String::from("instances must be at least 1")),
+            Ok(count) => Ok(Instances::Count(count)),
+            Err(_) => Err(format!(
+                "invalid instances '{s}', expected a number or \"max\""
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Instances {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Instances::Count(count) => write!(f, "{count}"),
+            Instances::Max => write!(f, "max"),
+        }
+    }
+}
+
+impl Serialize for Instances {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Instances::Count(count) => serializer.serialize_u64(*count as u64),
+            Instances::Max => serializer.serialize_str("max"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Instances {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Number(u64),
+            Text(String),
+        }
+
+        match Raw::deserialize(deserializer)? {
+            Raw::Number(count) => Instances::from_str(&count.to_string()),
+            Raw::Text(text) => Instances::from_str(&text),
+        }
+        .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_instances() {
+        assert_eq!("4".parse::<Instances>(), Ok(Instances::Count(4)));
+        assert_eq!("max".parse::<Instances>(), Ok(Instances::Max));
+        assert_eq!("MAX".parse::<Instances>(), Ok(Instances::Max));
+        assert!("0".parse::<Instances>().is_err());
+        assert!("many".parse::<Instances>().is_err());
+    }
+
+    #[test]
+    fn test_instances_count() {
+        assert_eq!(Instances::default().count(), 1);
+        assert_eq!(Instances::Count(3).count(), 3);
+        assert_eq!(Instances::Max.count(), num_cpus::get());
+    }
+
+    #[test]
+    fn test_scale_plan() {
+        // instance 1 was removed earlier, its index is the first one reused
+        let group = [(0, 10), (2, 12), (3, 13)];
+
+        assert_eq!(scale_plan(&group, 5), (vec![1, 4], vec![]));
+        assert_eq!(scale_plan(&group, 3), (vec![], vec![]));
+        assert_eq!(scale_plan(&group, 2), (vec![], vec![13]));
+        assert_eq!(scale_plan(&group, 1), (vec![], vec![12, 13]));
+        assert_eq!(scale_plan(&[(2, 12), (0, 10)], 1), (vec![], vec![12]));
+        assert_eq!(scale_plan(&[], 2), (vec![0, 1], vec![]));
+    }
+
+    #[test]
+    fn test_deserialize_instances() {
+        let count: Instances = serde_json::from_str("2").unwrap();
+        let max: Instances = serde_json::from_str("\"max\"").unwrap();
+
+        assert_eq!(count, Instances::Count(2));
+        assert_eq!(max, Instances::Max);
+        assert!(serde_json::from_str::<Instances>("0").is_err());
+    }
+}
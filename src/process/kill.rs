@@ -0,0 +1,121 @@
+use core::fmt;
+use nix::sys::signal::Signal;
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{self, Visitor},
+};
+use std::str::FromStr;
+
+/// Milliseconds to wait for a process tree to exit before it gets SIGKILL
+pub const DEFAULT_KILL_TIMEOUT: u64 = 5000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KillSignal(pub Signal);
+
+impl Default for KillSignal {
+    fn default() -> Self {
+        KillSignal(Signal::SIGTERM)
+    }
+}
+
+impl FromStr for KillSignal {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        if let Ok(number) = s.parse::<i32>() {
+            return Signal::try_from(number)
+                .map(KillSignal)
+                .map_err(|_| format!("invalid signal number '{number}'"));
+        }
+
+        let name = s.to_uppercase();
+        let name = if name.starts_with("SIG") {
+            name
+        } else {
+            format!("SIG{name}")
+        };
+
+        Signal::from_str(&name)
+            .map(KillSignal)
+            .map_err(|_| format!("invalid signal '{s}', expected a name like SIGINT or a number"))
+    }
+}
+
+impl fmt::Display for KillSignal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.as_str())
+    }
+}
+
+impl Serialize for KillSignal {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for KillSignal {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SignalVisitor;
+
+        impl<'de> Visitor<'de> for SignalVisitor {
+            type Value = KillSignal;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a signal name or number")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<KillSignal, E> {
+                KillSignal::from_str(value).map_err(E::custom)
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<KillSignal, E> {
+                KillSignal::from_str(&value.to_string()).map_err(E::custom)
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<KillSignal, E> {
+                KillSignal::from_str(&value.to_string()).map_err(E::custom)
+            }
+
+            fn visit_unit<E: de::Error>(self) -> Result<KillSignal, E> {
+                Ok(KillSignal::default())
+            }
+
+            fn visit_none<E: de::Error>(self) -> Result<KillSignal, E> {
+                Ok(KillSignal::default())
+            }
+        }
+
+        deserializer.deserialize_any(SignalVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_kill_signal() {
+        assert_eq!(
+            "SIGINT".parse::<KillSignal>(),
+            Ok(KillSignal(Signal::SIGINT))
+        );
+        assert_eq!(
+            "quit".parse::<KillSignal>(),
+            Ok(KillSignal(Signal::SIGQUIT))
+        );
+        assert_eq!("9".parse::<KillSignal>(), Ok(KillSignal(Signal::SIGKILL)));
+        assert!("SIGNOPE".parse::<KillSignal>().is_err());
+        assert!("99".parse::<KillSignal>().is_err());
+    }
+
+    #[test]
+    fn test_deserialize_kill_signal() {
+        let name: KillSignal = serde_json::from_str("\"SIGHUP\"").unwrap();
+        let number: KillSignal = serde_json::from_str("2").unwrap();
+
+        assert_eq!(name, KillSignal(Signal::SIGHUP));
+        assert_eq!(number, KillSignal(Signal::SIGINT));
+    }
+}
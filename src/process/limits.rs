@@ -0,0 +1,214 @@
+use super::memory::MaxMemory;
+use core::fmt;
+use nix::{
+    sys::resource::{self, RLIM_INFINITY, Resource, rlim_t},
+    unistd,
+};
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{self, Visitor},
+};
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+/// Value of a resource limit, sizes can be written like `512M`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Limit {
+    Value(u64),
+    Unlimited,
+}
+
+impl Limit {
+    fn raw(&self) -> rlim_t {
+        match self {
+            Limit::Value(value) => *value as rlim_t,
+            Limit::Unlimited => RLIM_INFINITY,
+        }
+    }
+}
+
+impl FromStr for Limit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "unlimited" | "infinity" => Ok(Limit::Unlimited),
+            value => match value.parse::<u64>() {
+                Ok(value) => Ok(Limit::Value(value)),
+                Err(_) => MaxMemory::from_str(value)
+                    .map(|MaxMemory(bytes)| Limit::Value(bytes))
+                    .map_err(|_| {
+                        format!(
+                            "invalid limit '{s}', expected a number, a size like 1G or unlimited"
+                        )
+                    }),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Value(value) => write!(f, "{value}"),
+            Limit::Unlimited => write!(f, "unlimited"),
+        }
+    }
+}
+
+impl Serialize for Limit {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Limit::Value(value) => serializer.serialize_u64(*value),
+            Limit::Unlimited => serializer.serialize_str("unlimited"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Limit {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct LimitVisitor;
+
+        impl<'de> Visitor<'de> for LimitVisitor {
+            type Value = Limit;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a number, a size like \"1G\" or \"unlimited\"")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Limit, E> {
+                Limit::from_str(value).map_err(E::custom)
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<Limit, E> {
+                Ok(Limit::Value(value))
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<Limit, E> {
+                u64::try_from(value)
+                    .map(Limit::Value)
+                    .map_err(|_| E::custom(format!("invalid limit '{value}'")))
+            }
+        }
+
+        deserializer.deserialize_any(LimitVisitor)
+    }
+}
+
+/// Resource limits applied with setrlimit before the process starts, unset limits are inherited from the daemon
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(default)]
+pub struct Limits {
+    /// Maximum number of open files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<u64>, example = 65536)]
+    pub open_files: Option<Limit>,
+    /// Maximum size of core dumps in bytes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>, example = "0")]
+    pub core_size: Option<Limit>,
+    /// Maximum size of the virtual address space in bytes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>, example = "4G")]
+    pub address_space: Option<Limit>,
+    /// Maximum number of processes of the user the process runs as
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<u64>, example = 512)]
+    pub processes: Option<Limit>,
+}
+
+impl Limits {
+    fn entries(&self) -> [(&'static str, Resource, Option<Limit>); 4] {
+        [
+            ("open files", Resource::RLIMIT_NOFILE, self.open_files),
+            ("core size", Resource::RLIMIT_CORE, self.core_size),
+            ("address space", Resource::RLIMIT_AS, self.address_space),
+            ("processes", Resource::RLIMIT_NPROC, self.processes),
+        ]
+    }
+
+    /// Limits to pass to setrlimit, used as both the soft and the hard limit
+    pub fn resources(&self) -> Vec<(Resource, rlim_t)> {
+        self.entries()
+            .into_iter()
+            .filter_map(|(_, resource, limit)| limit.map(|limit| (resource, limit.raw())))
+            .collect()
+    }
+
+    /// Check that the daemon is allowed to set the limits, only root can raise a hard limit
+    pub fn check(&self) -> Result<(), String> {
+        if unistd::geteuid().is_root() {
+            return Ok(());
+        }
+
+        for (name, resource, limit) in self.entries() {
+            let Some(limit) = limit else { continue };
+            let (_, hard) = resource::getrlimit(resource)
+                .map_err(|err| format!("failed to read the {name} limit: {err}"))?;
+
+            if hard != RLIM_INFINITY && (limit == Limit::Unlimited || limit.raw() > hard) {
+                return Err(format!(
+                    "the {name} limit {limit} is above the hard limit {hard} of the daemon"
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Limits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let applied = self
+            .entries()
+            .into_iter()
+            .filter_map(|(name, resource, limit)| {
+                let limit = match (resource, limit?) {
+                    (Resource::RLIMIT_CORE | Resource::RLIMIT_AS, Limit::Value(bytes))
+                        if bytes > 0 =>
+                    {
+                        MaxMemory(bytes).to_string()
+                    }
+                    (_, limit) => limit.to_string(),
+                };
+                Some(format!("{name} {limit}"))
+            })
+            .collect::<Vec<String>>();
+
+        match applied.is_empty() {
+            true => write!(f, "none"),
+            false => write!(f, "{}", applied.join(", ")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_limit() {
+        assert_eq!("65536".parse::<Limit>(), Ok(Limit::Value(65536)));
+        assert_eq!("0".parse::<Limit>(), Ok(Limit::Value(0)));
+        assert_eq!("1G".parse::<Limit>(), Ok(Limit::Value(1 << 30)));
+        assert_eq!("Unlimited".parse::<Limit>(), Ok(Limit::Unlimited));
+        assert!("lots".parse::<Limit>().is_err());
+    }
+
+    #[test]
+    fn test_limits_display() {
+        let limits = Limits {
+            open_files: Some(Limit::Value(1024)),
+            address_space: Some(Limit::Value(4 << 30)),
+            core_size: Some(Limit::Value(0)),
+            ..Limits::default()
+        };
+
+        assert_eq!(Limits::default().to_string(), "none");
+        assert_eq!(
+            limits.to_string(),
+            "open files 1024, core size 0, address space 4G"
+        );
+        assert_eq!(limits.resources().len(), 3);
+    }
+}
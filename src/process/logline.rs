@@ -0,0 +1,286 @@
+use chrono::{DateTime, FixedOffset, SecondsFormat, Utc};
+use colored::Colorize;
+use core::fmt;
+use global_placeholders::global;
+use nix::{sys::signal::kill, unistd::Pid};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::{
+    collections::HashMap,
+    ffi::CString,
+    fs::{self, File, OpenOptions},
+    io,
+    os::unix::{ffi::OsStrExt, fs::FileTypeExt},
+    path::Path,
+    str::FromStr,
+};
+use utoipa::ToSchema;
+
+/// How the output of a process ends up in its log files
+///
+/// Formats other than `Raw` go through pipes the daemon reads. A process started while the daemon
+/// is down writes raw lines to the log files instead, and one whose daemon stopped blocks on
+/// writing once the pipe is full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// The process writes to the log files itself
+    #[default]
+    Raw,
+    /// The daemon prefixes every line with an ISO 8601 timestamp
+    Timestamp,
+    /// The daemon writes every line as a JSON object with `ts`, `stream`, `id`, `name` and `msg`
+    Json,
+}
+
+impl LogFormat {
+    /// Check if the output goes through the daemon instead of straight to the log files
+    pub fn piped(&self) -> bool {
+        *self != LogFormat::Raw
+    }
+
+    /// Write a line read from the `stdout` or `stderr` of a process in this format
+    pub fn encode(
+        &self,
+        at: DateTime<Utc>,
+        stream: &str,
+        id: usize,
+        name: &str,
+        msg: &str,
+    ) -> String {
+        let ts = at.to_rfc3339_opts(SecondsFormat::Millis, true);
+
+        match self {
+            LogFormat::Raw => msg.to_string(),
+            LogFormat::Timestamp => format!("{ts} {msg}"),
+            LogFormat::Json => {
+                json!({"ts": ts, "stream": stream, "id": id, "name": name, "msg": msg}).to_string()
+            }
+        }
+    }
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "raw" => Ok(LogFormat::Raw),
+            "timestamp" => Ok(LogFormat::Timestamp),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!(
+                "invalid log format '{s}', expected raw, timestamp or json"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogFormat::Raw => write!(f, "raw"),
+            LogFormat::Timestamp => write!(f, "timestamp"),
+            LogFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// A line read back from a log file in any of the formats
+#[derive(Clone, Debug, PartialEq)]
+pub struct Line {
+    pub ts: Option<DateTime<FixedOffset>>,
+    pub stream: Option<String>,
+    pub msg: String,
+}
+
+impl Line {
+    pub fn parse(line: &str) -> Line {
+        let raw = || Line {
+            ts: None,
+            stream: None,
+            msg: line.to_string(),
+        };
+
+        if line.starts_with('{')
+            && let Ok(Value::Object(object)) = serde_json::from_str::<Value>(line)
+            && let Some(Value::String(msg)) = object.get("msg")
+        {
+            let text = |key: &str| object.get(key).and_then(Value::as_str);
+
+            return Line {
+                ts: text("ts").and_then(|ts| DateTime::parse_from_rfc3339(ts).ok()),
+                stream: text("stream").map(String::from),
+                msg: msg.clone(),
+            };
+        }
+
+        if !line.starts_with(|start: char| start.is_ascii_digit()) {
+            return raw();
+        }
+
+        match line
+            .split_once(' ')
+            .map(|(ts, msg)| (DateTime::parse_from_rfc3339(ts), msg))
+        {
+            Some((Ok(ts), msg)) => Line {
+                ts: Some(ts),
+                stream: None,
+                msg: msg.to_string(),
+            },
+            _ => raw(),
+        }
+    }
+
+    /// Message with its timestamp dimmed in front, raw lines are left as they are
+    pub fn colorize(&self) -> String {
+        match self.ts {
+            Some(ts) => format!(
+                "{} {}",
+                ts.to_rfc3339_opts(SecondsFormat::Millis, true)
+                    .bright_black(),
+                self.msg
+            ),
+            None => self.msg.clone(),
+        }
+    }
+}
+
+/// Parse a line from a log file and dim its timestamp
+pub fn colorize(line: &str) -> String {
+    Line::parse(line).colorize()
+}
+
+/// Interleaves the lines of several logs by their timestamps
+#[derive(Default)]
+pub struct Merger {
+    last: HashMap<usize, DateTime<FixedOffset>>,
+    lines: Vec<(Option<DateTime<FixedOffset>>, usize, String)>,
+}
+
+impl Merger {
+    /// Add a line of the log `index`, a line without a timestamp takes the one of the line before it in the same log
+    pub fn push(&mut self, index: usize, line: String) {
+        let ts = Line::parse(&line).ts.or(self.last.get(&index).copied());
+
+        if let Some(ts) = ts {
+            self.last.insert(index, ts);
+        }
+
+        self.lines.push((ts, index, line));
+    }
+
+    /// Take the lines added so far as `(log index, line)` in time order, lines with the same time keep their order
+    pub fn drain(&mut self) -> Vec<(usize, String)> {
+        let mut lines = std::mem::take(&mut self.lines);
+        lines.sort_by_key(|(ts, _, _)| *ts);
+        lines
+            .into_iter()
+            .map(|(_, index, line)| (index, line))
+            .collect()
+    }
+}
+
+/// Check if the daemon runs, it reads the pipes of relayed processes
+pub fn relay_running() -> bool {
+    fs::read_to_string(global!("pmc.pid"))
+        .ok()
+        .and_then(|pid| pid.trim().parse::<i32>().ok())
+        .is_some_and(|pid| kill(Pid::from_raw(pid), None).is_ok())
+}
+
+/// Create the named pipe at `path` if needed and open it for the process to write to
+///
+/// It is opened for reading too, so a process whose daemon stopped blocks once the pipe is full instead of getting SIGPIPE
+pub fn open_pipe(path: &str) -> Result<File, String> {
+    let path = Path::new(path);
+
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+
+    if !fs::metadata(path).is_ok_and(|metadata| metadata.file_type().is_fifo()) {
+        let _ = fs::remove_file(path);
+        let fifo = CString::new(path.as_os_str().as_bytes())
+            .map_err(|err| format!("Invalid pipe path {}: {err}", path.display()))?;
+
+        if unsafe { libc::mkfifo(fifo.as_ptr(), 0o600) } < 0 {
+            return Err(format!(
+                "Failed to create pipe {}: {}",
+                path.display(),
+                io::Error::last_os_error()
+            ));
+        }
+    }
+
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(|err| format!("Failed to open pipe {}: {err:?}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_log_lines() {
+        let at = DateTime::parse_from_rfc3339("2026-10-17T01:04:17.267Z")
+            .unwrap()
+            .to_utc();
+
+        let line = LogFormat::Timestamp.encode(at, "stdout", 1, "api", "listening on 3000");
+        assert_eq!(line, "2026-10-17T01:04:17.267Z listening on 3000");
+        assert_eq!(Line::parse(&line).ts.map(|ts| ts.to_utc()), Some(at));
+        assert_eq!(Line::parse(&line).msg, "listening on 3000");
+
+        let line = LogFormat::Json.encode(at, "stderr", 1, "api", "{\"not\": \"parsed\"}");
+        let parsed = Line::parse(&line);
+        assert_eq!(parsed.ts.map(|ts| ts.to_utc()), Some(at));
+        assert_eq!(parsed.stream.as_deref(), Some("stderr"));
+        assert_eq!(parsed.msg, "{\"not\": \"parsed\"}");
+
+        for raw in ["2026 was a good year", "{\"level\": \"info\"}", ""] {
+            assert_eq!(
+                Line::parse(raw),
+                Line {
+                    ts: None,
+                    stream: None,
+                    msg: raw.to_string()
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_merge_logs() {
+        let mut merger = Merger::default();
+
+        merger.push(0, String::from("2026-10-17T01:00:01Z request"));
+        merger.push(0, String::from("  continued"));
+        merger.push(0, String::from("2026-10-17T01:00:03Z response"));
+        merger.push(1, String::from("2026-10-17T01:00:02Z warning"));
+        merger.push(1, String::from("raw line"));
+
+        assert_eq!(
+            merger.drain(),
+            [
+                (0, String::from("2026-10-17T01:00:01Z request")),
+                (0, String::from("  continued")),
+                (1, String::from("2026-10-17T01:00:02Z warning")),
+                (1, String::from("raw line")),
+                (0, String::from("2026-10-17T01:00:03Z response")),
+            ]
+        );
+
+        merger.push(1, String::from("after drain"));
+        merger.push(0, String::from("2026-10-17T01:00:02.500Z late"));
+        assert_eq!(
+            merger.drain(),
+            [
+                (1, String::from("after drain")),
+                (0, String::from("2026-10-17T01:00:02.500Z late"))
+            ]
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use core::fmt;
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{self, Visitor},
+};
+use std::str::FromStr;
+
+const UNITS: [(char, u64); 4] = [
+    ('T', 1 << 40),
+    ('G', 1 << 30),
+    ('M', 1 << 20),
+    ('K', 1 << 10),
+];
+
+/// Memory limit in bytes, written like `512M` or `1G`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MaxMemory(pub u64);
+
+impl FromStr for MaxMemory {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = s.trim().to_uppercase();
+        let value = value.strip_suffix('B').unwrap_or(&value);
+
+        let (number, multiplier) = match UNITS.iter().find(|(unit, _)| value.ends_with(*unit)) {
+            Some((_, multiplier)) => (&value[..value.len() - 1], *multiplier),
+            None => (value, 1),
+        };
+
+        match number.trim().parse::<u64>() {
+            Ok(number) if number > 0 => number
+                .checked_mul(multiplier)
+                .map(MaxMemory)
+                .ok_or_else(|| format!("memory limit '{s}' is too large")),
+            _ => Err(format!(
+                "invalid memory limit '{s}', expected a size like 512M or 1G"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for MaxMemory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match UNITS
+            .iter()
+            .find(|(_, multiplier)| self.0.is_multiple_of(*multiplier))
+        {
+            Some((unit, multiplier)) => write!(f, "{}{unit}", self.0 / multiplier),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+impl Serialize for MaxMemory {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for MaxMemory {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct MemoryVisitor;
+
+        impl<'de> Visitor<'de> for MemoryVisitor {
+            type Value = MaxMemory;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a size like 512M or a number of bytes")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<MaxMemory, E> {
+                MaxMemory::from_str(value).map_err(E::custom)
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<MaxMemory, E> {
+                MaxMemory::from_str(&value.to_string()).map_err(E::custom)
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<MaxMemory, E> {
+                MaxMemory::from_str(&value.to_string()).map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(MemoryVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_max_memory() {
+        assert_eq!(
+            "512M".parse::<MaxMemory>(),
+            Ok(MaxMemory(512 * 1024 * 1024))
+        );
+        assert_eq!("1gb".parse::<MaxMemory>(), Ok(MaxMemory(1 << 30)));
+        assert_eq!("200k".parse::<MaxMemory>(), Ok(MaxMemory(200 * 1024)));
+        assert_eq!("4096".parse::<MaxMemory>(), Ok(MaxMemory(4096)));
+        assert!("0M".parse::<MaxMemory>().is_err());
+        assert!("lots".parse::<MaxMemory>().is_err());
+    }
+
+    #[test]
+    fn test_max_memory_display() {
+        assert_eq!(MaxMemory(512 * 1024 * 1024).to_string(), "512M");
+        assert_eq!(MaxMemory(1536 * 1024).to_string(), "1536K");
+        assert_eq!(MaxMemory(1000).to_string(), "1000");
+    }
+}
@@ -1,27 +1,66 @@
+pub mod backoff;
+pub mod cgroup;
+pub mod cron;
+pub mod dependency;
+pub mod dotenv;
 pub mod dump;
+pub mod environment;
+pub mod exit;
+pub mod glob;
 pub mod hash;
 pub mod http;
 pub mod id;
+pub mod instances;
+pub mod kill;
+pub mod limits;
+pub mod logline;
+pub mod memory;
+pub mod ready;
+pub mod restart;
+pub mod rotate;
+pub mod search;
+pub mod secrets;
+pub mod sink;
+pub mod supervisor;
 pub mod unix;
+pub mod user;
 
 use crate::{config, config::structs::Server, file, helpers};
+use backoff::Backoff;
+use cgroup::{Cgroup, CpuMax};
+use cron::Cron;
+use dotenv::FileEnv;
+use environment::EnvMode;
+use exit::ExitStatus;
+use instances::Instances;
+use kill::KillSignal;
+use limits::Limits;
+use logline::LogFormat;
+use memory::MaxMemory;
+use ready::Ready;
+use restart::{RestartPolicy, RestartReason, RestartRecord};
+use rotate::LogRotate;
+use user::{Credentials, Umask};
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::HashSet,
     env,
     fs::File,
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use nix::{
-    sys::signal::{Signal, kill},
+    sys::{
+        signal::{Signal, kill},
+        wait::{WaitPidFlag, waitpid},
+    },
     unistd::Pid,
 };
 
-use chrono::serde::ts_milliseconds;
+use chrono::serde::{ts_milliseconds, ts_milliseconds_option};
 use chrono::{DateTime, Utc};
 use global_placeholders::global;
 use macros_rs::{crashln, string, ternary, then};
@@ -49,6 +88,7 @@ pub struct Info {
     pub uptime: String,
     pub command: String,
     pub children: Vec<i64>,
+    pub instance: Option<usize>,
 }
 
 #[derive(Serialize, Deserialize, ToSchema)]
@@ -85,6 +125,23 @@ pub struct Raw {
     pub running: bool,
     pub crashed: bool,
     pub crashes: u64,
+    #[serde(default)]
+    pub backoff: Backoff,
+    /// Timestamp in milliseconds of a restart delayed by the backoff
+    #[serde(default)]
+    pub restart_at: Option<i64>,
+    #[serde(default)]
+    pub last_exit_code: Option<i32>,
+    #[serde(default)]
+    pub last_signal: Option<String>,
+    /// Status of the last run as returned by waitpid
+    #[serde(default)]
+    pub last_wait_status: Option<i32>,
+    /// Timestamp in milliseconds of the last exit
+    #[serde(default)]
+    pub last_exit_at: Option<i64>,
+    #[serde(default)]
+    pub history: Vec<RestartRecord>,
 }
 
 #[derive(Clone)]
@@ -107,6 +164,7 @@ pub struct ProcessItem {
     watch_path: String,
     #[schema(value_type = String, example = "2000-01-01T01:00:00.000Z")]
     start_time: DateTime<Utc>,
+    instance: Option<usize>,
 }
 
 #[derive(Clone)]
@@ -134,18 +192,47 @@ pub struct Process {
     pub started: DateTime<Utc>,
     #[serde(default)]
     pub initial_logs: InitialLogs,
+    #[serde(default)]
+    pub instance: Option<usize>,
+    #[serde(default)]
+    pub options: Options,
+    /// Set when the process exited on its own and its restart policy kept it down
+    #[serde(default)]
+    pub exited: bool,
+    #[serde(default)]
+    pub last_exit_code: Option<i32>,
+    /// Name of the signal that killed the last run
+    #[serde(default)]
+    pub last_signal: Option<String>,
+    /// Status of the last run as returned by waitpid
+    #[serde(default)]
+    pub last_wait_status: Option<i32>,
+    #[serde(default, with = "ts_milliseconds_option")]
+    pub last_exit_at: Option<DateTime<Utc>>,
+    /// Latest automatic restarts done by the daemon, oldest first
+    #[serde(default)]
+    pub history: Vec<RestartRecord>,
+    /// Values loaded from the env files of the process at its last start
+    #[serde(default)]
+    pub file_env: FileEnv,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Crash {
     pub crashed: bool,
     pub value: u64,
+    #[serde(default)]
+    pub backoff: Backoff,
+    /// Earliest time the daemon restarts the crashed process, set while a backoff delay is pending
+    #[serde(default)]
+    pub restart_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, Default)]
 pub struct InitialLogs {
     pub out: Vec<String>,
     pub error: Vec<String>,
+    /// Offset in the current log file up to which lines were captured, reset when the file is rotated
     pub start_pos_out: u64,
     pub start_pos_error: u64,
 }
@@ -158,6 +245,191 @@ pub struct Watch {
     pub hash: String,
 }
 
+/// Per process settings applied on every spawn of the process item
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+#[serde(default)]
+pub struct Options {
+    #[schema(value_type = String, example = "port:3000")]
+    pub ready: Ready,
+    #[schema(value_type = String, example = "SIGINT")]
+    pub kill_signal: KillSignal,
+    /// Milliseconds to wait after `kill_signal` before the process tree gets SIGKILL
+    #[schema(example = 5000)]
+    pub kill_timeout: u64,
+    #[schema(value_type = String, example = "on-failure")]
+    pub restart: RestartPolicy,
+    /// Restart the process once its tree stays over this much memory
+    #[schema(value_type = Option<String>, example = "512M")]
+    pub max_memory: Option<MaxMemory>,
+    /// Restart a running process or start a finished job on this schedule
+    #[schema(value_type = Option<String>, example = "0 3 * * *")]
+    pub cron: Option<Cron>,
+    /// Program the script is passed to, like `python3`
+    #[schema(example = "python3")]
+    pub interpreter: Option<String>,
+    pub interpreter_args: Vec<String>,
+    /// Arguments passed after the script, quoted for the shell when not using `exec`
+    pub args: Vec<String>,
+    /// Working directory, relative paths are resolved against the path the process was created in
+    #[schema(value_type = Option<String>, example = "server")]
+    pub cwd: Option<PathBuf>,
+    /// Run the interpreter or script directly instead of through the configured shell
+    pub exec: bool,
+    /// User to run as, a name or a uid
+    #[schema(example = "www-data")]
+    pub user: Option<String>,
+    /// Group to run as, defaults to the primary group of `user`
+    #[schema(example = "www-data")]
+    pub group: Option<String>,
+    #[schema(value_type = Option<String>, example = "022")]
+    pub umask: Option<Umask>,
+    pub limits: Limits,
+    /// CPU limit of the process cgroup, needs `daemon.cgroup`
+    #[schema(value_type = Option<String>, example = "50%")]
+    pub cpu_max: Option<CpuMax>,
+    /// Memory limit of the process cgroup, the kernel kills the process when it is reached
+    #[schema(value_type = Option<String>, example = "1G")]
+    pub memory_max: Option<MaxMemory>,
+    /// Env files read on every start, relative to the working directory
+    #[schema(value_type = Vec<String>, example = json!([".env", ".env.production"]))]
+    pub env_file: Vec<PathBuf>,
+    #[schema(value_type = String, example = "allowlist")]
+    pub env_mode: EnvMode,
+    /// Variables copied from the caller in allowlist mode, `*` matches any characters
+    #[schema(example = json!(["PATH", "HOME", "LC_*"]))]
+    pub env_allow: Vec<String>,
+    /// Named sets of variables applied over the stored environment, `env "production" { ... }` in HCL
+    #[schema(value_type = Object, example = json!({"production": {"NODE_ENV": "production"}}))]
+    pub env_profiles: BTreeMap<String, Env>,
+    /// Profile from `env_profiles` used on every start
+    #[schema(example = "production")]
+    pub env_profile: Option<String>,
+    /// Names of the processes started and ready before this one on restore, start all and apply
+    #[schema(example = json!(["db-proxy"]))]
+    pub depends_on: Vec<String>,
+    pub log_rotate: LogRotate,
+    /// Pipe the output through the daemon to write it as timestamped or JSON lines
+    #[schema(value_type = String, example = "timestamp")]
+    pub log_format: LogFormat,
+    /// Sinks from `[sinks]` in the config the output is shipped to, besides the log files
+    #[schema(example = json!(["central"]))]
+    pub log_sinks: Vec<String>,
+    /// Command the daemon feeds the output to instead of the log files, which are used again while it is down
+    #[schema(example = "logger -t api")]
+    pub log_pipe: Option<String>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            ready: Ready::default(),
+            kill_signal: KillSignal::default(),
+            kill_timeout: kill::DEFAULT_KILL_TIMEOUT,
+            restart: RestartPolicy::default(),
+            max_memory: None,
+            cron: None,
+            interpreter: None,
+            interpreter_args: vec![],
+            args: vec![],
+            cwd: None,
+            exec: false,
+            user: None,
+            group: None,
+            umask: None,
+            limits: Limits::default(),
+            cpu_max: None,
+            memory_max: None,
+            env_file: vec![],
+            env_mode: EnvMode::default(),
+            env_allow: vec![],
+            env_profiles: BTreeMap::new(),
+            env_profile: None,
+            depends_on: vec![],
+            log_rotate: LogRotate::default(),
+            log_format: LogFormat::default(),
+            log_sinks: vec![],
+            log_pipe: None,
+        }
+    }
+}
+
+impl Options {
+    /// Check that the daemon is allowed to apply the user, limits and cgroup settings
+    pub fn check(&self) -> Result<(), String> {
+        user::resolve(&self.user, &self.group)?;
+        self.limits.check()?;
+        cgroup::check(&self.cpu_max, &self.memory_max)
+    }
+
+    /// Working directory of a process created in `path`
+    pub fn working_dir(&self, path: &Path) -> PathBuf {
+        match &self.cwd {
+            Some(cwd) => path.join(cwd),
+            None => path.to_path_buf(),
+        }
+    }
+
+    /// Variables of the selected env profile
+    pub fn profile_env(&self) -> Option<&Env> {
+        self.env_profile
+            .as_ref()
+            .and_then(|name| self.env_profiles.get(name))
+    }
+
+    /// Check that the log sinks of the process are defined in the config and valid
+    pub fn check_log_sinks(&self) -> Result<(), String> {
+        then!(self.log_sinks.is_empty(), return Ok(()));
+        let sinks = config::read().sinks;
+
+        for name in &self.log_sinks {
+            match sinks.get(name) {
+                Some(sink) => sink
+                    .check()
+                    .map_err(|err| format!("log sink '{name}': {err}"))?,
+                None => {
+                    return Err(format!(
+                        "log sink '{name}' is not defined in [sinks] of the config"
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check if the output goes through the daemon instead of straight to the log files
+    pub fn relayed(&self) -> bool {
+        self.log_format.piped() || !self.log_sinks.is_empty() || self.log_pipe.is_some()
+    }
+
+    /// Check that the selected env profile is defined
+    pub fn check_env_profile(&self) -> Result<(), String> {
+        match &self.env_profile {
+            Some(name) if !self.env_profiles.contains_key(name) => {
+                match self.env_profiles.is_empty() {
+                    true => Err(format!(
+                        "env profile '{name}' is not defined, the process has no env profiles"
+                    )),
+                    false => Err(format!(
+                        "env profile '{name}' is not defined, expected one of {}",
+                        self.env_profiles
+                            .keys()
+                            .cloned()
+                            .collect::<Vec<String>>()
+                            .join(", ")
+                    )),
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that the env files of a process created in `path` can be read
+    pub fn check_env_files(&self, path: &Path) -> Result<(), String> {
+        dotenv::load(&self.env_file, &self.working_dir(path), &Env::new()).map(|_| ())
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Runner {
     pub id: id::Id,
@@ -187,6 +459,8 @@ pub struct RemoteConfig {
     pub shell: String,
     pub args: Vec<String>,
     pub log_path: String,
+    #[serde(default)]
+    pub log_rotate: LogRotate,
 }
 
 pub enum Status {
@@ -217,6 +491,26 @@ pub struct ProcessMetadata {
     pub args: Vec<String>,
     /// Environment variables
     pub env: Vec<String>,
+    /// File the wait status gets written to once the command exits
+    pub exit_file: Option<String>,
+    /// File created once the command sends SIGUSR2 to `PMC_READY_PID`, only set for `Ready::Signal`
+    pub ready_file: Option<String>,
+    /// Working directory of the command
+    pub cwd: Option<PathBuf>,
+    /// Run `shell` with `args` directly, `command` is not used
+    pub exec: bool,
+    /// User and group the command runs as
+    pub credentials: Option<Credentials>,
+    pub umask: Option<Umask>,
+    pub limits: Limits,
+    /// Cgroup the command is moved into before exec
+    pub cgroup: Option<Cgroup>,
+    /// Values loaded from the env files, already part of `env`
+    pub file_env: FileEnv,
+    /// Start from an empty environment instead of the one of the daemon
+    pub clear_env: bool,
+    /// Named pipes the output is written to instead of the log files, read by the daemon
+    pub pipes: Option<LogInfo>,
 }
 
 macro_rules! lock {
@@ -228,16 +522,78 @@ macro_rules! lock {
     }};
 }
 
-fn kill_children(children: Vec<i64>) {
-    for pid in children {
-        match kill(Pid::from_raw(pid as i32), Signal::SIGTERM) {
-            Ok(_) => {}
-            Err(nix::errno::Errno::ESRCH) => {
-                // Process already terminated
-            }
-            Err(err) => {
-                log::error!("Failed to stop pid {}: {err:?}", pid);
-            }
+fn log_name(name: &str, instance: Option<usize>) -> String {
+    let name = name.replace(' ', "_");
+
+    match instance {
+        Some(index) if index > 0 => format!("{name}-{index}"),
+        _ => name,
+    }
+}
+
+fn log_offsets(log_name: &str) -> InitialLogs {
+    let log_out_path = global!("pmc.logs.out", log_name);
+    let log_err_path = global!("pmc.logs.error", log_name);
+
+    InitialLogs {
+        out: vec![],
+        error: vec![],
+        start_pos_out: std::fs::metadata(&log_out_path)
+            .map(|m| m.len())
+            .unwrap_or(0),
+        start_pos_error: std::fs::metadata(&log_err_path)
+            .map(|m| m.len())
+            .unwrap_or(0),
+    }
+}
+
+fn instance_env(instance: Option<usize>) -> Vec<String> {
+    let index = instance.unwrap_or(0);
+    vec![
+        format!("INSTANCE_ID={index}"),
+        format!("PMC_INSTANCE={index}"),
+    ]
+}
+
+/// Wait status file of the process run started at `started`
+fn exit_file(id: usize, started: DateTime<Utc>) -> String {
+    format!(
+        "{}exits/{id}-{}",
+        global!("pmc.base"),
+        started.timestamp_millis()
+    )
+}
+
+/// File the supervisor of the run started at `started` creates once the run sent its ready signal
+fn ready_file(id: usize, started: DateTime<Utc>) -> String {
+    format!("{}.ready", exit_file(id, started))
+}
+
+/// Named pipes a piped process writes its output to, they are kept across restarts
+fn pipes(id: usize) -> LogInfo {
+    LogInfo {
+        out: format!("{}pipes/{id}-out", global!("pmc.base")),
+        error: format!("{}pipes/{id}-error", global!("pmc.base")),
+    }
+}
+
+/// Remove what is left of the run started at `started` once it was stopped, its wait status, ready file and cgroup
+fn clear_run_files(id: usize, started: DateTime<Utc>) {
+    let _ = std::fs::remove_file(exit_file(id, started));
+    let _ = std::fs::remove_file(ready_file(id, started));
+    cgroup::remove_run(id, started);
+}
+
+/// Remove the wait status files of every run of the process
+fn clear_exit_files(id: usize) {
+    let Ok(entries) = std::fs::read_dir(format!("{}exits", global!("pmc.base"))) else {
+        return;
+    };
+
+    let prefix = format!("{id}-");
+    for entry in entries.flatten() {
+        if entry.file_name().to_string_lossy().starts_with(&prefix) {
+            let _ = std::fs::remove_file(entry.path());
         }
     }
 }
@@ -287,15 +643,21 @@ impl Runner {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn start(
         &mut self,
         name: &String,
         command: &String,
         path: PathBuf,
         watch: &Option<String>,
+        instances: &Instances,
+        options: &Options,
+        backoff: &Backoff,
     ) -> &mut Self {
         if let Some(remote) = &self.remote {
-            if let Err(err) = http::create(remote, name, command, path, watch) {
+            if let Err(err) = http::create(
+                remote, name, command, path, watch, instances, options, backoff,
+            ) {
                 crashln!(
                     "{} Failed to start create {name}\nError: {:#?}",
                     *helpers::FAIL,
@@ -303,12 +665,7 @@ impl Runner {
                 );
             };
         } else {
-            let id = self.id.next();
-            let config = config::read().runner;
-            let crash = Crash {
-                crashed: false,
-                value: 0,
-            };
+            let count = instances.count();
 
             let watch = match watch {
                 Some(watch) => Watch {
@@ -323,51 +680,71 @@ impl Runner {
                 },
             };
 
-            let log_name = name.replace(' ', "_");
-            let log_out_path = global!("pmc.logs.out", log_name.as_str());
-            let log_err_path = global!("pmc.logs.error", log_name.as_str());
-            let start_pos_out = std::fs::metadata(&log_out_path).map(|m| m.len()).unwrap_or(0);
-            let start_pos_error = std::fs::metadata(&log_err_path).map(|m| m.len()).unwrap_or(0);
-
-            let pid = process_run(ProcessMetadata {
-                args: config.args,
-                name: name.clone(),
-                shell: config.shell,
-                command: command.clone(),
-                log_path: config.log_path,
-                env: unix::env(),
-            })
-            .unwrap_or_else(|err| crashln!("Failed to run process: {err}"));
-
-            self.list.insert(
-                id,
-                Process {
-                    id,
-                    pid,
-                    path,
-                    watch,
-                    crash,
+            for index in 0..count {
+                self.spawn(Process {
+                    id: 0,
+                    pid: 0,
+                    path: path.clone(),
+                    watch: watch.clone(),
+                    crash: Crash {
+                        crashed: false,
+                        value: 0,
+                        backoff: *backoff,
+                        restart_at: None,
+                    },
                     restarts: 0,
                     running: true,
                     children: vec![],
                     name: name.clone(),
                     started: Utc::now(),
                     script: command.clone(),
-                    env: env::vars().collect(),
-                    initial_logs: InitialLogs {
-                        out: vec![],
-                        error: vec![],
-                        start_pos_out,
-                        start_pos_error,
-                    },
-                },
-            );
+                    env: environment::capture(options.env_mode, &options.env_allow, env::vars()),
+                    initial_logs: InitialLogs::default(),
+                    instance: ternary!(count > 1, Some(index), None),
+                    options: options.clone(),
+                    exited: false,
+                    last_exit_code: None,
+                    last_signal: None,
+                    last_wait_status: None,
+                    last_exit_at: None,
+                    history: vec![],
+                    file_env: FileEnv::new(),
+                })
+                .unwrap_or_else(|err| crashln!("Failed to run process: {err}"));
+            }
         }
 
         self
     }
 
-    pub fn restart(&mut self, id: usize, dead: bool) -> &mut Self {
+    /// Run a new process item from a template and insert it under a fresh id
+    fn spawn(&mut self, mut process: Process) -> Result<usize, String> {
+        let id = self.id.next();
+
+        let log_name = log_name(&process.name, process.instance);
+        let initial_logs = log_offsets(&log_name);
+
+        let mut temp_env = process
+            .env
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<String>>();
+        temp_env.extend(instance_env(process.instance));
+
+        let metadata = process.metadata(id, temp_env, process.started)?;
+
+        process.file_env = metadata.file_env.clone();
+        process.pid = process_run(metadata)?;
+
+        process.id = id;
+        process.initial_logs = initial_logs;
+        self.list.insert(id, process);
+
+        Ok(id)
+    }
+
+    /// Restart the process `id`, when it cannot be started again it is marked as crashed
+    pub fn restart(&mut self, id: usize, dead: bool) -> Result<&mut Self, String> {
         if let Some(remote) = &self.remote {
             if let Err(err) = http::restart(remote, id) {
                 crashln!(
@@ -376,70 +753,196 @@ impl Runner {
                     err
                 );
             };
-        } else {
-            let process = self.process(id);
-            let config = config::read().runner;
-            let Process {
-                path, script, name, ..
-            } = process.clone();
-
-            let log_name = name.replace(' ', "_");
-            let log_out_path = global!("pmc.logs.out", log_name.as_str());
-            let log_err_path = global!("pmc.logs.error", log_name.as_str());
-            let start_pos_out = std::fs::metadata(&log_out_path).map(|m| m.len()).unwrap_or(0);
-            let start_pos_error = std::fs::metadata(&log_err_path).map(|m| m.len()).unwrap_or(0);
-
-            kill_children(process.children.clone());
-            process_stop(process.pid)
+
+            return Ok(self);
+        }
+
+        let process = self.process(id);
+        let Process { name, instance, .. } = process.clone();
+
+        let log_name = log_name(&name, instance);
+        let initial_logs = log_offsets(&log_name);
+
+        let mut temp_env = process
+            .env
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<String>>();
+        then!(
+            process.options.env_mode.inherits(),
+            temp_env.extend(unix::env())
+        );
+        temp_env.extend(instance_env(instance));
+
+        let started = Utc::now();
+
+        // the old instance is kept running when the new one cannot be set up
+        let metadata = match process.metadata(id, temp_env, started) {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                process.running = process.running && process_alive(process.pid);
+                process.crash.crashed = true;
+                return Err(err);
+            }
+        };
+
+        // a run that already exited is only started again, its pid may belong to another process by now
+        if process.running && process_alive(process.pid) {
+            process_terminate(process.pid, &process.children, &process.options)
                 .unwrap_or_else(|err| crashln!("Failed to stop process: {err}"));
+        }
+        clear_run_files(id, process.started);
+
+        process.file_env = metadata.file_env.clone();
+        process.children = vec![];
 
-            if let Err(err) = std::env::set_current_dir(&path) {
+        process.pid = match process_run(metadata) {
+            Ok(pid) => pid,
+            Err(err) => {
                 process.running = false;
-                process.children = vec![];
                 process.crash.crashed = true;
-                println!(
-                    "{} Failed to set working directory {:?}\nError: {:#?}",
+                return Err(err);
+            }
+        };
+
+        process.running = true;
+        process.exited = false;
+        process.started = started;
+        process.crash.crashed = false;
+        process.crash.restart_at = None;
+        then!(
+            process.options.env_mode.inherits(),
+            process.env.extend(env::vars().collect::<Env>())
+        );
+        process.initial_logs = initial_logs;
+
+        then!(dead, process.restarts += 1);
+        then!(dead, process.crash.value += 1);
+        then!(!dead, process.crash.value = 0);
+
+        Ok(self)
+    }
+
+    pub fn reload(&mut self, id: usize) -> Result<&mut Self, String> {
+        if let Some(remote) = &self.remote {
+            if let Err(err) = http::reload(remote, id) {
+                crashln!(
+                    "{} Failed to reload process {id}\nError: {:#?}",
                     *helpers::FAIL,
-                    path,
                     err
                 );
-            } else {
-                let mut temp_env = process
-                    .env
-                    .iter()
-                    .map(|(key, value)| format!("{}={}", key, value))
-                    .collect::<Vec<String>>();
-                temp_env.extend(unix::env());
-
-                process.pid = process_run(ProcessMetadata {
-                    args: config.args,
-                    name: name.clone(),
-                    shell: config.shell,
-                    log_path: config.log_path,
-                    command: script.to_string(),
-                    env: temp_env,
-                })
-                .unwrap_or_else(|err| crashln!("Failed to run process: {err}"));
+            };
 
-                process.running = true;
-                process.children = vec![];
-                process.started = Utc::now();
-                process.crash.crashed = false;
-                process.env.extend(env::vars().collect::<Env>());
-                process.initial_logs = InitialLogs {
-                    out: vec![],
-                    error: vec![],
-                    start_pos_out,
-                    start_pos_error,
-                };
+            return Ok(self);
+        }
 
-                then!(dead, process.restarts += 1);
-                then!(dead, process.crash.value += 1);
-                then!(!dead, process.crash.value = 0);
-            }
+        let process = self.process(id).clone();
+
+        if !process.running {
+            return self.restart(id, false);
         }
 
-        self
+        let log_name = log_name(&process.name, process.instance);
+        let initial_logs = log_offsets(&log_name);
+
+        let mut temp_env = process
+            .env
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<String>>();
+        then!(
+            process.options.env_mode.inherits(),
+            temp_env.extend(unix::env())
+        );
+        temp_env.extend(instance_env(process.instance));
+
+        let started = Utc::now();
+        let metadata = process.metadata(id, temp_env, started)?;
+        let file_env = metadata.file_env.clone();
+        let pid = process_run(metadata)?;
+
+        let ready_file = ready_file(id, started);
+        if !process
+            .options
+            .ready
+            .wait(pid, Path::new(&ready_file), ready::READY_TIMEOUT)
+        {
+            let _ = process_terminate(pid, &[], &process.options);
+            clear_run_files(id, started);
+            return Err(format!(
+                "Process {id} did not become ready ({}), kept the old instance",
+                process.options.ready
+            ));
+        }
+
+        let _ = process_terminate(process.pid, &process.children, &process.options);
+        clear_run_files(id, process.started);
+
+        let item = self.process(id);
+        item.pid = pid;
+        item.running = true;
+        item.children = vec![];
+        item.started = started;
+        item.crash.crashed = false;
+        then!(
+            item.options.env_mode.inherits(),
+            item.env.extend(env::vars().collect::<Env>())
+        );
+        item.file_env = file_env;
+        item.initial_logs = initial_logs;
+
+        Ok(self)
+    }
+
+    /// Add or remove instances of the group of process `id`, the instances that are kept are not touched
+    pub fn scale(&mut self, id: usize, instances: &Instances) -> Result<&mut Self, String> {
+        if let Some(remote) = &self.remote {
+            if let Err(err) = http::scale(remote, id, instances) {
+                crashln!(
+                    "{} Failed to scale process {id}\nError: {:#?}",
+                    *helpers::FAIL,
+                    err
+                );
+            };
+
+            return Ok(self);
+        }
+
+        let template = self.process(id).clone();
+        let count = instances.count();
+
+        let group: Vec<(usize, usize)> = self
+            .list
+            .iter()
+            .filter(|(_, p)| p.name == template.name)
+            .map(|(id, p)| (p.instance.unwrap_or(0), *id))
+            .collect();
+
+        let (added, surplus) = instances::scale_plan(&group, count);
+
+        for id in surplus {
+            self.discard(id);
+        }
+
+        for index in added {
+            self.spawn(Process {
+                restarts: 0,
+                running: true,
+                children: vec![],
+                exited: false,
+                started: Utc::now(),
+                instance: Some(index),
+                crash: Crash {
+                    crashed: false,
+                    value: 0,
+                    restart_at: None,
+                    ..template.crash
+                },
+                ..template.clone()
+            })?;
+        }
+
+        Ok(self)
     }
 
     pub fn remove(&mut self, id: usize) {
@@ -452,12 +955,28 @@ impl Runner {
                 );
             };
         } else {
-            self.stop(id);
-            self.list.remove(&id);
+            self.discard(id);
             self.save();
         }
     }
 
+    /// Stop the process `id` and drop it with its wait status files, cgroups and named pipes
+    fn discard(&mut self, id: usize) {
+        // saved before it is stopped, so the daemon does not restart it once its pid is gone
+        let Some(process) = self.list.remove(&id) else {
+            return;
+        };
+        self.save();
+
+        let _ = process_terminate(process.pid, &process.children, &process.options);
+        clear_exit_files(id);
+        cgroup::remove(id);
+
+        let pipes = pipes(id);
+        let _ = std::fs::remove_file(pipes.out);
+        let _ = std::fs::remove_file(pipes.error);
+    }
+
     pub fn set_id(&mut self, id: id::Id) {
         self.id = id;
         self.id.next();
@@ -536,6 +1055,45 @@ impl Runner {
         self
     }
 
+    /// Record how the last run ended, an unknown status still records the time it was noticed
+    pub fn set_exit_status(&mut self, id: usize, status: Option<ExitStatus>) -> &mut Self {
+        let process = self.process(id);
+
+        process.last_exit_code = status.and_then(|status| status.code);
+        process.last_signal = status
+            .and_then(|status| status.signal)
+            .map(|signal| signal.to_string());
+        process.last_wait_status = status.map(|status| status.raw);
+        process.last_exit_at = Some(status.map_or_else(Utc::now, |status| status.at));
+        clear_exit_files(id);
+        self
+    }
+
+    /// Add an automatic restart to the history of the process item
+    pub fn record_restart(&mut self, id: usize, reason: RestartReason) -> &mut Self {
+        let history = &mut self.process(id).history;
+
+        history.push(RestartRecord {
+            reason,
+            at: Utc::now(),
+        });
+        if history.len() > restart::HISTORY_LIMIT {
+            history.remove(0);
+        }
+        self
+    }
+
+    /// Mark the process item as finished, its restart policy keeps it down
+    pub fn set_exited(&mut self, id: usize) -> &mut Self {
+        let process = self.process(id);
+
+        process.running = false;
+        process.exited = true;
+        process.children = vec![];
+        process.crash.restart_at = None;
+        self
+    }
+
     pub fn set_env(&mut self, id: usize, env: Env) -> &mut Self {
         self.process(id).env.extend(env);
         self
@@ -567,6 +1125,16 @@ impl Runner {
         self
     }
 
+    pub fn reset_crashes(&mut self, id: usize) -> &mut Self {
+        self.process(id).crash.value = 0;
+        self
+    }
+
+    pub fn set_restart_at(&mut self, id: usize, time: Option<DateTime<Utc>>) -> &mut Self {
+        self.process(id).crash.restart_at = time;
+        self
+    }
+
     pub fn stop(&mut self, id: usize) -> &mut Self {
         if let Some(remote) = &self.remote {
             if let Err(err) = http::stop(remote, id) {
@@ -577,24 +1145,17 @@ impl Runner {
                 );
             };
         } else {
-            let process_to_stop = self.process(id);
-            let pid_to_check = process_to_stop.pid;
+            let process = self.process(id);
 
-            kill_children(process_to_stop.children.clone());
-            let _ = process_stop(pid_to_check); // Continue even if stopping fails
+            // Continue even if stopping fails
+            let _ = process_terminate(process.pid, &process.children, &process.options);
+            clear_run_files(id, process.started);
 
-            // waiting until Process is terminated
-            for _ in 0..50 {
-                match unix::NativeProcess::new(pid_to_check as u32) {
-                    Ok(_p) => thread::sleep(Duration::from_millis(100)),
-                    Err(_) => break,
-                }
-            }
-
-            let process = self.process(id);
             process.running = false;
+            process.exited = false;
             process.crash.crashed = false;
             process.crash.value = 0;
+            process.crash.restart_at = None;
             process.children = vec![];
         }
 
@@ -617,6 +1178,27 @@ impl Runner {
         self
     }
 
+    pub fn set_env_profile(&mut self, id: usize, profile: String) -> &mut Self {
+        if self.remote.is_some() {
+            crashln!(
+                "{} Switching the env profile is not supported on remote servers",
+                *helpers::FAIL
+            );
+        }
+
+        let options = &mut self.process(id).options;
+        options.env_profile = Some(profile);
+
+        if let Err(err) = options.check_env_profile() {
+            crashln!(
+                "{} Failed to switch the env profile of {id}\nError: {err}",
+                *helpers::FAIL
+            );
+        }
+
+        self
+    }
+
     pub fn rename(&mut self, id: usize, name: String) -> &mut Self {
         if let Some(remote) = &self.remote {
             if let Err(err) = http::rename(remote, id, name) {
@@ -654,6 +1236,58 @@ impl Runner {
             .map(|(id, _)| *id)
     }
 
+    pub fn find_all(&self, name: &str, server_name: &String) -> Vec<usize> {
+        let runner = self.resolve_runner(server_name);
+
+        runner
+            .list
+            .iter()
+            .filter(|(_, p)| p.name == name)
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// Order `ids` so the processes they depend on come first, also returns the dependencies that were skipped
+    pub fn start_order(&self, ids: &[usize]) -> (Vec<usize>, Vec<String>) {
+        let nodes = self
+            .list
+            .iter()
+            .map(|(id, p)| (*id, p.name.clone(), p.options.depends_on.clone()))
+            .collect::<Vec<_>>();
+
+        let names = nodes
+            .iter()
+            .filter(|(id, _, _)| ids.contains(id))
+            .map(|(_, name, _)| name.as_str())
+            .collect::<Vec<&str>>();
+
+        let (order, skipped) = dependency::order_lenient(&nodes, &names);
+        (
+            order.into_iter().filter(|id| ids.contains(id)).collect(),
+            skipped,
+        )
+    }
+
+    /// Names of the processes that other processes depend on
+    pub fn dependencies(&self) -> HashSet<String> {
+        self.list
+            .values()
+            .flat_map(|p| p.options.depends_on.iter().cloned())
+            .collect()
+    }
+
+    /// Block until a process that others depend on is ready, false if it died or timed out
+    pub fn wait_ready(&self, id: usize) -> bool {
+        match self.info(id) {
+            Some(process) if process.running => process.options.ready.wait(
+                process.pid,
+                Path::new(&ready_file(id, process.started)),
+                ready::READY_TIMEOUT,
+            ),
+            _ => false,
+        }
+    }
+
     pub fn find_prefix(&self, prefix: &str, server_name: &String) -> Vec<(usize, String)> {
         let runner = self.resolve_runner(server_name);
         let prefix_lower = prefix.to_lowercase();
@@ -710,11 +1344,9 @@ impl Runner {
             let mut memory_usage: Option<MemoryInfo> = None;
             let mut cpu_percent: Option<f64> = None;
 
-            if let Ok(process) = unix::NativeProcess::new(item.pid as u32)
-                && let Ok(mem_info_native) = process.memory_info()
-            {
+            if let Some(memory) = process_memory(item.pid) {
                 cpu_percent = Some(get_process_cpu_usage_percentage(item.pid));
-                memory_usage = Some(MemoryInfo::from(mem_info_native));
+                memory_usage = Some(memory);
             }
 
             let cpu_percent = match cpu_percent {
@@ -727,14 +1359,7 @@ impl Runner {
                 None => string!("0b"),
             };
 
-            let status = if item.running {
-                string!("online")
-            } else {
-                match item.crash.crashed {
-                    true => string!("crashed"),
-                    false => string!("stopped"),
-                }
-            };
+            let status = item.status();
 
             processes.push(ProcessItem {
                 id,
@@ -747,6 +1372,7 @@ impl Runner {
                 start_time: item.started,
                 watch_path: item.watch.path.clone(),
                 uptime: helpers::format_duration(item.started),
+                instance: item.instance,
             });
         }
 
@@ -779,13 +1405,197 @@ impl LogInfo {
 impl Process {
     /// Get a log paths of the process item
     pub fn logs(&self) -> LogInfo {
-        let name = self.name.replace(" ", "_");
+        let name = log_name(&self.name, self.instance);
 
         LogInfo {
             out: global!("pmc.logs.out", name.as_str()),
             error: global!("pmc.logs.error", name.as_str()),
         }
     }
+
+    /// Get the named pipes the daemon reads the output from when the log format is piped
+    pub fn pipes(&self) -> LogInfo {
+        pipes(self.id)
+    }
+
+    /// Get the name of the process item including its instance index
+    pub fn display_name(&self) -> String {
+        match self.instance {
+            Some(index) => format!("{}:{index}", self.name),
+            None => self.name.clone(),
+        }
+    }
+
+    /// Working directory of the process item
+    pub fn cwd(&self) -> PathBuf {
+        self.options.working_dir(&self.path)
+    }
+
+    /// Stored environment with the selected env profile and the values loaded from env files at the last start
+    pub fn full_env(&self) -> Env {
+        let mut env = self.env.clone();
+        env.extend(self.options.profile_env().cloned().unwrap_or_default());
+        env.extend(
+            self.file_env
+                .iter()
+                .map(|(key, loaded)| (key.clone(), loaded.value.clone())),
+        );
+        env
+    }
+
+    /// Command passed to the shell, the interpreter and arguments are quoted around the script
+    pub fn shell_command(&self) -> String {
+        let Options {
+            interpreter,
+            interpreter_args,
+            args,
+            ..
+        } = &self.options;
+
+        interpreter
+            .iter()
+            .chain(interpreter_args)
+            .map(|value| shell_quote(value))
+            .chain([self.script.clone()])
+            .chain(args.iter().map(|value| shell_quote(value)))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    /// Program and arguments of a direct exec run, the script is split on whitespace
+    pub fn exec_argv(&self) -> Vec<String> {
+        let Options {
+            interpreter,
+            interpreter_args,
+            args,
+            ..
+        } = &self.options;
+
+        interpreter
+            .iter()
+            .chain(interpreter_args)
+            .cloned()
+            .chain(self.script.split_whitespace().map(String::from))
+            .chain(args.iter().cloned())
+            .collect()
+    }
+
+    /// Build the metadata used to spawn a run of the process item
+    fn metadata(
+        &self,
+        id: usize,
+        mut env: Vec<String>,
+        started: DateTime<Utc>,
+    ) -> Result<ProcessMetadata, String> {
+        let config = config::read().runner;
+        let credentials = user::resolve(&self.options.user, &self.options.group)?;
+        let mut stored = self.env.clone();
+
+        // the profile overrides the stored values, env files can still use and override it
+        if let Some(profile) = self.options.profile_env() {
+            env.extend(profile.iter().map(|(key, value)| format!("{key}={value}")));
+            stored.extend(profile.clone());
+        }
+
+        let file_env = dotenv::load(&self.options.env_file, &self.cwd(), &stored)?;
+
+        env.extend(
+            file_env
+                .iter()
+                .map(|(key, loaded)| format!("{key}={}", loaded.value)),
+        );
+
+        if let Some(Credentials {
+            name: Some(name),
+            home: Some(home),
+            ..
+        }) = &credentials
+        {
+            let name = name.to_string_lossy();
+            env.extend([
+                format!("USER={name}"),
+                format!("LOGNAME={name}"),
+                format!("HOME={}", home.display()),
+            ]);
+        }
+
+        let (shell, args, command) = match self.options.exec {
+            true => {
+                let mut argv = self.exec_argv().into_iter();
+                (
+                    argv.next().unwrap_or_default(),
+                    argv.collect(),
+                    String::new(),
+                )
+            }
+            false => (config.shell, config.args, self.shell_command()),
+        };
+
+        Ok(ProcessMetadata {
+            name: log_name(&self.name, self.instance),
+            shell,
+            command,
+            log_path: config.log_path,
+            args,
+            env,
+            exit_file: Some(exit_file(id, started)),
+            ready_file: (self.options.ready == Ready::Signal).then(|| ready_file(id, started)),
+            cwd: Some(self.cwd()),
+            exec: self.options.exec,
+            credentials,
+            umask: self.options.umask,
+            limits: self.options.limits.clone(),
+            cgroup: cgroup::root().map(|root| Cgroup {
+                path: cgroup::path(&root, id, started),
+                cpu_max: self.options.cpu_max,
+                memory_max: self.options.memory_max,
+            }),
+            file_env,
+            clear_env: !self.options.env_mode.inherits(),
+            pipes: self.options.relayed().then(|| pipes(id)),
+        })
+    }
+
+    /// Get the status of the process item, `exited(code)` or `exited(signal)` once it finished for good
+    pub fn status(&self) -> String {
+        match (
+            self.running,
+            self.crash.crashed,
+            self.last_exit_code,
+            &self.last_signal,
+        ) {
+            (true, ..) => string!("online"),
+            (_, true, ..) => string!("crashed"),
+            (_, _, Some(code), _) if self.exited => format!("exited({code})"),
+            (_, _, _, Some(signal)) if self.exited => format!("exited({signal})"),
+            _ if self.exited => string!("exited"),
+            _ => string!("stopped"),
+        }
+    }
+
+    /// Describe how the last run ended, like `SIGKILL 5m ago`
+    pub fn last_exit(&self) -> String {
+        let Some(at) = self.last_exit_at else {
+            return string!("none");
+        };
+
+        match (self.last_exit_code, &self.last_signal) {
+            (Some(code), _) => format!("code {code} {} ago", helpers::format_duration(at)),
+            (_, Some(signal)) => format!("{signal} {} ago", helpers::format_duration(at)),
+            _ => format!("unknown {} ago", helpers::format_duration(at)),
+        }
+    }
+
+    /// Read the exit status of the current run, available once the process exited
+    pub fn exit_status(&self) -> Option<ExitStatus> {
+        let path = exit_file(self.id, self.started);
+        let status = std::fs::read_to_string(&path).ok()?.trim().parse().ok()?;
+        let at = std::fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .map_or_else(|_| Utc::now(), DateTime::<Utc>::from);
+
+        ExitStatus::from_wait(status, at)
+    }
 }
 
 impl ProcessWrapper {
@@ -794,9 +1604,17 @@ impl ProcessWrapper {
         lock!(self.runner).stop(self.id).save();
     }
 
-    /// Restart the process item
-    pub fn restart(&mut self) {
-        lock!(self.runner).restart(self.id, false).save();
+    /// Restart the process item, a failed restart is saved as crashed
+    pub fn restart(&mut self) -> Result<(), String> {
+        let mut runner = lock!(self.runner);
+        let restarted = runner.restart(self.id, false).map(|_| ());
+        runner.save();
+        restarted
+    }
+
+    /// Select the env profile used when the process item starts
+    pub fn set_env_profile(&mut self, profile: String) {
+        lock!(self.runner).set_env_profile(self.id, profile).save();
     }
 
     /// Rename the process item
@@ -815,8 +1633,11 @@ impl ProcessWrapper {
     }
 
     /// Set the process item as crashed
-    pub fn crashed(&mut self) {
-        lock!(self.runner).restart(self.id, true).save();
+    pub fn crashed(&mut self) -> Result<(), String> {
+        let mut runner = lock!(self.runner);
+        let restarted = runner.restart(self.id, true).map(|_| ());
+        runner.save();
+        restarted
     }
 
     /// Get the borrowed runner reference (lives till program end)
@@ -844,21 +1665,12 @@ impl ProcessWrapper {
         let mut memory_usage: Option<MemoryInfo> = None;
         let mut cpu_percent: Option<f64> = None;
 
-        if let Ok(process) = unix::NativeProcess::new(item.pid as u32)
-            && let Ok(mem_info_native) = process.memory_info()
-        {
-            cpu_percent = Some(get_process_cpu_usage_percentage(item.pid as i64));
-            memory_usage = Some(MemoryInfo::from(mem_info_native));
+        if let Some(memory) = process_memory(item.pid) {
+            cpu_percent = Some(get_process_cpu_usage_percentage(item.pid));
+            memory_usage = Some(memory);
         }
 
-        let status = if item.running {
-            string!("online")
-        } else {
-            match item.crash.crashed {
-                true => string!("crashed"),
-                false => string!("stopped"),
-            }
-        };
+        let status = item.status();
 
         ItemSingle {
             info: Info {
@@ -868,6 +1680,7 @@ impl ProcessWrapper {
                 name: item.name.clone(),
                 path: item.path.clone(),
                 children: item.children.clone(),
+                instance: item.instance,
                 uptime: helpers::format_duration(item.started),
                 command: format!(
                     "{} {} '{}'",
@@ -895,13 +1708,24 @@ impl ProcessWrapper {
                 running: item.running,
                 crashed: item.crash.crashed,
                 crashes: item.crash.value,
+                backoff: item.crash.backoff,
+                restart_at: item.crash.restart_at.map(|time| time.timestamp_millis()),
+                last_exit_code: item.last_exit_code,
+                last_signal: item.last_signal.clone(),
+                last_wait_status: item.last_wait_status,
+                last_exit_at: item.last_exit_at.map(|time| time.timestamp_millis()),
+                history: item.history.clone(),
             },
         }
     }
 }
 
-/// Get the CPU usage percentage of the process
+/// Get the CPU usage percentage of the process, of its whole cgroup when it runs in one
 pub fn get_process_cpu_usage_percentage(pid: i64) -> f64 {
+    if let Some(cgroup) = cgroup::of_pid(pid) {
+        return cgroup::cpu_percent(&cgroup).unwrap_or(0.0);
+    }
+
     match unix::NativeProcess::new(pid as u32) {
         Ok(process) => match process.cpu_percent() {
             Ok(cpu_percent) => cpu_percent.min(100.0 * num_cpus::get() as f64),
@@ -932,8 +1756,120 @@ pub fn process_stop(pid: i64) -> Result<(), String> {
     }
 }
 
-/// Find the children of the process
+/// Check if a pid is still alive, reaping it first when it is our own child
+fn process_alive(pid: i64) -> bool {
+    let pid = Pid::from_raw(pid as i32);
+    let _ = waitpid(pid, Some(WaitPidFlag::WNOHANG));
+
+    kill(pid, None).is_ok()
+}
+
+/// Stop the process tree with the configured kill signal and escalate
+/// to SIGKILL for anything still alive after the kill timeout
+pub fn process_terminate(pid: i64, children: &[i64], options: &Options) -> Result<(), String> {
+    let Options {
+        kill_signal: KillSignal(signal),
+        kill_timeout,
+        ..
+    } = *options;
+
+    let mut tree = process_find_children(pid);
+    for child in children {
+        then!(!tree.contains(child), tree.push(*child));
+    }
+
+    // Stop child processes first
+    for child_pid in &tree {
+        let _ = kill(Pid::from_raw(*child_pid as i32), signal);
+    }
+
+    match kill(Pid::from_raw(pid as i32), signal) {
+        Ok(_) | Err(nix::errno::Errno::ESRCH) => tree.push(pid),
+        Err(err) => return Err(format!("Failed to stop process {}: {:?}", pid, err)),
+    }
+
+    let started = Instant::now();
+    let timeout = Duration::from_millis(kill_timeout);
+
+    loop {
+        tree.retain(|pid| process_alive(*pid));
+
+        if tree.is_empty() {
+            return Ok(());
+        }
+
+        if started.elapsed() >= timeout {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    for pid in &tree {
+        let _ = kill(Pid::from_raw(*pid as i32), Signal::SIGKILL);
+    }
+
+    if let Ok(mut logger) = crate::log::Logger::daemon() {
+        logger.write(&format!(
+            "[daemon] escalated to SIGKILL (pid={pid}, signal={}, timeout={kill_timeout}ms, remaining={tree:?})",
+            options.kill_signal
+        ));
+    }
+
+    Ok(())
+}
+
+/// Quote a value for the shell unless it only has safe characters
+fn shell_quote(value: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_./=:@%+,".contains(c);
+
+    match !value.is_empty() && value.chars().all(safe) {
+        true => value.to_string(),
+        false => format!("'{}'", value.replace('\'', "'\\''")),
+    }
+}
+
+/// Get the memory usage of the process, the rss is the memory charged to its cgroup when it runs in one
+pub fn process_memory(pid: i64) -> Option<MemoryInfo> {
+    let mut memory = MemoryInfo::from(
+        unix::NativeProcess::new(pid as u32)
+            .ok()?
+            .memory_info()
+            .ok()?,
+    );
+
+    if let Some(charged) = cgroup::of_pid(pid).and_then(|cgroup| cgroup::memory(&cgroup)) {
+        memory.rss = charged;
+    }
+
+    Some(memory)
+}
+
+/// Get the resident memory in bytes of the process and all of its children
+pub fn process_tree_memory(pid: i64) -> u64 {
+    if let Some(charged) = cgroup::of_pid(pid).and_then(|cgroup| cgroup::memory(&cgroup)) {
+        return charged;
+    }
+
+    let mut pids = process_find_children(pid);
+    pids.push(pid);
+
+    pids.iter()
+        .filter_map(|pid| unix::NativeProcess::new(*pid as u32).ok())
+        .filter_map(|process| process.memory_info().ok())
+        .map(|memory| memory.rss())
+        .sum()
+}
+
+/// Find the children of the process, every other process in its cgroup when it runs in one
 pub fn process_find_children(parent_pid: i64) -> Vec<i64> {
+    if let Some(cgroup) = cgroup::of_pid(parent_pid) {
+        return cgroup::pids(&cgroup)
+            .into_iter()
+            .filter(|pid| *pid != parent_pid)
+            .collect();
+    }
+
     let mut children = Vec::new();
     let mut to_check = vec![parent_pid];
     let mut checked = HashSet::new();
@@ -962,6 +1898,8 @@ pub fn process_find_children(parent_pid: i64) -> Vec<i64> {
 
     #[cfg(not(target_os = "linux"))]
     {
+        use std::collections::HashMap;
+
         match unix::native_processes() {
             Ok(processes) => {
                 // Build parent->children map in single pass
@@ -999,7 +1937,12 @@ pub fn process_find_children(parent_pid: i64) -> Vec<i64> {
 
 /// Run the process
 pub fn process_run(metadata: ProcessMetadata) -> Result<i64, String> {
+    use nix::{sys::resource::setrlimit, unistd};
     use std::fs::OpenOptions;
+    use std::io;
+    use std::os::fd::AsRawFd;
+    use std::os::unix::process::CommandExt;
+    use std::path::Path;
     use std::process::{Command, Stdio};
 
     let log_base = format!("{}/{}", metadata.log_path, metadata.name.replace(' ', "_"));
@@ -1019,28 +1962,99 @@ pub fn process_run(metadata: ProcessMetadata) -> Result<i64, String> {
         .open(&stderr_path)
         .map_err(|err| format!("Failed to open stderr log file {}: {:?}", stderr_path, err))?;
 
+    // the daemon reads piped output and writes the log files itself, without it the output goes straight to them
+    let (stdout_file, stderr_file) = match &metadata.pipes {
+        Some(pipes) if logline::relay_running() => (
+            logline::open_pipe(&pipes.out)?,
+            logline::open_pipe(&pipes.error)?,
+        ),
+        Some(pipes) => {
+            let _ = std::fs::remove_file(&pipes.out);
+            let _ = std::fs::remove_file(&pipes.error);
+            (stdout_file, stderr_file)
+        }
+        None => (stdout_file, stderr_file),
+    };
+
+    if let Some(parent) = metadata
+        .exit_file
+        .as_ref()
+        .and_then(|file| Path::new(file).parent())
+    {
+        let _ = std::fs::create_dir_all(parent);
+    }
+
     // Execute process
     let mut cmd = Command::new(&metadata.shell);
-    cmd.args(&metadata.args)
-        .arg(&metadata.command)
-        .envs(metadata.env.iter().map(|env_var| {
-            let parts: Vec<&str> = env_var.splitn(2, '=').collect();
-            if parts.len() == 2 {
-                (parts[0], parts[1])
-            } else {
-                (env_var.as_str(), "")
+    cmd.args(&metadata.args);
+    then!(!metadata.exec, cmd.arg(&metadata.command));
+
+    if let Some(cwd) = &metadata.cwd {
+        cmd.current_dir(cwd);
+    }
+
+    then!(metadata.clear_env, cmd.env_clear());
+    cmd.envs(metadata.env.iter().map(|env_var| {
+        let parts: Vec<&str> = env_var.splitn(2, '=').collect();
+        if parts.len() == 2 {
+            (parts[0], parts[1])
+        } else {
+            (env_var.as_str(), "")
+        }
+    }))
+    .stdout(Stdio::from(stdout_file))
+    .stderr(Stdio::from(stderr_file))
+    .stdin(Stdio::null());
+
+    let cgroup_procs = metadata.cgroup.as_ref().map(Cgroup::create).transpose()?;
+    let cgroup_fd = cgroup_procs.as_ref().map(|file| file.as_raw_fd());
+    let credentials = metadata.credentials.clone();
+    let groups = credentials
+        .iter()
+        .flat_map(|credentials| credentials.groups.iter().map(|gid| gid.as_raw()))
+        .collect::<Vec<libc::gid_t>>();
+    let umask = metadata.umask;
+    let limits = metadata.limits.resources();
+
+    // Runs in the child between fork and exec
+    unsafe {
+        cmd.pre_exec(move || {
+            // writing 0 to cgroup.procs moves the writing process
+            if let Some(fd) = cgroup_fd {
+                then!(
+                    libc::write(fd, b"0".as_ptr().cast(), 1) < 0,
+                    return Err(io::Error::last_os_error())
+                );
+            }
+
+            if let Some(Umask(mask)) = umask {
+                libc::umask(mask as libc::mode_t);
+            }
+
+            for (resource, limit) in &limits {
+                setrlimit(*resource, *limit, *limit)?;
+            }
+
+            if let Some(credentials) = &credentials {
+                let groups = libc::setgroups(groups.len() as _, groups.as_ptr());
+
+                if (unistd::geteuid().is_root() && groups < 0)
+                    || libc::setgid(credentials.gid.as_raw()) < 0
+                    || libc::setuid(credentials.uid.as_raw()) < 0
+                {
+                    return Err(io::Error::last_os_error());
+                }
             }
-        }))
-        .stdout(Stdio::from(stdout_file))
-        .stderr(Stdio::from(stderr_file))
-        .stdin(Stdio::null());
 
-    let child = cmd
-        .spawn()
-        .map_err(|err| format!("Failed to spawn process: {:?}", err))?;
+            Ok(())
+        });
+    }
 
-    let shell_pid = child.id() as i64;
-    let actual_pid = unix::get_actual_child_pid(shell_pid);
+    let shell_pid = supervisor::spawn(&mut cmd, metadata.exit_file, metadata.ready_file)?;
+    let actual_pid = match metadata.exec {
+        true => shell_pid,
+        false => unix::get_actual_child_pid(shell_pid),
+    };
 
     Ok(actual_pid)
 }
@@ -1077,6 +2091,8 @@ mod tests {
             crash: Crash {
                 crashed: false,
                 value: 0,
+                backoff: Backoff::default(),
+                restart_at: None,
             },
             watch: Watch {
                 enabled: false,
@@ -1086,6 +2102,15 @@ mod tests {
             children: vec![],
             started: Utc::now(),
             initial_logs: InitialLogs::default(),
+            instance: None,
+            options: Options::default(),
+            exited: false,
+            last_exit_code: None,
+            last_signal: None,
+            last_wait_status: None,
+            last_exit_at: None,
+            history: vec![],
+            file_env: FileEnv::new(),
         };
 
         runner.list.insert(id, process);
@@ -1126,6 +2151,8 @@ mod tests {
             crash: Crash {
                 crashed: false,
                 value: 0,
+                backoff: Backoff::default(),
+                restart_at: None,
             },
             watch: Watch {
                 enabled: false,
@@ -1135,6 +2162,15 @@ mod tests {
             children: vec![],
             started: Utc::now(),
             initial_logs: InitialLogs::default(),
+            instance: None,
+            options: Options::default(),
+            exited: false,
+            last_exit_code: None,
+            last_signal: None,
+            last_wait_status: None,
+            last_exit_at: None,
+            history: vec![],
+            file_env: FileEnv::new(),
         };
 
         runner.list.insert(id, process);
@@ -1164,6 +2200,179 @@ mod tests {
         assert_eq!(cpu_usage, 0.0);
     }
 
+    #[test]
+    fn test_signal_ready_dependency() {
+        let mut runner = setup_test_runner();
+        let id = runner.id.next();
+        let started = Utc::now();
+
+        let base = std::env::temp_dir().join(format!("pmc-ready-{}", std::process::id()));
+        std::fs::create_dir_all(base.join("exits")).unwrap();
+        global_placeholders::init!("pmc.base", format!("{}/", base.display()));
+
+        let metadata = ProcessMetadata {
+            name: "test_ready".to_string(),
+            shell: "/bin/sh".to_string(),
+            command: String::new(),
+            log_path: std::env::temp_dir().to_string_lossy().to_string(),
+            args: vec![
+                "-c".to_string(),
+                "sleep 0.5; kill -USR2 $PMC_READY_PID; sleep 5".to_string(),
+            ],
+            env: vec![],
+            exit_file: None,
+            ready_file: Some(ready_file(id, started)),
+            cwd: None,
+            exec: true,
+            credentials: None,
+            umask: None,
+            limits: Limits::default(),
+            cgroup: None,
+            file_env: FileEnv::new(),
+            clear_env: false,
+            pipes: None,
+        };
+
+        // started by a spawner that exits before the dependency reports ready, like the CLI
+        let pid_file = base.join("pid");
+        match unsafe { libc::fork() } {
+            0 => {
+                if let Ok(pid) = process_run(metadata) {
+                    let _ = std::fs::write(&pid_file, pid.to_string());
+                }
+                unsafe { libc::_exit(0) }
+            }
+            spawner => {
+                let _ = waitpid(Pid::from_raw(spawner), None);
+            }
+        }
+
+        let pid = std::fs::read_to_string(&pid_file)
+            .unwrap()
+            .parse::<i64>()
+            .unwrap();
+
+        let process = Process {
+            id,
+            pid,
+            env: BTreeMap::new(),
+            name: "test_ready".to_string(),
+            path: PathBuf::from("/tmp"),
+            script: String::new(),
+            restarts: 0,
+            running: true,
+            crash: Crash {
+                crashed: false,
+                value: 0,
+                backoff: Backoff::default(),
+                restart_at: None,
+            },
+            watch: Watch {
+                enabled: false,
+                path: String::new(),
+                hash: String::new(),
+            },
+            children: vec![],
+            started,
+            initial_logs: InitialLogs::default(),
+            instance: None,
+            options: Options {
+                ready: Ready::Signal,
+                ..Options::default()
+            },
+            exited: false,
+            last_exit_code: None,
+            last_signal: None,
+            last_wait_status: None,
+            last_exit_at: None,
+            history: vec![],
+            file_env: FileEnv::new(),
+        };
+
+        runner.list.insert(id, process);
+
+        assert!(runner.wait_ready(id));
+
+        let _ = process_stop(pid);
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    #[ignore = "it needs root and daemon.cgroup set to a cgroup v2 directory"]
+    fn test_reload_with_cgroups() {
+        let root = cgroup::root().expect("daemon.cgroup is not set");
+        let base = std::env::temp_dir().join(format!("pmc-cgroup-{}", std::process::id()));
+        std::fs::create_dir_all(&base).unwrap();
+
+        global_placeholders::init!("pmc.base", format!("{}/", base.display()));
+        global_placeholders::init!("pmc.logs.out", format!("{}/{{}}-out.log", base.display()));
+        global_placeholders::init!(
+            "pmc.logs.error",
+            format!("{}/{{}}-error.log", base.display())
+        );
+
+        let mut runner = setup_test_runner();
+        let id = runner.id.next();
+
+        let mut process = Process {
+            id,
+            pid: 0,
+            env: BTreeMap::new(),
+            name: "test_cgroup_reload".to_string(),
+            path: PathBuf::from("/tmp"),
+            script: "sleep 30".to_string(),
+            restarts: 0,
+            running: true,
+            crash: Crash {
+                crashed: false,
+                value: 0,
+                backoff: Backoff::default(),
+                restart_at: None,
+            },
+            watch: Watch {
+                enabled: false,
+                path: String::new(),
+                hash: String::new(),
+            },
+            children: vec![],
+            started: Utc::now(),
+            initial_logs: InitialLogs::default(),
+            instance: None,
+            options: Options {
+                ready: Ready::Delay(200),
+                exec: true,
+                ..Options::default()
+            },
+            exited: false,
+            last_exit_code: None,
+            last_signal: None,
+            last_wait_status: None,
+            last_exit_at: None,
+            history: vec![],
+            file_env: FileEnv::new(),
+        };
+
+        let metadata = process.metadata(id, vec![], process.started).unwrap();
+        process.pid = process_run(metadata).unwrap();
+        runner.list.insert(id, process);
+
+        let old = runner.pid(id);
+        assert!(cgroup::of_pid(old).is_some_and(|path| path.starts_with(&root)));
+
+        // the new instance runs in a cgroup of its own and survives stopping the old one
+        runner.reload(id).unwrap();
+        let new = runner.pid(id);
+
+        assert_ne!(old, new);
+        assert!(process_alive(new));
+        assert!(!process_alive(old));
+        assert_ne!(cgroup::of_pid(new), None);
+
+        let _ = process_terminate(new, &[], &runner.try_info(id).options);
+        cgroup::remove(id);
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
     // Integration test for actual process operations
     #[test]
     #[ignore = "it requires actual process execution"]
@@ -1175,6 +2384,17 @@ mod tests {
             log_path: "/tmp".to_string(),
             args: vec!["-c".to_string()],
             env: vec!["TEST_ENV=test_value".to_string()],
+            exit_file: None,
+            ready_file: None,
+            cwd: None,
+            exec: false,
+            credentials: None,
+            umask: None,
+            limits: Limits::default(),
+            cgroup: None,
+            file_env: FileEnv::new(),
+            clear_env: false,
+            pipes: None,
         };
 
         match process_run(metadata) {
@@ -1192,4 +2412,109 @@ mod tests {
             }
         }
     }
+
+    fn command_process(script: &str, options: Options) -> Process {
+        Process {
+            id: 0,
+            pid: 0,
+            env: BTreeMap::new(),
+            name: "test_command".to_string(),
+            path: PathBuf::from("/tmp"),
+            script: script.to_string(),
+            restarts: 0,
+            running: false,
+            crash: Crash {
+                crashed: false,
+                value: 0,
+                backoff: Backoff::default(),
+                restart_at: None,
+            },
+            watch: Watch {
+                enabled: false,
+                path: String::new(),
+                hash: String::new(),
+            },
+            children: vec![],
+            started: Utc::now(),
+            initial_logs: InitialLogs::default(),
+            instance: None,
+            options,
+            exited: false,
+            last_exit_code: None,
+            last_signal: None,
+            last_wait_status: None,
+            last_exit_at: None,
+            history: vec![],
+            file_env: FileEnv::new(),
+        }
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("--port=3000"), "--port=3000");
+        assert_eq!(shell_quote("/usr/bin/node"), "/usr/bin/node");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("hello world"), "'hello world'");
+        assert_eq!(shell_quote("$HOME"), "'$HOME'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(shell_quote("\"a\" `b`"), "'\"a\" `b`'");
+
+        // the shell gives back every value unchanged
+        let values = [
+            "hello world",
+            "$HOME",
+            "it's",
+            "\"a\" `b`",
+            "a;b|c&d",
+            "*",
+            "",
+        ];
+        let line = values
+            .iter()
+            .map(|value| shell_quote(value))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let output = std::process::Command::new("/bin/sh")
+            .args(["-c", &format!("printf '%s\\n' {line}")])
+            .output()
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            values.map(|value| format!("{value}\n")).concat()
+        );
+    }
+
+    #[test]
+    fn test_exec_argv() {
+        let options = Options {
+            interpreter: Some("/usr/bin/env node".to_string()),
+            interpreter_args: vec!["--title=my app".to_string()],
+            args: vec!["--name".to_string(), "it's $USER".to_string()],
+            ..Options::default()
+        };
+        let process = command_process("server.js  --port 3000", options);
+
+        // only the script is split, the interpreter and arguments are passed as they are
+        assert_eq!(
+            process.exec_argv(),
+            [
+                "/usr/bin/env node",
+                "--title=my app",
+                "server.js",
+                "--port",
+                "3000",
+                "--name",
+                "it's $USER"
+            ]
+        );
+        assert_eq!(
+            process.shell_command(),
+            "'/usr/bin/env node' '--title=my app' server.js  --port 3000 --name 'it'\\''s $USER'"
+        );
+        assert_eq!(
+            command_process("./run.sh", Options::default()).exec_argv(),
+            ["./run.sh"]
+        );
+    }
 }
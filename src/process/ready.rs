@@ -0,0 +1,323 @@
+use crate::process::{process_find_children, unix};
+use core::fmt;
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{self, Visitor},
+};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::{fs, path::Path, str::FromStr};
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use nix::{sys::signal::kill, unistd::Pid};
+
+/// How long a reloaded instance may take to report ready before the reload is aborted
+pub const READY_TIMEOUT: Duration = Duration::from_secs(30);
+
+const DEFAULT_DELAY_MS: u64 = 1000;
+const POLL_INTERVAL_MS: u64 = 100;
+
+/// Pids that sent SIGUSR2 and were not claimed by a waiter yet, filled from the signal handler
+/// with the monotonic second the signal arrived in the upper half
+static READY_SENDERS: [AtomicU64; 64] = [const { AtomicU64::new(0) }; 64];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ready {
+    /// Consider the process ready after a fixed delay in milliseconds
+    Delay(u64),
+    /// Wait until the process listens on a tcp port (any port when not set)
+    Port(Option<u16>),
+    /// Wait until the process sends SIGUSR2 to the pid in `PMC_READY_PID`, its supervisor
+    Signal,
+}
+
+impl Default for Ready {
+    fn default() -> Self {
+        Ready::Delay(DEFAULT_DELAY_MS)
+    }
+}
+
+impl FromStr for Ready {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, value) = match s.trim().split_once(':') {
+            Some((kind, value)) => (kind, Some(value.trim())),
+            None => (s.trim(), None),
+        };
+
+        match (kind.to_lowercase().as_str(), value) {
+            ("delay", None) => Ok(Ready::Delay(DEFAULT_DELAY_MS)),
+            ("delay", Some(ms)) => ms
+                .parse::<u64>()
+                .map(Ready::Delay)
+                .map_err(|_| format!("invalid ready delay '{ms}', expected milliseconds")),
+            ("port", None) => Ok(Ready::Port(None)),
+            ("port", Some(port)) => port
+                .parse::<u16>()
+                .map(|port| Ready::Port(Some(port)))
+                .map_err(|_| format!("invalid ready port '{port}'")),
+            ("signal", None) => Ok(Ready::Signal),
+            _ => Err(format!(
+                "invalid ready '{s}', expected delay[:ms], port[:port] or signal"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Ready {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ready::Delay(ms) => write!(f, "delay:{ms}"),
+            Ready::Port(Some(port)) => write!(f, "port:{port}"),
+            Ready::Port(None) => write!(f, "port"),
+            Ready::Signal => write!(f, "signal"),
+        }
+    }
+}
+
+impl Serialize for Ready {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Ready {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ReadyVisitor;
+
+        impl<'de> Visitor<'de> for ReadyVisitor {
+            type Value = Ready;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("delay[:ms], port[:port] or signal")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Ready, E> {
+                Ready::from_str(value).map_err(E::custom)
+            }
+
+            fn visit_unit<E: de::Error>(self) -> Result<Ready, E> {
+                Ok(Ready::default())
+            }
+
+            fn visit_none<E: de::Error>(self) -> Result<Ready, E> {
+                Ok(Ready::default())
+            }
+        }
+
+        deserializer.deserialize_any(ReadyVisitor)
+    }
+}
+
+extern "C" fn handle_ready_signal(
+    _: libc::c_int,
+    info: *mut libc::siginfo_t,
+    _: *mut libc::c_void,
+) {
+    let sender = unsafe { (*info).si_pid() } as u32 as u64;
+    let entry = monotonic_secs() << 32 | sender;
+
+    // only atomics are safe to use here, a full table drops the signal
+    for slot in &READY_SENDERS {
+        if slot
+            .compare_exchange(0, entry, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+        {
+            return;
+        }
+    }
+}
+
+/// Seconds on the monotonic clock, clock_gettime can be called from a signal handler
+fn monotonic_secs() -> u64 {
+    let mut now: libc::timespec = unsafe { std::mem::zeroed() };
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut now) };
+    now.tv_sec as u64
+}
+
+/// Install the SIGUSR2 handler used by `Ready::Signal`
+pub fn listen() {
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = handle_ready_signal as *const () as usize;
+        action.sa_flags = libc::SA_SIGINFO | libc::SA_RESTART;
+        libc::sigemptyset(&mut action.sa_mask);
+        libc::sigaction(libc::SIGUSR2, &action, std::ptr::null_mut());
+    }
+}
+
+/// Create `ready_file` once `pid` or one of its processes sent SIGUSR2, runs in the supervisor of `pid`
+pub fn report(pid: i64, ready_file: String) {
+    thread::spawn(move || {
+        while !claim(pid) {
+            thread::sleep(Duration::from_millis(POLL_INTERVAL_MS));
+        }
+
+        let _ = fs::write(ready_file, "");
+    });
+}
+
+/// Take a recorded ready signal sent by `pid` or one of its processes, other processes keep theirs
+fn claim(pid: i64) -> bool {
+    let mut claimed = false;
+
+    for slot in &READY_SENDERS {
+        let entry = slot.load(Ordering::SeqCst);
+
+        if entry == 0 {
+            continue;
+        }
+
+        let sender = (entry & u32::MAX as u64) as i64;
+        let received = entry >> 32;
+
+        // signals nobody waited for, or from senders that exited, can no longer be claimed
+        let stale =
+            monotonic_secs().saturating_sub(received) > READY_TIMEOUT.as_secs() || !alive(sender);
+        let owned = !claimed && belongs_to(pid, sender);
+
+        if (owned || stale)
+            && slot
+                .compare_exchange(entry, 0, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+        {
+            claimed |= owned;
+        }
+    }
+
+    claimed
+}
+
+fn alive(pid: i64) -> bool {
+    kill(Pid::from_raw(pid as i32), None).is_ok()
+}
+
+fn belongs_to(pid: i64, sender: i64) -> bool {
+    sender == pid
+        || process_find_children(pid).contains(&sender)
+        || unix::get_parent_pid(pid as i32).ok().flatten() == Some(sender as i32)
+}
+
+impl Ready {
+    /// Block until the process reports ready, returns false if it died or timed out
+    ///
+    /// A `Signal` is seen through `ready_file`, which the supervisor of the process creates
+    pub fn wait(&self, pid: i64, ready_file: &Path, timeout: Duration) -> bool {
+        if let Ready::Delay(ms) = self {
+            thread::sleep(Duration::from_millis(*ms));
+            return alive(pid);
+        }
+
+        let started = Instant::now();
+
+        while started.elapsed() < timeout {
+            if !alive(pid) {
+                return false;
+            }
+
+            let ready = match self {
+                Ready::Port(port) => {
+                    let ports = unix::get_listening_ports();
+                    let mut pids = process_find_children(pid);
+                    pids.push(pid);
+
+                    pids.iter()
+                        .filter_map(|pid| ports.get(pid))
+                        .flatten()
+                        .any(|listening| port.is_none_or(|port| port == *listening))
+                }
+                Ready::Signal => ready_file.exists(),
+                Ready::Delay(_) => true,
+            };
+
+            if ready {
+                return true;
+            }
+
+            thread::sleep(Duration::from_millis(POLL_INTERVAL_MS));
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ready() {
+        assert_eq!("delay".parse::<Ready>(), Ok(Ready::Delay(1000)));
+        assert_eq!("delay:250".parse::<Ready>(), Ok(Ready::Delay(250)));
+        assert_eq!("port".parse::<Ready>(), Ok(Ready::Port(None)));
+        assert_eq!("port:3000".parse::<Ready>(), Ok(Ready::Port(Some(3000))));
+        assert_eq!("signal".parse::<Ready>(), Ok(Ready::Signal));
+        assert!("port:http".parse::<Ready>().is_err());
+        assert!("signal:1".parse::<Ready>().is_err());
+    }
+
+    #[test]
+    fn test_deserialize_ready() {
+        let port: Ready = ron::from_str("\"port:8080\"").unwrap();
+        let missing: Ready = serde_json::from_str("null").unwrap();
+
+        assert_eq!(port, Ready::Port(Some(8080)));
+        assert_eq!(missing, Ready::default());
+    }
+
+    #[test]
+    fn test_signal_wakes_only_its_waiter() {
+        use std::process::Command;
+
+        listen();
+
+        let spawn = |script: &str| {
+            Command::new("sh")
+                .args(["-c", script])
+                .env("PMC_READY_PID", std::process::id().to_string())
+                .spawn()
+                .unwrap()
+        };
+
+        let mut signals = spawn("sleep 0.2; kill -USR2 $PMC_READY_PID; sleep 5");
+        let mut silent = spawn("sleep 5");
+        let (signals_pid, silent_pid) = (signals.id() as i64, silent.id() as i64);
+
+        let ready_file = |pid: i64| std::env::temp_dir().join(format!("pmc-ready-{pid}"));
+        for pid in [signals_pid, silent_pid] {
+            let _ = fs::remove_file(ready_file(pid));
+            report(pid, ready_file(pid).display().to_string());
+        }
+
+        let waiter = thread::spawn(move || {
+            Ready::Signal.wait(silent_pid, &ready_file(silent_pid), Duration::from_secs(2))
+        });
+        assert!(Ready::Signal.wait(
+            signals_pid,
+            &ready_file(signals_pid),
+            Duration::from_secs(2)
+        ));
+        assert!(!waiter.join().unwrap());
+
+        for child in [&mut signals, &mut silent] {
+            let _ = child.kill();
+            let _ = child.wait();
+            let _ = fs::remove_file(ready_file(child.id() as i64));
+        }
+    }
+
+    #[test]
+    fn test_ready_roundtrip() {
+        for ready in [
+            Ready::Delay(50),
+            Ready::Port(Some(80)),
+            Ready::Port(None),
+            Ready::Signal,
+        ] {
+            assert_eq!(ready.to_string().parse::<Ready>(), Ok(ready));
+        }
+    }
+}
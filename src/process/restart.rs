@@ -0,0 +1,135 @@
+use super::exit::ExitStatus;
+use chrono::{DateTime, Utc, serde::ts_milliseconds};
+use core::fmt;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+/// Number of automatic restarts kept in the history of a process
+pub const HISTORY_LIMIT: usize = 20;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    /// Restart whenever the process exits, restore it even if it was stopped
+    Always,
+    /// Restart only when the process exits with a non zero code
+    OnFailure,
+    /// Never restart, the process keeps its exit status
+    Never,
+    /// Like always, but a stopped process stays stopped on restore
+    #[default]
+    UnlessStopped,
+}
+
+impl RestartPolicy {
+    /// Check if a run that ended with `status` should be restarted, signals and unknown statuses count as failures
+    pub fn should_restart(&self, status: Option<&ExitStatus>) -> bool {
+        match self {
+            RestartPolicy::Always | RestartPolicy::UnlessStopped => true,
+            RestartPolicy::OnFailure => !status.is_some_and(ExitStatus::success),
+            RestartPolicy::Never => false,
+        }
+    }
+
+    /// Check if `pmc restore` starts the process, only `always` brings back a stopped one
+    pub fn restores(&self, running: bool) -> bool {
+        running || *self == RestartPolicy::Always
+    }
+}
+
+impl FromStr for RestartPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().replace('_', "-").as_str() {
+            "always" => Ok(RestartPolicy::Always),
+            "on-failure" => Ok(RestartPolicy::OnFailure),
+            "never" | "no" => Ok(RestartPolicy::Never),
+            "unless-stopped" => Ok(RestartPolicy::UnlessStopped),
+            _ => Err(format!(
+                "invalid restart policy '{s}', expected always, on-failure, never or unless-stopped"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for RestartPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RestartPolicy::Always => write!(f, "always"),
+            RestartPolicy::OnFailure => write!(f, "on-failure"),
+            RestartPolicy::Never => write!(f, "never"),
+            RestartPolicy::UnlessStopped => write!(f, "unless-stopped"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RestartReason {
+    /// The process exited and its restart policy brought it back
+    Crash,
+    /// The process tree stayed over its `max_memory` limit
+    Memory,
+    /// The `cron` schedule of the process fired
+    Cron,
+}
+
+impl fmt::Display for RestartReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RestartReason::Crash => write!(f, "crash"),
+            RestartReason::Memory => write!(f, "memory"),
+            RestartReason::Cron => write!(f, "cron"),
+        }
+    }
+}
+
+/// An automatic restart done by the daemon
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+pub struct RestartRecord {
+    pub reason: RestartReason,
+    /// Timestamp in milliseconds of the restart
+    #[serde(with = "ts_milliseconds")]
+    #[schema(value_type = i64)]
+    pub at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_restart_policy() {
+        assert_eq!("always".parse::<RestartPolicy>(), Ok(RestartPolicy::Always));
+        assert_eq!(
+            "on_failure".parse::<RestartPolicy>(),
+            Ok(RestartPolicy::OnFailure)
+        );
+        assert_eq!(
+            "Unless-Stopped".parse::<RestartPolicy>(),
+            Ok(RestartPolicy::UnlessStopped)
+        );
+        assert_eq!("no".parse::<RestartPolicy>(), Ok(RestartPolicy::Never));
+        assert!("sometimes".parse::<RestartPolicy>().is_err());
+    }
+
+    #[test]
+    fn test_should_restart() {
+        let at = Utc::now();
+        let exited = |code: i32| ExitStatus::from_wait(code << 8, at);
+        let killed = ExitStatus::from_wait(libc::SIGKILL, at);
+
+        assert!(RestartPolicy::Always.should_restart(exited(0).as_ref()));
+        assert!(RestartPolicy::OnFailure.should_restart(exited(1).as_ref()));
+        assert!(RestartPolicy::OnFailure.should_restart(killed.as_ref()));
+        assert!(RestartPolicy::OnFailure.should_restart(None));
+        assert!(!RestartPolicy::OnFailure.should_restart(exited(0).as_ref()));
+        assert!(!RestartPolicy::Never.should_restart(exited(1).as_ref()));
+
+        assert!(RestartPolicy::Always.restores(false));
+        assert!(!RestartPolicy::UnlessStopped.restores(false));
+        assert!(RestartPolicy::UnlessStopped.restores(true));
+    }
+}
@@ -0,0 +1,362 @@
+use super::memory::MaxMemory;
+use crate::config;
+use core::fmt;
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{self, Visitor},
+};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+use utoipa::ToSchema;
+
+/// Rotated files kept per log when `max_files` is not set
+pub const DEFAULT_MAX_FILES: usize = 5;
+
+const UNITS: [(char, u64); 5] = [
+    ('w', 604_800),
+    ('d', 86_400),
+    ('h', 3_600),
+    ('m', 60),
+    ('s', 1),
+];
+
+/// Span of time in seconds, written like `30m`, `12h` or `7d`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Age(pub u64);
+
+impl Age {
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs(self.0)
+    }
+}
+
+impl FromStr for Age {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = s.trim().to_lowercase();
+
+        let (number, multiplier) = match UNITS.iter().find(|(unit, _)| value.ends_with(*unit)) {
+            Some((_, multiplier)) => (&value[..value.len() - 1], *multiplier),
+            None => (value.as_str(), 1),
+        };
+
+        match number.trim().parse::<u64>() {
+            Ok(number) if number > 0 => number
+                .checked_mul(multiplier)
+                .map(Age)
+                .ok_or_else(|| format!("age '{s}' is too large")),
+            _ => Err(format!(
+                "invalid age '{s}', expected a span like 30m, 12h or 7d"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Age {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match UNITS
+            .iter()
+            .find(|(_, multiplier)| self.0.is_multiple_of(*multiplier))
+        {
+            Some((unit, multiplier)) => write!(f, "{}{unit}", self.0 / multiplier),
+            None => write!(f, "{}s", self.0),
+        }
+    }
+}
+
+impl Serialize for Age {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Age {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct AgeVisitor;
+
+        impl<'de> Visitor<'de> for AgeVisitor {
+            type Value = Age;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a span like \"12h\" or a number of seconds")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Age, E> {
+                Age::from_str(value).map_err(E::custom)
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<Age, E> {
+                Age::from_str(&value.to_string()).map_err(E::custom)
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<Age, E> {
+                Age::from_str(&value.to_string()).map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(AgeVisitor)
+    }
+}
+
+/// When the log files of a process are rotated, unset fields fall back to `runner.log_rotate` in the config
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(default)]
+pub struct LogRotate {
+    /// Rotate a log file once it grows past this size
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>, example = "10M")]
+    pub max_size: Option<MaxMemory>,
+    /// Rotate a log file once it has been written to for this long
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>, example = "1d")]
+    pub max_age: Option<Age>,
+    /// Rotated files kept per log, older ones are deleted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = 5)]
+    pub max_files: Option<usize>,
+    /// Compress rotated files with gzip
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compress: Option<bool>,
+}
+
+impl LogRotate {
+    /// Fill the unset settings from `fallback`
+    pub fn or(&self, fallback: &LogRotate) -> LogRotate {
+        LogRotate {
+            max_size: self.max_size.or(fallback.max_size),
+            max_age: self.max_age.or(fallback.max_age),
+            max_files: self.max_files.or(fallback.max_files),
+            compress: self.compress.or(fallback.compress),
+        }
+    }
+
+    /// Settings merged with the global ones from the config
+    pub fn resolve(&self) -> LogRotate {
+        self.or(&config::read().runner.log_rotate)
+    }
+
+    /// Logs are only rotated when a size or age limit is set
+    pub fn enabled(&self) -> bool {
+        self.max_size.is_some() || self.max_age.is_some()
+    }
+
+    /// At least one rotated file is kept, its age tells when the log was last rotated
+    fn max_files(&self) -> usize {
+        self.max_files.unwrap_or(DEFAULT_MAX_FILES).max(1)
+    }
+
+    /// Check if the log file at `path` went over its size or age limit
+    pub fn due(&self, path: &Path) -> bool {
+        let Ok(metadata) = fs::metadata(path) else {
+            return false;
+        };
+
+        if metadata.len() == 0 {
+            return false;
+        }
+
+        if let Some(MaxMemory(size)) = self.max_size
+            && metadata.len() >= size
+        {
+            return true;
+        }
+
+        // a log that was never rotated is as old as the file itself
+        let since = match rotated_files(path).first() {
+            Some(last) => fs::metadata(last).and_then(|metadata| metadata.modified()),
+            None => metadata.created(),
+        };
+
+        match (self.max_age, since) {
+            (Some(age), Ok(since)) => since
+                .elapsed()
+                .is_ok_and(|elapsed| elapsed >= age.duration()),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for LogRotate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.enabled() {
+            return write!(f, "disabled");
+        }
+
+        let limits = [
+            self.max_size.map(|size| format!("size {size}")),
+            self.max_age.map(|age| format!("age {age}")),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<String>>();
+
+        write!(f, "{}, keep {}", limits.join(", "), self.max_files())?;
+
+        match self.compress {
+            Some(true) => write!(f, ", gzip"),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Path of the rotated file with `index`, 1 being the newest
+pub fn rotated(path: &Path, index: usize, compress: bool) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+    match compress {
+        true => path.with_file_name(format!("{name}.{index}.gz")),
+        false => path.with_file_name(format!("{name}.{index}")),
+    }
+}
+
+/// Rotated files of the log at `path`, newest first
+pub fn rotated_files(path: &Path) -> Vec<PathBuf> {
+    (1..)
+        .map_while(|index| {
+            [false, true]
+                .into_iter()
+                .map(|compress| rotated(path, index, compress))
+                .find(|rotated| rotated.exists())
+        })
+        .collect()
+}
+
+/// Open a log file or a rotated one, gzip files are decompressed
+pub fn reader(path: &Path) -> io::Result<Box<dyn BufRead>> {
+    let file = File::open(path)?;
+
+    Ok(
+        match path.extension().is_some_and(|extension| extension == "gz") {
+            true => Box::new(BufReader::new(GzDecoder::new(file))),
+            false => Box::new(BufReader::new(file)),
+        },
+    )
+}
+
+/// Contents of the newest rotated file past `position`, the lines written to the log before it was truncated
+pub fn rotated_since(path: &Path, position: u64) -> io::Result<Vec<u8>> {
+    let Some(last) = rotated_files(path).into_iter().next() else {
+        return Ok(vec![]);
+    };
+
+    let mut reader = reader(&last)?;
+    io::copy(&mut reader.by_ref().take(position), &mut io::sink())?;
+
+    let mut rest = vec![];
+    reader.read_to_end(&mut rest)?;
+    Ok(rest)
+}
+
+/// Copy everything written to `source` so far, including lines written while copying
+fn copy_all(source: &mut File, target: &mut dyn Write) -> io::Result<u64> {
+    let mut copied = 0;
+    let mut buffer = vec![0; 64 * 1024];
+
+    source.seek(SeekFrom::Start(0))?;
+
+    loop {
+        match source.read(&mut buffer)? {
+            0 => return Ok(copied),
+            read => {
+                target.write_all(&buffer[..read])?;
+                copied += read as u64;
+            }
+        }
+    }
+}
+
+/// Move the log at `path` to `<path>.1`, shifting older files up and deleting the ones over `max_files`
+///
+/// The file is copied and truncated in place, the process keeps writing to the same descriptor
+pub fn rotate(path: &Path, settings: &LogRotate) -> io::Result<u64> {
+    let max_files = settings.max_files();
+    let compress = settings.compress == Some(true);
+
+    for (index, file) in rotated_files(path).iter().enumerate().rev() {
+        match index + 1 >= max_files {
+            true => fs::remove_file(file)?,
+            false => {
+                let gzip = file.extension().is_some_and(|extension| extension == "gz");
+                fs::rename(file, rotated(path, index + 2, gzip))?;
+            }
+        }
+    }
+
+    let mut source = OpenOptions::new().read(true).write(true).open(path)?;
+    let target = File::create(rotated(path, 1, compress))?;
+
+    let copied = match compress {
+        true => {
+            let mut encoder = GzEncoder::new(target, Compression::default());
+            let copied = copy_all(&mut source, &mut encoder)?;
+            source.set_len(0)?;
+            encoder.finish()?;
+            copied
+        }
+        false => {
+            let mut target = target;
+            let copied = copy_all(&mut source, &mut target)?;
+            source.set_len(0)?;
+            copied
+        }
+    };
+
+    Ok(copied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!("30m".parse::<Age>(), Ok(Age(1800)));
+        assert_eq!("1D".parse::<Age>(), Ok(Age(86_400)));
+        assert_eq!("90".parse::<Age>(), Ok(Age(90)));
+        assert_eq!(Age(7200).to_string(), "2h");
+        assert_eq!(Age(90).to_string(), "90s");
+        assert!("0h".parse::<Age>().is_err());
+        assert!("soon".parse::<Age>().is_err());
+    }
+
+    #[test]
+    fn test_rotate_log() {
+        let dir = std::env::temp_dir().join(format!("pmc-rotate-{}", std::process::id()));
+        let path = dir.join("app-out.log");
+        let settings = LogRotate {
+            max_size: Some(MaxMemory(4)),
+            max_files: Some(2),
+            ..LogRotate::default()
+        };
+
+        fs::create_dir_all(&dir).unwrap();
+
+        for contents in ["first\n", "second\n", "third\n"] {
+            fs::write(&path, contents).unwrap();
+            assert!(settings.due(&path));
+            assert_eq!(rotate(&path, &settings).unwrap(), contents.len() as u64);
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+        assert_eq!(
+            fs::read_to_string(rotated(&path, 1, false)).unwrap(),
+            "third\n"
+        );
+        assert_eq!(
+            fs::read_to_string(rotated(&path, 2, false)).unwrap(),
+            "second\n"
+        );
+        assert_eq!(rotated_files(&path).len(), 2);
+        assert_eq!(rotated_since(&path, 2).unwrap(), b"ird\n");
+        assert!(!settings.due(&path));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
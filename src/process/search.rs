@@ -0,0 +1,329 @@
+use super::{
+    logline::Line,
+    rotate::{self, Age},
+};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeDelta, TimeZone, Utc};
+use core::fmt;
+use macros_rs::then;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{collections::VecDeque, fs, io::BufRead, path::Path, str::FromStr, sync::LazyLock};
+use utoipa::ToSchema;
+
+/// Matching lines returned by a search when no limit is given
+pub const DEFAULT_LIMIT: usize = 100;
+
+static LEVEL_WORD: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(trace|debug|info|warn|warning|error|err|fatal|critical|panic)\b").unwrap()
+});
+
+/// Severity of a log line, guessed from its message
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl Level {
+    /// Level from the `level` field of a JSON message, otherwise the first level word in the message
+    pub fn of(msg: &str) -> Option<Level> {
+        if msg.starts_with('{')
+            && let Ok(Value::Object(object)) = serde_json::from_str::<Value>(msg)
+            && let Some(level) = ["level", "severity", "lvl"]
+                .iter()
+                .find_map(|key| object.get(*key))
+        {
+            return match level {
+                Value::String(level) => level.parse().ok(),
+                Value::Number(level) => level.as_u64().map(Level::from_number),
+                _ => None,
+            };
+        }
+
+        LEVEL_WORD
+            .find(msg)
+            .and_then(|word| word.as_str().parse().ok())
+    }
+
+    /// Numeric levels as written by pino and bunyan
+    fn from_number(level: u64) -> Level {
+        match level {
+            ..=10 => Level::Trace,
+            11..=20 => Level::Debug,
+            21..=30 => Level::Info,
+            31..=40 => Level::Warn,
+            41..=50 => Level::Error,
+            _ => Level::Fatal,
+        }
+    }
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "trace" => Ok(Level::Trace),
+            "debug" => Ok(Level::Debug),
+            "info" => Ok(Level::Info),
+            "warn" | "warning" => Ok(Level::Warn),
+            "error" | "err" => Ok(Level::Error),
+            "fatal" | "critical" | "panic" => Ok(Level::Fatal),
+            _ => Err(format!(
+                "invalid level '{s}', expected trace, debug, info, warn, error or fatal"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Level::Trace => write!(f, "trace"),
+            Level::Debug => write!(f, "debug"),
+            Level::Info => write!(f, "info"),
+            Level::Warn => write!(f, "warn"),
+            Level::Error => write!(f, "error"),
+            Level::Fatal => write!(f, "fatal"),
+        }
+    }
+}
+
+/// Point in time given as a span back from now like `1h`, an RFC 3339 time or a local `YYYY-MM-DD[ HH:MM[:SS]]`
+pub fn parse_time(value: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(age) = value.parse::<Age>() {
+        return Ok(Utc::now() - TimeDelta::from_std(age.duration()).unwrap_or(TimeDelta::MAX));
+    }
+
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.to_utc());
+    }
+
+    let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value.trim(), format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        });
+
+    match naive.and_then(|naive| Local.from_local_datetime(&naive).earliest()) {
+        Some(time) => Ok(time.to_utc()),
+        None => Err(format!(
+            "invalid time '{value}', expected a span like 1h or a time like 2026-10-17 12:00"
+        )),
+    }
+}
+
+/// Filters of a log search, as given on the command line and in the query string of the search api
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Filters {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grep: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub until: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<String>,
+}
+
+impl Filters {
+    pub fn is_empty(&self) -> bool {
+        self.grep.is_none() && self.since.is_none() && self.until.is_none() && self.level.is_none()
+    }
+
+    /// Parse the filters, relative times are resolved against the current time
+    pub fn compile(&self) -> Result<Query, String> {
+        Ok(Query {
+            grep: self
+                .grep
+                .as_deref()
+                .map(Regex::new)
+                .transpose()
+                .map_err(|err| format!("invalid regex: {err}"))?,
+            since: self.since.as_deref().map(parse_time).transpose()?,
+            until: self.until.as_deref().map(parse_time).transpose()?,
+            level: self.level.as_deref().map(str::parse).transpose()?,
+        })
+    }
+}
+
+/// Compiled search filters
+#[derive(Clone, Debug, Default)]
+pub struct Query {
+    grep: Option<Regex>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    level: Option<Level>,
+}
+
+impl Query {
+    /// Check a line, `ts` is its own time or the time of the last timestamped line before it
+    pub fn matches(&self, line: &Line, ts: Option<DateTime<Utc>>) -> bool {
+        if let Some(grep) = &self.grep
+            && !grep.is_match(&line.msg)
+        {
+            return false;
+        }
+
+        if let Some(level) = self.level
+            && Level::of(&line.msg).is_none_or(|found| found < level)
+        {
+            return false;
+        }
+
+        // lines from a log without timestamps are only filtered by the age of the file
+        ts.is_none_or(|ts| {
+            self.since.is_none_or(|since| ts >= since) && self.until.is_none_or(|until| ts <= until)
+        })
+    }
+}
+
+/// Lines of a log that matched a search
+#[derive(Debug, Default, Deserialize, Serialize, ToSchema)]
+pub struct SearchResult {
+    /// Path of the current log file, its rotated files are searched too
+    #[schema(example = "/home/user/.pmc/logs/api-out.log")]
+    pub path: String,
+    /// Matching lines, oldest first
+    #[schema(example = json!(["2026-10-17T01:04:17.267Z ERROR connection refused"]))]
+    pub lines: Vec<String>,
+    /// More lines matched than the limit, only the newest ones are returned
+    pub truncated: bool,
+}
+
+/// Search the log at `path` and its rotated files from oldest to newest, keeping the last `limit` matches
+pub fn search(path: &Path, query: &Query, limit: usize) -> SearchResult {
+    let mut lines = VecDeque::new();
+    let mut truncated = false;
+    let mut last = None;
+
+    for file in rotate::rotated_files(path)
+        .into_iter()
+        .rev()
+        .chain([path.to_path_buf()])
+    {
+        let modified = fs::metadata(&file).and_then(|metadata| metadata.modified());
+
+        // nothing was written to the file after `since`
+        if let (Some(since), Ok(modified)) = (query.since, modified)
+            && DateTime::<Utc>::from(modified) < since
+        {
+            continue;
+        }
+
+        let Ok(mut reader) = rotate::reader(&file) else {
+            continue;
+        };
+
+        let mut buffer = Vec::new();
+        while matches!(reader.read_until(b'\n', &mut buffer), Ok(read) if read > 0) {
+            let text = String::from_utf8_lossy(&buffer)
+                .trim_end_matches(['\n', '\r'])
+                .to_string();
+            let line = Line::parse(&text);
+            buffer.clear();
+
+            last = line.ts.map(|ts| ts.to_utc()).or(last);
+            then!(!query.matches(&line, last), continue);
+
+            if lines.len() == limit {
+                lines.pop_front();
+                truncated = true;
+            }
+
+            then!(limit > 0, lines.push_back(text));
+        }
+    }
+
+    SearchResult {
+        path: path.to_string_lossy().to_string(),
+        lines: lines.into(),
+        truncated,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_level() {
+        assert_eq!(
+            Level::of("2026-10-17 ERROR connection refused"),
+            Some(Level::Error)
+        );
+        assert_eq!(Level::of("[warn] slow query"), Some(Level::Warn));
+        assert_eq!(
+            Level::of(r#"{"level":50,"msg":"boom"}"#),
+            Some(Level::Error)
+        );
+        assert_eq!(
+            Level::of(r#"{"level":"info","msg":"error free"}"#),
+            Some(Level::Info)
+        );
+        assert_eq!(Level::of("interesting things happened"), None);
+        assert!(Level::Fatal > Level::Error && Level::Warn < Level::Error);
+    }
+
+    #[test]
+    fn test_search_rotated_logs() {
+        let dir = std::env::temp_dir().join(format!("pmc-search-{}", std::process::id()));
+        let path = dir.join("api-out.log");
+        let settings = rotate::LogRotate {
+            compress: Some(true),
+            ..rotate::LogRotate::default()
+        };
+
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            &path,
+            "2026-10-17T01:00:00Z ERROR first\n2026-10-17T01:00:01Z INFO ok\n",
+        )
+        .unwrap();
+        rotate::rotate(&path, &settings).unwrap();
+        fs::write(
+            &path,
+            "2026-10-17T02:00:00Z error second\n  at handler\n2026-10-17T02:00:01Z fatal third\n",
+        )
+        .unwrap();
+
+        let filters = |level: &str, since: Option<&str>| Filters {
+            level: Some(level.to_string()),
+            since: since.map(String::from),
+            ..Filters::default()
+        };
+
+        let result = search(&path, &filters("error", None).compile().unwrap(), 10);
+        assert_eq!(result.lines.len(), 3);
+        assert!(result.lines[0].ends_with("ERROR first"));
+        assert!(!result.truncated);
+
+        let result = search(&path, &filters("error", None).compile().unwrap(), 1);
+        assert!(result.lines[0].ends_with("fatal third"));
+        assert!(result.truncated);
+
+        let since = filters("error", Some("2026-10-17T01:30:00Z"))
+            .compile()
+            .unwrap();
+        assert_eq!(search(&path, &since, 10).lines.len(), 2);
+
+        let grep = Filters {
+            grep: Some(String::from(r"^\s+at ")),
+            ..Filters::default()
+        };
+        assert_eq!(
+            search(&path, &grep.compile().unwrap(), 10).lines,
+            ["  at handler"]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,107 @@
+use super::{Env, Runner, glob};
+use crate::config;
+
+/// Value shown in place of a masked secret
+pub const MASKED: &str = "********";
+
+/// Mask patterns from `secrets.mask` in the config
+pub fn patterns() -> Vec<String> {
+    config::read().secrets.mask
+}
+
+/// Check if the value of `key` should be masked
+pub fn is_secret(key: &str, patterns: &[String]) -> bool {
+    patterns.iter().any(|pattern| glob::matches(pattern, key))
+}
+
+/// Replace the values of secret variables with a placeholder
+pub fn mask(env: &mut Env, patterns: &[String]) {
+    env.iter_mut()
+        .filter(|(key, _)| is_secret(key, patterns))
+        .for_each(|(_, value)| *value = MASKED.to_string());
+}
+
+/// Mask the stored, env file and profile values of every process, used before the dump leaves the daemon
+pub fn mask_runner(runner: &mut Runner, patterns: &[String]) {
+    for process in runner.list.values_mut() {
+        mask(&mut process.env, patterns);
+        process
+            .options
+            .env_profiles
+            .values_mut()
+            .for_each(|profile| mask(profile, patterns));
+        process
+            .file_env
+            .iter_mut()
+            .filter(|(key, _)| is_secret(key, patterns))
+            .for_each(|(_, loaded)| loaded.value = MASKED.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::id::Id;
+
+    #[test]
+    fn test_secret_patterns() {
+        let patterns = vec![
+            String::from("*_TOKEN"),
+            String::from("*secret*"),
+            String::from("PASSWORD"),
+        ];
+
+        assert!(is_secret("GITHUB_TOKEN", &patterns));
+        assert!(is_secret("client_secret_id", &patterns));
+        assert!(is_secret("SECRET", &patterns));
+        assert!(is_secret("password", &patterns));
+        assert!(!is_secret("DB_PASSWORD", &patterns));
+        assert!(!is_secret("TOKENS", &patterns));
+        assert!(!is_secret("PATH", &patterns));
+    }
+
+    #[test]
+    fn test_mask_env() {
+        let mut env = Env::from([
+            (String::from("API_TOKEN"), String::from("abc")),
+            (String::from("PORT"), String::from("8080")),
+        ]);
+
+        mask(&mut env, &[String::from("*_TOKEN")]);
+        assert_eq!(env["API_TOKEN"], MASKED);
+        assert_eq!(env["PORT"], "8080");
+    }
+
+    #[test]
+    fn test_mask_runner_profiles() {
+        let process = serde_json::from_value(serde_json::json!({
+            "id": 0, "pid": 0, "name": "api", "path": "/tmp", "script": "", "restarts": 0, "running": false,
+            "crash": { "crashed": false, "value": 0 }, "watch": { "enabled": false, "path": "", "hash": "" },
+            "children": [], "started": 0,
+            "env": { "API_TOKEN": "abc" },
+            "options": { "env_profiles": {
+                "production": { "API_TOKEN": "prod", "PASSWORD": "hunter2", "PORT": "80" },
+                "staging": { "API_TOKEN": "stage" },
+            } },
+        }))
+        .unwrap();
+
+        let mut runner = Runner {
+            id: Id::new(1),
+            remote: None,
+            list: [(0, process)].into(),
+        };
+
+        mask_runner(
+            &mut runner,
+            &[String::from("*_TOKEN"), String::from("PASSWORD")],
+        );
+
+        let options = &runner.list[&0].options;
+        assert_eq!(runner.list[&0].env["API_TOKEN"], MASKED);
+        assert_eq!(options.env_profiles["production"]["API_TOKEN"], MASKED);
+        assert_eq!(options.env_profiles["production"]["PASSWORD"], MASKED);
+        assert_eq!(options.env_profiles["production"]["PORT"], "80");
+        assert_eq!(options.env_profiles["staging"]["API_TOKEN"], MASKED);
+    }
+}
@@ -0,0 +1,333 @@
+use super::search::Level;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::{collections::BTreeMap, ffi::CStr, path::PathBuf, str::FromStr, sync::LazyLock};
+
+/// Socket journald reads native protocol datagrams from
+pub const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+
+/// Local syslog socket used when a syslog sink has no address
+pub const SYSLOG_SOCKET: &str = "/dev/log";
+
+static HOSTNAME: LazyLock<String> = LazyLock::new(|| {
+    let mut buffer = [0u8; 256];
+
+    match unsafe { libc::gethostname(buffer.as_mut_ptr().cast(), buffer.len()) } {
+        0 => CStr::from_bytes_until_nul(&buffer)
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        _ => String::new(),
+    }
+});
+
+/// Where the output of processes is shipped besides their log files, `[sinks.<name>]` in the config
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Sink {
+    /// RFC 5424 messages to `udp://host:port`, `tcp://host:port` or a unix socket path
+    Syslog {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        address: Option<String>,
+        #[serde(default)]
+        facility: Facility,
+    },
+    /// Entries sent to the native journald socket
+    Journald {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        socket: Option<PathBuf>,
+    },
+    /// Batches of newline delimited JSON posted to `url`
+    Http {
+        url: String,
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        headers: BTreeMap<String, String>,
+        /// Lines sent in one request at most
+        #[serde(default = "default_batch_size")]
+        batch_size: usize,
+        /// Milliseconds a line waits for its batch to fill up
+        #[serde(default = "default_flush_interval")]
+        flush_interval: u64,
+        /// Attempts after a failed request before the batch is dropped
+        #[serde(default = "default_retries")]
+        retries: u32,
+    },
+}
+
+fn default_batch_size() -> usize {
+    100
+}
+
+fn default_flush_interval() -> u64 {
+    1000
+}
+
+fn default_retries() -> u32 {
+    3
+}
+
+impl Sink {
+    /// Check the settings that are not checked when the config is read
+    pub fn check(&self) -> Result<(), String> {
+        match self {
+            Sink::Syslog { address, .. } => SyslogAddress::resolve(address).map(|_| ()),
+            Sink::Journald { .. } => Ok(()),
+            Sink::Http {
+                url, batch_size, ..
+            } => match reqwest::Url::parse(url) {
+                Ok(_) if *batch_size == 0 => Err(String::from(
+                    "batch_size of an http sink has to be at least 1",
+                )),
+                Ok(url) if matches!(url.scheme(), "http" | "https") => Ok(()),
+                _ => Err(format!("invalid http sink url '{url}'")),
+            },
+        }
+    }
+}
+
+/// Syslog facility, `user` unless set
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Facility {
+    Kern,
+    #[default]
+    User,
+    Mail,
+    Daemon,
+    Auth,
+    Syslog,
+    Lpr,
+    News,
+    Uucp,
+    Cron,
+    Authpriv,
+    Ftp,
+    Local0 = 16,
+    Local1,
+    Local2,
+    Local3,
+    Local4,
+    Local5,
+    Local6,
+    Local7,
+}
+
+impl Facility {
+    fn code(&self) -> u8 {
+        *self as u8
+    }
+}
+
+/// Transport of a syslog sink
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SyslogAddress {
+    Udp(String),
+    Tcp(String),
+    Unix(PathBuf),
+}
+
+impl SyslogAddress {
+    /// Parse the address of a sink, the local syslog socket when it is not set
+    pub fn resolve(address: &Option<String>) -> Result<SyslogAddress, String> {
+        address.as_deref().unwrap_or(SYSLOG_SOCKET).parse()
+    }
+}
+
+impl FromStr for SyslogAddress {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid syslog address '{s}', expected udp://host:port, tcp://host:port or a socket path"
+            )
+        };
+
+        match s.trim().split_once("://") {
+            Some(("udp", host)) if host.contains(':') => Ok(SyslogAddress::Udp(host.to_string())),
+            Some(("tcp", host)) if host.contains(':') => Ok(SyslogAddress::Tcp(host.to_string())),
+            Some(("unix", path)) if path.starts_with('/') => {
+                Ok(SyslogAddress::Unix(PathBuf::from(path)))
+            }
+            None if s.trim().starts_with('/') => Ok(SyslogAddress::Unix(PathBuf::from(s.trim()))),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// A line of output handed to the sinks of a process
+#[derive(Clone, Debug)]
+pub struct Record {
+    pub at: DateTime<Utc>,
+    pub stream: &'static str,
+    pub id: usize,
+    pub pid: i64,
+    pub name: String,
+    pub msg: String,
+}
+
+impl Record {
+    /// Syslog severity from the level in the message, lines without one are errors on stderr and info on stdout
+    pub fn severity(&self) -> u8 {
+        match Level::of(&self.msg) {
+            Some(Level::Trace | Level::Debug) => 7,
+            Some(Level::Info) => 6,
+            Some(Level::Warn) => 4,
+            Some(Level::Error) => 3,
+            Some(Level::Fatal) => 2,
+            None if self.stream == "stderr" => 3,
+            None => 6,
+        }
+    }
+
+    /// RFC 5424 message, the process name is the app name and the stream the message id
+    pub fn syslog(&self, facility: Facility) -> String {
+        // header fields are printable ascii without spaces
+        let field = |value: &str, max: usize| match value
+            .chars()
+            .filter(|c| c.is_ascii_graphic())
+            .take(max)
+            .collect::<String>()
+        {
+            value if value.is_empty() => String::from("-"),
+            value => value,
+        };
+
+        format!(
+            "<{}>1 {} {} {} {} {} - {}",
+            facility.code() * 8 + self.severity(),
+            self.at.to_rfc3339_opts(SecondsFormat::Micros, true),
+            field(&HOSTNAME, 255),
+            field(&self.name, 48),
+            self.pid,
+            self.stream,
+            self.msg
+        )
+    }
+
+    /// Datagram in the journald native protocol
+    pub fn journald(&self) -> Vec<u8> {
+        let fields = [
+            ("MESSAGE", self.msg.clone()),
+            ("PRIORITY", self.severity().to_string()),
+            ("SYSLOG_IDENTIFIER", self.name.clone()),
+            ("SYSLOG_PID", self.pid.to_string()),
+            (
+                "SYSLOG_TIMESTAMP",
+                self.at.to_rfc3339_opts(SecondsFormat::Micros, true),
+            ),
+            ("PMC_ID", self.id.to_string()),
+            ("PMC_STREAM", self.stream.to_string()),
+        ];
+
+        let mut datagram = Vec::new();
+
+        for (key, value) in fields {
+            datagram.extend_from_slice(key.as_bytes());
+
+            // values with a newline are written with their length in front
+            match value.contains('\n') {
+                true => {
+                    datagram.push(b'\n');
+                    datagram.extend_from_slice(&(value.len() as u64).to_le_bytes());
+                }
+                false => datagram.push(b'='),
+            }
+
+            datagram.extend_from_slice(value.as_bytes());
+            datagram.push(b'\n');
+        }
+
+        datagram
+    }
+
+    /// Object written as one line of an http sink batch
+    pub fn json(&self) -> Value {
+        json!({
+            "ts": self.at.to_rfc3339_opts(SecondsFormat::Millis, true),
+            "host": *HOSTNAME,
+            "stream": self.stream,
+            "id": self.id,
+            "pid": self.pid,
+            "name": self.name,
+            "level": Level::of(&self.msg).map(|level| level.to_string()),
+            "msg": self.msg,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(stream: &'static str, msg: &str) -> Record {
+        Record {
+            at: DateTime::parse_from_rfc3339("2026-10-17T01:04:17.267Z")
+                .unwrap()
+                .to_utc(),
+            stream,
+            id: 3,
+            pid: 4242,
+            name: String::from("my api"),
+            msg: msg.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_encode_records() {
+        let line = record("stdout", "WARN slow query").syslog(Facility::Local0);
+        assert!(line.starts_with("<132>1 2026-10-17T01:04:17.267000Z "));
+        assert!(line.ends_with(" myapi 4242 stdout - WARN slow query"));
+        assert!(
+            record("stderr", "boom")
+                .syslog(Facility::User)
+                .starts_with("<11>1 ")
+        );
+
+        let datagram = record("stdout", "first\nsecond").journald();
+        let mut expected = b"MESSAGE\n".to_vec();
+        expected.extend_from_slice(&12u64.to_le_bytes());
+        expected.extend_from_slice(b"first\nsecond\nPRIORITY=6\nSYSLOG_IDENTIFIER=my api\n");
+        assert!(datagram.starts_with(&expected));
+
+        let json = record("stderr", "error: refused").json();
+        assert_eq!(json["level"], "error");
+        assert_eq!(json["ts"], "2026-10-17T01:04:17.267Z");
+    }
+
+    #[test]
+    fn test_parse_sinks() {
+        assert_eq!(
+            "udp://10.0.0.5:514".parse(),
+            Ok(SyslogAddress::Udp(String::from("10.0.0.5:514")))
+        );
+        assert_eq!(
+            "unix:///dev/log".parse(),
+            Ok(SyslogAddress::Unix(PathBuf::from("/dev/log")))
+        );
+        assert_eq!(
+            SyslogAddress::resolve(&None),
+            Ok(SyslogAddress::Unix(PathBuf::from(SYSLOG_SOCKET)))
+        );
+        assert!("tcp://logs".parse::<SyslogAddress>().is_err());
+
+        let sinks: BTreeMap<String, Sink> = toml::from_str(
+            r#"
+            central = { type = "syslog", address = "tcp://logs:6514", facility = "local3" }
+            journal = { type = "journald" }
+            ingest = { type = "http", url = "https://logs.example.com/v1", batch_size = 0 }
+            "#,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            sinks["central"],
+            Sink::Syslog {
+                facility: Facility::Local3,
+                ..
+            }
+        ));
+        assert!(sinks["central"].check().is_ok() && sinks["journal"].check().is_ok());
+        assert!(sinks["ingest"].check().is_err());
+    }
+}
@@ -0,0 +1,139 @@
+use super::{exit, ready};
+use macros_rs::string;
+use nix::{
+    sys::{
+        signal::{self, SaFlags, SigAction, SigHandler, SigSet, SigmaskHow, Signal},
+        wait::waitpid,
+    },
+    unistd::{self, ForkResult},
+};
+use std::{
+    fs,
+    io::{PipeWriter, Read, Write},
+    process::Command,
+    thread,
+};
+
+/// Spawn `cmd` from a forked supervisor that stays its parent until it exits, returns the pid of `cmd`
+///
+/// The supervisor writes the raw wait status to `exit_file`, so the status of a run started by the
+/// CLI is recorded even though the CLI exits right after spawning it. With a `ready_file` it is also
+/// the `PMC_READY_PID` the command sends SIGUSR2 to, it lives exactly as long as the command
+pub fn spawn(
+    cmd: &mut Command,
+    exit_file: Option<String>,
+    ready_file: Option<String>,
+) -> Result<i64, String> {
+    let (mut reader, writer) =
+        std::io::pipe().map_err(|err| format!("Failed to create supervisor pipe: {err}"))?;
+
+    match unsafe { unistd::fork() } {
+        Ok(ForkResult::Child) => {
+            drop(reader);
+            supervise(cmd, writer, exit_file, ready_file)
+        }
+        Ok(ForkResult::Parent { child }) => {
+            drop(writer);
+
+            // a long running spawner reaps the supervisor, when the spawner exits first init does
+            thread::spawn(move || waitpid(child, None));
+
+            let mut reply = String::new();
+            let _ = reader.read_to_string(&mut reply);
+
+            match reply.strip_prefix("pid ") {
+                Some(pid) => pid
+                    .parse()
+                    .map_err(|_| format!("Invalid supervisor reply '{reply}'")),
+                None if reply.is_empty() => {
+                    Err(string!("Supervisor exited before the process was spawned"))
+                }
+                None => Err(reply),
+            }
+        }
+        Err(err) => Err(format!("Failed to fork supervisor: {err}")),
+    }
+}
+
+/// Runs in the forked supervisor, it only exits once `cmd` exited
+fn supervise(
+    cmd: &mut Command,
+    mut writer: PipeWriter,
+    exit_file: Option<String>,
+    ready_file: Option<String>,
+) -> ! {
+    // keep the run out of the terminal session of the CLI, like the runs started by the daemon
+    let _ = unistd::setsid();
+    reset_signals();
+
+    if ready_file.is_some() {
+        ready::listen();
+        cmd.env("PMC_READY_PID", std::process::id().to_string());
+    }
+
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(err) => {
+            let _ = write!(writer, "Failed to spawn process: {err:?}");
+            unsafe { libc::_exit(1) }
+        }
+    };
+
+    let _ = write!(writer, "pid {}", child.id());
+    drop(writer);
+    detach();
+
+    if let Some(ready_file) = ready_file {
+        ready::report(child.id() as i64, ready_file);
+    }
+
+    // on linux the status is written before the pid is released, so the daemon never finds it gone without one
+    if let Some(raw) = exit::wait(&mut child)
+        && let Some(exit_file) = exit_file
+    {
+        let _ = fs::write(exit_file, format!("{raw}\n"));
+    }
+
+    let _ = child.wait();
+    unsafe { libc::_exit(0) }
+}
+
+/// Drop the signal handlers inherited from the spawner, a supervisor forked from the daemon would
+/// otherwise run the daemon's termination handler and remove its pid file when it gets SIGTERM
+fn reset_signals() {
+    let default = SigAction::new(SigHandler::SigDfl, SaFlags::empty(), SigSet::empty());
+
+    for signal in
+        Signal::iterator().filter(|signal| !matches!(signal, Signal::SIGKILL | Signal::SIGSTOP))
+    {
+        let _ = unsafe { signal::sigaction(signal, &default) };
+    }
+
+    let _ = signal::sigprocmask(SigmaskHow::SIG_SETMASK, Some(&SigSet::empty()), None);
+}
+
+/// Let go of the descriptors inherited from the spawner, like its terminal or the sockets of the daemon
+fn detach() {
+    let fds = fs::read_dir("/dev/fd")
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<i32>().ok())
+        .collect::<Vec<i32>>();
+
+    unsafe {
+        let null = libc::open(c"/dev/null".as_ptr(), libc::O_RDWR);
+
+        for fd in fds {
+            match fd {
+                0..=2 if null >= 0 => libc::dup2(null, fd),
+                _ if fd != null => libc::close(fd),
+                _ => 0,
+            };
+        }
+
+        if null > 2 {
+            libc::close(null);
+        }
+    }
+}
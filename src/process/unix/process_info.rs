@@ -218,10 +218,8 @@ mod tests {
     #[test]
     fn test_get_parent_pid_init() {
         // Test with init process (PID 1) - should have no parent or parent 0
-        if let Ok(parent) = get_parent_pid(1) {
-            if let Some(ppid) = parent {
-                assert_eq!(ppid, 0);
-            }
+        if let Ok(Some(ppid)) = get_parent_pid(1) {
+            assert_eq!(ppid, 0);
         }
     }
 }
@@ -0,0 +1,247 @@
+use core::fmt;
+use nix::unistd::{self, Gid, Group, Uid, User};
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{self, Visitor},
+};
+use std::{
+    ffi::{CStr, CString},
+    path::PathBuf,
+    str::FromStr,
+};
+
+/// File mode creation mask applied before exec, written in octal like `022`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Umask(pub u32);
+
+impl FromStr for Umask {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = s.trim();
+        let digits = value.strip_prefix("0o").unwrap_or(value);
+
+        match u32::from_str_radix(digits, 8) {
+            Ok(mask) if mask <= 0o777 => Ok(Umask(mask)),
+            _ => Err(format!(
+                "invalid umask '{s}', expected an octal mode like 022"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Umask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04o}", self.0)
+    }
+}
+
+impl Serialize for Umask {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Umask {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct UmaskVisitor;
+
+        impl<'de> Visitor<'de> for UmaskVisitor {
+            type Value = Umask;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an octal mode like \"022\"")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Umask, E> {
+                Umask::from_str(value).map_err(E::custom)
+            }
+
+            // a bare number like 22 is read as its octal digits
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<Umask, E> {
+                Umask::from_str(&value.to_string()).map_err(E::custom)
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<Umask, E> {
+                Umask::from_str(&value.to_string()).map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(UmaskVisitor)
+    }
+}
+
+/// User and group a process runs as after the privileges are dropped
+#[derive(Clone, Debug)]
+pub struct Credentials {
+    pub uid: Uid,
+    pub gid: Gid,
+    /// Name of the user, unset for ids without a passwd entry
+    pub name: Option<CString>,
+    pub home: Option<PathBuf>,
+    /// Supplementary groups, looked up here since reading /etc/group is not safe between fork and exec
+    pub groups: Vec<Gid>,
+}
+
+fn find_user(user: &str) -> Result<User, String> {
+    let found = match user.parse::<u32>() {
+        Ok(uid) => User::from_uid(Uid::from_raw(uid)),
+        Err(_) => User::from_name(user),
+    };
+
+    found
+        .map_err(|err| format!("failed to look up user '{user}': {err}"))?
+        .ok_or_else(|| format!("user '{user}' does not exist"))
+}
+
+fn find_group(group: &str) -> Result<Gid, String> {
+    if let Ok(gid) = group.parse::<u32>() {
+        return Ok(Gid::from_raw(gid));
+    }
+
+    Group::from_name(group)
+        .map_err(|err| format!("failed to look up group '{group}': {err}"))?
+        .map(|group| group.gid)
+        .ok_or_else(|| format!("group '{group}' does not exist"))
+}
+
+/// Groups of the user `name` including `gid`, like initgroups would set them
+#[cfg(not(target_os = "macos"))]
+fn group_list(name: &CStr, gid: Gid) -> Result<Vec<Gid>, String> {
+    unistd::getgrouplist(name, gid).map_err(|err| {
+        format!(
+            "failed to look up the groups of '{}': {err}",
+            name.to_string_lossy()
+        )
+    })
+}
+
+/// Groups of the user `name` including `gid`, like initgroups would set them
+#[cfg(target_os = "macos")]
+fn group_list(name: &CStr, gid: Gid) -> Result<Vec<Gid>, String> {
+    let mut size: libc::c_int = 64;
+
+    // macos does not report the needed size, grow until the list fits
+    while size <= 1 << 16 {
+        let mut groups = vec![0; size as usize];
+        let mut count = size;
+
+        if unsafe {
+            libc::getgrouplist(
+                name.as_ptr(),
+                gid.as_raw() as _,
+                groups.as_mut_ptr(),
+                &mut count,
+            )
+        } >= 0
+        {
+            groups.truncate(count as usize);
+            return Ok(groups
+                .into_iter()
+                .map(|gid| Gid::from_raw(gid as _))
+                .collect());
+        }
+
+        size *= 2;
+    }
+
+    Err(format!(
+        "failed to look up the groups of '{}'",
+        name.to_string_lossy()
+    ))
+}
+
+/// Check that the current process is allowed to switch to `credentials`
+fn check_permission(credentials: &Credentials) -> Result<(), String> {
+    let euid = unistd::geteuid();
+
+    if euid.is_root() {
+        return Ok(());
+    }
+
+    let in_group = credentials.gid == unistd::getegid()
+        || unistd::getgroups().is_ok_and(|groups| groups.contains(&credentials.gid));
+
+    match credentials.uid == euid && in_group {
+        true => Ok(()),
+        false => Err(format!(
+            "the daemon runs as uid {euid} and lacks permission to switch to uid {} gid {}",
+            credentials.uid, credentials.gid
+        )),
+    }
+}
+
+/// Resolve the user and group a process should run as, the group defaults to the primary group of the user
+pub fn resolve(
+    user: &Option<String>,
+    group: &Option<String>,
+) -> Result<Option<Credentials>, String> {
+    let credentials = match (user, group) {
+        (None, None) => return Ok(None),
+        (Some(user), group) => {
+            let found = find_user(user)?;
+            let gid = match group {
+                Some(group) => find_group(group)?,
+                None => found.gid,
+            };
+            let name = CString::new(found.name).ok();
+
+            Credentials {
+                uid: found.uid,
+                gid,
+                groups: match &name {
+                    Some(name) => group_list(name, gid)?,
+                    None => vec![gid],
+                },
+                name,
+                home: Some(found.dir),
+            }
+        }
+        (None, Some(group)) => {
+            let gid = find_group(group)?;
+            Credentials {
+                uid: unistd::geteuid(),
+                gid,
+                name: None,
+                home: None,
+                groups: vec![gid],
+            }
+        }
+    };
+
+    check_permission(&credentials)?;
+    Ok(Some(credentials))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_umask() {
+        assert_eq!("022".parse::<Umask>(), Ok(Umask(0o022)));
+        assert_eq!("0o077".parse::<Umask>(), Ok(Umask(0o077)));
+        assert_eq!(Umask(0o027).to_string(), "0027");
+        assert!("089".parse::<Umask>().is_err());
+        assert!("1777".parse::<Umask>().is_err());
+    }
+
+    #[test]
+    fn test_resolve_credentials() {
+        assert!(resolve(&None, &None).unwrap().is_none());
+        assert!(resolve(&Some(String::from("pmc-no-such-user")), &None).is_err());
+        assert!(resolve(&None, &Some(String::from("pmc-no-such-group"))).is_err());
+    }
+
+    #[test]
+    fn test_resolve_groups() {
+        let uid = unistd::geteuid();
+        let Ok(Some(user)) = User::from_uid(uid) else {
+            return;
+        };
+
+        let credentials = resolve(&Some(uid.to_string()), &None).unwrap().unwrap();
+        assert_eq!(credentials.gid, user.gid);
+        assert!(credentials.groups.contains(&user.gid));
+    }
+}